@@ -0,0 +1,154 @@
+//! Search throughput benchmarks comparing the byte-level literal fast path with the
+//! decode + regex path.
+//!
+//! Runs over every file in `test_files/` (see `scripts/generate_test_files.sh`) plus a
+//! synthetic log file. Set `LTV_BENCH_SYNTHETIC_MB` to control the synthetic file size,
+//! e.g. `LTV_BENCH_SYNTHETIC_MB=4096 cargo bench -p large-text-core` for a 4 GB input.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use large_text_core::file_reader::{detect_encoding, FileReader};
+use large_text_core::query::SearchOptions;
+use large_text_core::search_engine::{SearchEngine, SearchMessage, SearchType};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{atomic::AtomicBool, mpsc, Arc};
+use tempfile::NamedTempFile;
+
+const DEFAULT_SYNTHETIC_MB: usize = 256;
+
+fn count(engine: &SearchEngine, reader: &Arc<FileReader>) -> usize {
+    let (tx, rx) = mpsc::sync_channel(1024);
+    engine.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+
+    let mut total = 0;
+    while let Ok(msg) = rx.recv() {
+        match msg {
+            SearchMessage::CountResult(c) => total += c,
+            SearchMessage::Done(SearchType::Count) => break,
+            SearchMessage::Error(e) => panic!("search failed: {}", e),
+            _ => {}
+        }
+    }
+    total
+}
+
+fn synthetic_file(size_mb: usize) -> NamedTempFile {
+    let file = NamedTempFile::new().expect("create temp file");
+    let mut writer = BufWriter::new(file.reopen().expect("reopen temp file"));
+    let target = size_mb * 1024 * 1024;
+    let mut written = 0;
+    let mut i: usize = 0;
+    while written < target {
+        let line = if i.is_multiple_of(97) {
+            format!(
+                "2024-01-01T00:00:{:02} WARN  request {} failed: upstream timeout after 30s\n",
+                i % 60,
+                i
+            )
+        } else {
+            format!(
+                "2024-01-01T00:00:{:02} INFO  request {} served in {}ms status=200\n",
+                i % 60,
+                i,
+                i % 500
+            )
+        };
+        writer
+            .write_all(line.as_bytes())
+            .expect("write synthetic data");
+        written += line.len();
+        i += 1;
+    }
+    writer.flush().expect("flush synthetic data");
+    file
+}
+
+fn test_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test_files");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.metadata().map(|m| m.len() > 0).unwrap_or(false))
+        .collect();
+    files.sort();
+    files
+}
+
+fn bench_search(c: &mut Criterion) {
+    let synthetic_mb = std::env::var("LTV_BENCH_SYNTHETIC_MB")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SYNTHETIC_MB);
+    let synthetic = synthetic_file(synthetic_mb);
+
+    let mut inputs: Vec<(String, PathBuf)> = test_files()
+        .into_iter()
+        .map(|p| (p.file_name().unwrap().to_string_lossy().into_owned(), p))
+        .collect();
+    inputs.push((
+        format!("synthetic_{}MB", synthetic_mb),
+        synthetic.path().to_path_buf(),
+    ));
+
+    // (name, query, options) - the `regex_*` variants force the decode + regex path
+    // for the same literal so the two pipelines can be compared directly.
+    let cases = [
+        (
+            "literal_case",
+            "timeout",
+            SearchOptions {
+                case_sensitive: true,
+                ..Default::default()
+            },
+        ),
+        ("literal_nocase", "timeout", SearchOptions::default()),
+        (
+            "regex_case",
+            "timeout",
+            SearchOptions {
+                use_regex: true,
+                case_sensitive: true,
+                ..Default::default()
+            },
+        ),
+        (
+            "regex_nocase",
+            "timeout",
+            SearchOptions {
+                use_regex: true,
+                ..Default::default()
+            },
+        ),
+    ];
+
+    let mut group = c.benchmark_group("count_matches");
+    group.sample_size(10);
+
+    for (input_name, path) in &inputs {
+        let mut sample = [0u8; 4096];
+        let n = std::fs::File::open(path)
+            .and_then(|mut f| f.read(&mut sample))
+            .unwrap_or(0);
+        let reader = Arc::new(
+            FileReader::new(path.clone(), detect_encoding(&sample[..n])).expect("open input"),
+        );
+        group.throughput(Throughput::Bytes(reader.len() as u64));
+
+        for (case_name, query, options) in &cases {
+            let mut engine = SearchEngine::new();
+            engine.set_query_with_options(query.to_string(), *options);
+            group.bench_with_input(
+                BenchmarkId::new(*case_name, input_name),
+                &reader,
+                |b, reader| b.iter(|| count(&engine, reader)),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -0,0 +1,395 @@
+//! Caret movement over the raw bytes of a file. Positions are byte offsets, so a
+//! selection can span any number of lines without resolving line numbers, which
+//! the sparse line index only estimates.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::ops::Range;
+
+use crate::file_reader::FileReader;
+
+/// Bytes scanned at a time when looking for line and word boundaries.
+const SCAN_CHUNK: usize = 64 * 1024;
+
+/// A caret plus the anchor where the selection started; empty when both coincide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: usize,
+    pub caret: usize,
+}
+
+impl Selection {
+    pub fn new(offset: usize) -> Self {
+        Self {
+            anchor: offset,
+            caret: offset,
+        }
+    }
+
+    /// Moves the caret to `offset`, keeping the anchor when `extend` is set.
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.caret)..self.anchor.max(self.caret)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.caret
+    }
+}
+
+/// Length in bytes of the character at the start of `bytes`. An invalid sequence
+/// counts as one character, like the U+FFFD the decoder emits for it.
+fn char_len(encoding: &'static Encoding, bytes: &[u8]) -> usize {
+    if bytes.is_empty() {
+        return 0;
+    }
+    if encoding == UTF_8 {
+        let head = &bytes[..bytes.len().min(4)];
+        match head.utf8_chunks().next() {
+            Some(chunk) => match chunk.valid().chars().next() {
+                Some(c) => c.len_utf8(),
+                None => chunk.invalid().len().max(1),
+            },
+            None => 1,
+        }
+    } else if encoding == UTF_16LE || encoding == UTF_16BE {
+        if bytes.len() < 2 {
+            return bytes.len();
+        }
+        let unit = |b: &[u8]| {
+            if encoding == UTF_16LE {
+                u16::from_le_bytes([b[0], b[1]])
+            } else {
+                u16::from_be_bytes([b[0], b[1]])
+            }
+        };
+        let surrogate_pair = (0xD800..0xDC00).contains(&unit(bytes))
+            && bytes.len() >= 4
+            && (0xDC00..0xE000).contains(&unit(&bytes[2..]));
+        if surrogate_pair {
+            4
+        } else {
+            2
+        }
+    } else {
+        1
+    }
+}
+
+/// Offset of each character in `bytes`, relative to its start, followed by `bytes.len()`.
+/// There is one entry per character the decoder produces for `bytes`.
+pub fn char_offsets(encoding: &'static Encoding, bytes: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        offsets.push(i);
+        i += char_len(encoding, &bytes[i..]);
+    }
+    offsets.push(bytes.len());
+    offsets
+}
+
+/// The last character boundary at or before `index` in `bytes`, found by walking
+/// from the start, so it also works for encodings that cannot resync backwards.
+pub fn floor_char_boundary(encoding: &'static Encoding, bytes: &[u8], index: usize) -> usize {
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = i + char_len(encoding, &bytes[i..]);
+        if next > index {
+            return i;
+        }
+        i = next;
+    }
+    bytes.len()
+}
+
+pub fn next_char(reader: &FileReader, offset: usize) -> usize {
+    let bytes = reader.get_bytes(offset, offset + 4);
+    (offset + char_len(reader.encoding(), bytes)).min(reader.len())
+}
+
+pub fn prev_char(reader: &FileReader, offset: usize) -> usize {
+    if offset == 0 {
+        return 0;
+    }
+    let encoding = reader.encoding();
+    let start = if encoding == UTF_8 {
+        // Back up over continuation bytes to the lead byte
+        let mut start = offset - 1;
+        while start > 0
+            && offset - start < 4
+            && reader.get_bytes(start, start + 1)[0] & 0xC0 == 0x80
+        {
+            start -= 1;
+        }
+        start
+    } else if encoding == UTF_16LE || encoding == UTF_16BE {
+        let start = offset.saturating_sub(2);
+        if start >= 2 && char_len(encoding, reader.get_bytes(start - 2, offset)) == 4 {
+            start - 2
+        } else {
+            start
+        }
+    } else {
+        offset - 1
+    };
+    if start + char_len(encoding, reader.get_bytes(start, offset)) == offset {
+        start
+    } else {
+        offset - 1
+    }
+}
+
+/// Start of the line containing `offset`.
+pub fn line_start(reader: &FileReader, offset: usize) -> usize {
+    let mut end = offset.min(reader.len());
+    while end > 0 {
+        let start = end.saturating_sub(SCAN_CHUNK);
+        if let Some(pos) = memchr::memrchr(b'\n', reader.get_bytes(start, end)) {
+            return start + pos + 1;
+        }
+        end = start;
+    }
+    0
+}
+
+/// End of the line containing `offset`, before its `\n` or `\r\n`.
+pub fn line_end(reader: &FileReader, offset: usize) -> usize {
+    let mut start = offset;
+    while start < reader.len() {
+        let chunk = reader.get_bytes(start, start + SCAN_CHUNK);
+        if let Some(pos) = memchr::memchr(b'\n', chunk) {
+            let end = start + pos;
+            return if end > offset && reader.get_bytes(end - 1, end) == b"\r" {
+                end - 1
+            } else {
+                end
+            };
+        }
+        start += chunk.len();
+    }
+    reader.len()
+}
+
+/// Start of the line after the one containing `offset`, or the file length on the last line.
+pub fn next_line_start(reader: &FileReader, offset: usize) -> usize {
+    let mut start = offset;
+    while start < reader.len() {
+        let chunk = reader.get_bytes(start, start + SCAN_CHUNK);
+        if let Some(pos) = memchr::memchr(b'\n', chunk) {
+            return start + pos + 1;
+        }
+        start += chunk.len();
+    }
+    reader.len()
+}
+
+/// One character right, stepping over the line break as a whole at the end of a line.
+pub fn next_position(reader: &FileReader, offset: usize) -> usize {
+    if offset >= line_end(reader, offset) {
+        next_line_start(reader, offset)
+    } else {
+        next_char(reader, offset)
+    }
+}
+
+/// One character left, stepping over the line break as a whole at the start of a line.
+pub fn prev_position(reader: &FileReader, offset: usize) -> usize {
+    let start = line_start(reader, offset);
+    if offset == start && offset > 0 {
+        line_end(reader, line_start(reader, offset - 1))
+    } else {
+        prev_char(reader, offset)
+    }
+}
+
+/// Number of characters between `line_start` and `offset`.
+pub fn column(reader: &FileReader, line_start: usize, offset: usize) -> usize {
+    let bytes = reader.get_bytes(line_start, offset);
+    let encoding = reader.encoding();
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        i += char_len(encoding, &bytes[i..]);
+        count += 1;
+    }
+    count
+}
+
+/// Offset of the character `column` characters into the line starting at `line_start`,
+/// or the line's end if it is shorter.
+pub fn offset_at_column(reader: &FileReader, line_start: usize, column: usize) -> usize {
+    let end = line_end(reader, line_start);
+    let mut offset = line_start;
+    for _ in 0..column {
+        if offset >= end {
+            break;
+        }
+        offset = next_char(reader, offset);
+    }
+    offset.min(end)
+}
+
+/// The position `column` characters into the previous line, or the file start on the first line.
+pub fn line_up(reader: &FileReader, offset: usize, column: usize) -> usize {
+    let start = line_start(reader, offset);
+    if start == 0 {
+        return 0;
+    }
+    offset_at_column(reader, line_start(reader, start - 1), column)
+}
+
+/// The position `column` characters into the next line, or the file end on the last line.
+pub fn line_down(reader: &FileReader, offset: usize, column: usize) -> usize {
+    let next = next_line_start(reader, offset);
+    if next >= reader.len() {
+        return reader.len();
+    }
+    offset_at_column(reader, next, column)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Characters of `reader[start..end]` paired with their absolute offsets.
+fn chars_with_offsets(reader: &FileReader, start: usize, end: usize) -> Vec<(usize, char)> {
+    let encoding = reader.encoding();
+    let bytes = reader.get_bytes(start, end);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    char_offsets(encoding, bytes)
+        .into_iter()
+        .map(|o| start + o)
+        .zip(text.chars())
+        .collect()
+}
+
+/// End of the word at or after `offset`, not crossing the end of the line.
+pub fn next_word(reader: &FileReader, offset: usize) -> usize {
+    let end = line_end(reader, offset);
+    if offset >= end {
+        return next_position(reader, offset);
+    }
+    let chars = chars_with_offsets(reader, offset, end.min(offset + SCAN_CHUNK));
+    chars
+        .iter()
+        .skip_while(|(_, c)| !is_word_char(*c))
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(end.min(offset + SCAN_CHUNK), |(o, _)| *o)
+}
+
+/// Start of the word at or before `offset`, not crossing the start of the line.
+pub fn prev_word(reader: &FileReader, offset: usize) -> usize {
+    let start = line_start(reader, offset);
+    if offset <= start {
+        return prev_position(reader, offset);
+    }
+    let mut window_start = start.max(offset.saturating_sub(SCAN_CHUNK));
+    if (offset - window_start) % 2 == 1 && reader.encoding() != UTF_8 {
+        // Keep two-byte code units aligned
+        window_start += 1;
+    }
+    let chars = chars_with_offsets(reader, window_start, offset);
+    chars
+        .iter()
+        .rev()
+        .skip_while(|(_, c)| !is_word_char(*c))
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(window_start, |(o, _)| *o)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn reader(bytes: &[u8], encoding: &'static Encoding) -> Result<(NamedTempFile, FileReader)> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(bytes)?;
+        let reader = FileReader::new(file.path().to_path_buf(), encoding)?;
+        Ok((file, reader))
+    }
+
+    #[test]
+    fn test_line_boundaries_and_crlf() -> Result<()> {
+        let (_file, r) = reader(b"ab\r\ncd\nlast", UTF_8)?;
+        assert_eq!(line_start(&r, 5), 4);
+        assert_eq!(line_end(&r, 0), 2);
+        assert_eq!(line_end(&r, 4), 6);
+        assert_eq!(line_end(&r, 8), 11);
+        assert_eq!(next_line_start(&r, 0), 4);
+
+        // The line break is stepped over as one position
+        assert_eq!(next_position(&r, 2), 4);
+        assert_eq!(prev_position(&r, 4), 2);
+        assert_eq!(next_position(&r, 11), 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_char_stepping_utf8_and_utf16() -> Result<()> {
+        let (_file, r) = reader("aé€😀\n".as_bytes(), UTF_8)?;
+        assert_eq!(next_char(&r, 0), 1);
+        assert_eq!(next_char(&r, 1), 3);
+        assert_eq!(next_char(&r, 3), 6);
+        assert_eq!(next_char(&r, 6), 10);
+        assert_eq!(prev_char(&r, 10), 6);
+        assert_eq!(prev_char(&r, 6), 3);
+        assert_eq!(prev_char(&r, 3), 1);
+        assert_eq!(char_offsets(UTF_8, b"a\xFFb"), vec![0, 1, 2, 3]);
+        assert_eq!(floor_char_boundary(UTF_8, "aé€".as_bytes(), 2), 1);
+        assert_eq!(floor_char_boundary(UTF_8, "aé€".as_bytes(), 3), 3);
+        assert_eq!(floor_char_boundary(UTF_8, "aé€".as_bytes(), 100), 6);
+
+        let utf16: Vec<u8> = "a😀b".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let (_file, r) = reader(&utf16, UTF_16LE)?;
+        assert_eq!(next_char(&r, 0), 2);
+        assert_eq!(next_char(&r, 2), 6);
+        assert_eq!(prev_char(&r, 6), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vertical_movement_keeps_column() -> Result<()> {
+        let (_file, r) = reader(b"hello\nhi\nworld", UTF_8)?;
+        let col = column(&r, 0, 4);
+        assert_eq!(col, 4);
+        // Clamped to the end of the shorter line, then back to the column
+        assert_eq!(line_down(&r, 4, col), 8);
+        assert_eq!(line_down(&r, 8, col), 13);
+        assert_eq!(line_up(&r, 13, col), 8);
+        assert_eq!(line_up(&r, 2, col), 0);
+        assert_eq!(line_down(&r, 10, col), r.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_word_movement() -> Result<()> {
+        let (_file, r) = reader(b"foo bar_baz, qux\nnext", UTF_8)?;
+        assert_eq!(next_word(&r, 0), 3);
+        assert_eq!(next_word(&r, 3), 11);
+        assert_eq!(next_word(&r, 13), 16);
+        assert_eq!(next_word(&r, 16), 17);
+        assert_eq!(prev_word(&r, 16), 13);
+        assert_eq!(prev_word(&r, 13), 4);
+        assert_eq!(prev_word(&r, 17), 16);
+        Ok(())
+    }
+
+    #[test]
+    fn test_selection_range() {
+        let mut selection = Selection::new(10);
+        selection.move_to(4, true);
+        assert_eq!(selection.range(), 4..10);
+        selection.move_to(6, false);
+        assert!(selection.is_empty());
+    }
+}
@@ -0,0 +1,613 @@
+use anyhow::Result;
+use encoding_rs::UTF_8;
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{sync_channel, Sender},
+    Arc,
+};
+
+use crate::cursor;
+use crate::file_reader::FileReader;
+use crate::query::build_pattern;
+use crate::search_engine::{SearchEngine, SearchMessage, SearchResult};
+
+/// Bytes copied per step, between progress reports and cancellation checks.
+const EXPORT_CHUNK: usize = 4 * 1024 * 1024;
+/// Matches requested per `fetch_matches` call while exporting matches.
+const MATCH_PAGE: usize = 10_000;
+
+/// What `export_matches` writes for each match.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MatchExportMode {
+    /// Every matching line, like `grep`.
+    #[default]
+    Lines,
+    /// Only the matched text, one match per line, like `grep -o`.
+    MatchedText,
+    /// The regex capture groups of each match, tab-separated.
+    CaptureGroups,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchExportOptions {
+    pub mode: MatchExportMode,
+    /// Lines written before and after each matching line (`Lines` mode only).
+    pub context: usize,
+    /// Prefix output with the 1-based line number: `N:` on matching lines, `N-` on context.
+    pub line_numbers: bool,
+    pub to_utf8: bool,
+}
+
+pub enum ExportMessage {
+    Progress(usize, usize), // bytes_processed, total_bytes
+    Done(usize),            // bytes_written
+    Error(String),
+}
+
+/// Writes `range` of the file to `writer` a chunk at a time straight from the mmap,
+/// decoding it and writing UTF-8 when `to_utf8` is set. Stops early once
+/// `cancel_token` is set. Returns the number of bytes written.
+pub fn write_range<W: Write>(
+    reader: &FileReader,
+    range: Range<usize>,
+    to_utf8: bool,
+    writer: &mut W,
+    cancel_token: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> Result<usize> {
+    let start = range.start;
+    let written = copy_bytes(reader, range, to_utf8, writer, cancel_token, |offset| {
+        progress(offset - start)
+    })?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// The chunked copy behind `write_range`, reporting the absolute offset reached and
+/// leaving the writer unflushed.
+fn copy_bytes<W: Write>(
+    reader: &FileReader,
+    range: Range<usize>,
+    to_utf8: bool,
+    writer: &mut W,
+    cancel_token: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> Result<usize> {
+    let end = range.end.min(reader.len());
+    let start = range.start.min(end);
+    let transcode = to_utf8 && reader.encoding() != UTF_8;
+    let mut decoder = reader.encoding().new_decoder_without_bom_handling();
+    let mut text = String::new();
+    let mut written = 0;
+
+    let mut offset = start;
+    while offset < end {
+        if cancel_token.load(Ordering::Relaxed) {
+            break;
+        }
+        let chunk_end = (offset + EXPORT_CHUNK).min(end);
+        let bytes = reader.get_bytes(offset, chunk_end);
+        if transcode {
+            text.clear();
+            if let Some(needed) = decoder.max_utf8_buffer_length(bytes.len()) {
+                text.reserve(needed);
+            }
+            let _ = decoder.decode_to_string(bytes, &mut text, chunk_end == end);
+            writer.write_all(text.as_bytes())?;
+            written += text.len();
+        } else {
+            writer.write_all(bytes)?;
+            written += bytes.len();
+        }
+        offset = chunk_end;
+        progress(offset);
+    }
+    Ok(written)
+}
+
+/// Exports `range` of the file to `output_path`, reporting progress over `tx`.
+/// A cancelled export removes the partial output.
+pub fn export_range(
+    reader: &FileReader,
+    range: Range<usize>,
+    output_path: &Path,
+    to_utf8: bool,
+    tx: Sender<ExportMessage>,
+    cancel_token: Arc<AtomicBool>,
+) {
+    let total = range.len();
+    let result = create_output(reader, output_path).and_then(|mut writer| {
+        write_range(reader, range, to_utf8, &mut writer, &cancel_token, |done| {
+            let _ = tx.send(ExportMessage::Progress(done, total));
+        })
+    });
+    finish(result, output_path, &tx, &cancel_token);
+}
+
+/// Writes every match of `engine`'s query (within its range) to `writer`, paging
+/// through `SearchEngine::fetch_matches` so only one page of results is held at a
+/// time. Progress is reported as the offset reached within the searched range.
+/// Returns the number of bytes written.
+pub fn write_matches<W: Write>(
+    reader: &Arc<FileReader>,
+    engine: &SearchEngine,
+    options: MatchExportOptions,
+    writer: &mut W,
+    cancel_token: &Arc<AtomicBool>,
+    mut progress: impl FnMut(usize),
+) -> Result<usize> {
+    let captures = match options.mode {
+        MatchExportMode::CaptureGroups => capture_regexes(engine)?,
+        _ => Vec::new(),
+    };
+    let range_start = engine.range().map_or(0, |r| r.start);
+    let mut out = MatchWriter {
+        reader,
+        options,
+        writer,
+        cancel_token,
+        written: 0,
+        written_until: 0,
+        pending_after: 0..0,
+        counted: (0, 0),
+    };
+
+    let mut start_offset = range_start;
+    loop {
+        // A fresh channel per page: a cancelled fetch exits without `Done`, and
+        // dropping its sender is what ends the loop below
+        let (tx, rx) = sync_channel(16);
+        engine.fetch_matches(
+            reader.clone(),
+            tx,
+            start_offset,
+            MATCH_PAGE,
+            cancel_token.clone(),
+        );
+
+        let mut fetched = 0;
+        let mut last: Option<SearchResult> = None;
+        for message in rx {
+            match message {
+                SearchMessage::ChunkResult(chunk) => {
+                    for m in chunk.matches {
+                        out.write_match(&m, &captures)?;
+                        progress(m.byte_offset.saturating_sub(range_start));
+                        last = Some(m);
+                        fetched += 1;
+                    }
+                }
+                SearchMessage::Error(e) => anyhow::bail!(e),
+                SearchMessage::Done(_) => break,
+                _ => {}
+            }
+        }
+
+        match last {
+            Some(m) if fetched == MATCH_PAGE && !cancel_token.load(Ordering::Relaxed) => {
+                start_offset = m.byte_offset + m.match_len.max(1);
+            }
+            _ => break,
+        }
+    }
+
+    out.flush_pending()?;
+    out.writer.flush()?;
+    Ok(out.written)
+}
+
+/// Exports the matches of `engine`'s query to `output_path`, reporting progress
+/// over `tx`. A cancelled export removes the partial output.
+pub fn export_matches(
+    reader: Arc<FileReader>,
+    engine: &SearchEngine,
+    options: MatchExportOptions,
+    output_path: &Path,
+    tx: Sender<ExportMessage>,
+    cancel_token: Arc<AtomicBool>,
+) {
+    let total = engine.range().map_or(reader.len(), |r| {
+        r.end.min(reader.len()) - r.start.min(reader.len())
+    });
+    let result = create_output(&reader, output_path).and_then(|mut writer| {
+        write_matches(
+            &reader,
+            engine,
+            options,
+            &mut writer,
+            &cancel_token,
+            |done| {
+                let _ = tx.send(ExportMessage::Progress(done, total));
+            },
+        )
+    });
+    finish(result, output_path, &tx, &cancel_token);
+}
+
+/// One regex per search pattern, for pulling capture groups out of matched text.
+fn capture_regexes(engine: &SearchEngine) -> Result<Vec<Regex>> {
+    let options = engine.options();
+    if !options.use_regex || options.max_edits.is_some() {
+        anyhow::bail!("Capture groups need a regex search");
+    }
+    engine
+        .patterns()
+        .iter()
+        .map(|p| Ok(Regex::new(&build_pattern(p, &options))?))
+        .collect()
+}
+
+/// Streams the output of `write_matches`, remembering how far into the file it has
+/// written so overlapping context is not repeated.
+struct MatchWriter<'a, W: Write> {
+    reader: &'a FileReader,
+    options: MatchExportOptions,
+    writer: &'a mut W,
+    cancel_token: &'a AtomicBool,
+    written: usize,
+    /// File offset up to which lines have been written.
+    written_until: usize,
+    /// Trailing context of the last match, written once the next match is known.
+    pending_after: Range<usize>,
+    /// (offset, 0-based line number) of the last line-number lookup.
+    counted: (usize, usize),
+}
+
+impl<W: Write> MatchWriter<'_, W> {
+    fn write_match(&mut self, m: &SearchResult, captures: &[Regex]) -> Result<()> {
+        let start = m.byte_offset;
+        let end = start + m.match_len;
+        match self.options.mode {
+            MatchExportMode::Lines => self.write_match_lines(start, end),
+            MatchExportMode::MatchedText => {
+                let text = self.reader.get_chunk(start, end);
+                self.write_record(start, &text)
+            }
+            MatchExportMode::CaptureGroups => {
+                let text = self.reader.get_chunk(start, end);
+                let record = match captures
+                    .get(m.pattern_index)
+                    .and_then(|re| re.captures(&text))
+                {
+                    Some(caps) if caps.len() > 1 => caps
+                        .iter()
+                        .skip(1)
+                        .map(|g| g.map_or("", |g| g.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("\t"),
+                    _ => text,
+                };
+                self.write_record(start, &record)
+            }
+        }
+    }
+
+    /// Writes one `grep -o` style record: the optional line number, `text`, and a newline.
+    fn write_record(&mut self, offset: usize, text: &str) -> Result<()> {
+        if self.options.line_numbers {
+            let prefix = format!("{}:", self.line_number(offset) + 1);
+            self.write(prefix.as_bytes())?;
+        }
+        // Matched text is decoded already, so records are always UTF-8
+        self.write(text.as_bytes())?;
+        self.write(b"\n")
+    }
+
+    fn write_match_lines(&mut self, start: usize, end: usize) -> Result<()> {
+        let reader = self.reader;
+        let first = cursor::line_start(reader, start);
+        let last_end = cursor::next_line_start(reader, end.saturating_sub(1).max(first));
+        let mut before = first;
+        for _ in 0..self.options.context {
+            if before == 0 {
+                break;
+            }
+            before = cursor::line_start(reader, before - 1);
+        }
+        let mut after = last_end;
+        for _ in 0..self.options.context {
+            if after >= reader.len() {
+                break;
+            }
+            after = cursor::next_line_start(reader, after);
+        }
+
+        // Trailing context of the previous match stops where this match's lines begin
+        let pending = self.pending_after.start..self.pending_after.end.min(first);
+        self.write_lines(pending, '-')?;
+        self.pending_after = 0..0;
+
+        if self.options.context > 0 && self.written > 0 && before > self.written_until {
+            self.write(b"--\n")?;
+        }
+        self.write_lines(before.max(self.written_until)..first, '-')?;
+        self.write_lines(first.max(self.written_until)..last_end, ':')?;
+        self.pending_after = self.written_until..after;
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        let pending = std::mem::replace(&mut self.pending_after, 0..0);
+        self.write_lines(pending, '-')
+    }
+
+    /// Copies whole lines in `range` from the file, each prefixed with its line number
+    /// and `separator` when line numbers are on.
+    fn write_lines(&mut self, range: Range<usize>, separator: char) -> Result<()> {
+        if range.start >= range.end {
+            return Ok(());
+        }
+        let mut line = range.start;
+        while line < range.end && !self.cancel_token.load(Ordering::Relaxed) {
+            let line_end = if self.options.line_numbers {
+                let prefix = format!("{}{}", self.line_number(line) + 1, separator);
+                self.write(prefix.as_bytes())?;
+                cursor::next_line_start(self.reader, line).min(range.end)
+            } else {
+                range.end
+            };
+            self.written += copy_bytes(
+                self.reader,
+                line..line_end,
+                self.options.to_utf8,
+                self.writer,
+                self.cancel_token,
+                |_| {},
+            )?;
+            line = line_end;
+        }
+        // The last line of the file may have no line break of its own. The check decodes
+        // the last character, which spans several bytes in UTF-16.
+        let tail = range.end.saturating_sub(4).max(range.start);
+        if !self.reader.get_chunk(tail, range.end).ends_with('\n') {
+            self.write(b"\n")?;
+        }
+        self.written_until = range.end;
+        Ok(())
+    }
+
+    /// 0-based line number of `offset`, counting line breaks forward from the previous
+    /// lookup. Offsets only grow during an export, so the file is scanned once.
+    fn line_number(&mut self, offset: usize) -> usize {
+        let (from, line) = self.counted;
+        let line = if offset >= from {
+            line + memchr::memchr_iter(b'\n', self.reader.get_bytes(from, offset)).count()
+        } else {
+            memchr::memchr_iter(b'\n', self.reader.get_bytes(0, offset)).count()
+        };
+        self.counted = (offset, line);
+        line
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+}
+
+/// Opens the export target, refusing the file being read: truncating it would pull
+/// the data out from under the memory map.
+fn create_output(reader: &FileReader, output_path: &Path) -> Result<BufWriter<File>> {
+    if let (Ok(input), Ok(output)) = (
+        std::fs::canonicalize(reader.path()),
+        std::fs::canonicalize(output_path),
+    ) {
+        if input == output {
+            anyhow::bail!("Cannot export to the file being viewed");
+        }
+    }
+    Ok(BufWriter::new(File::create(output_path)?))
+}
+
+fn finish(
+    result: Result<usize>,
+    output_path: &Path,
+    tx: &Sender<ExportMessage>,
+    cancel_token: &AtomicBool,
+) {
+    let message = match result {
+        Ok(_) if cancel_token.load(Ordering::Relaxed) => {
+            let _ = std::fs::remove_file(output_path);
+            ExportMessage::Error("Export cancelled".to_string())
+        }
+        Ok(written) => ExportMessage::Done(written),
+        Err(e) => ExportMessage::Error(e.to_string()),
+    };
+    let _ = tx.send(message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::UTF_16LE;
+    use std::sync::mpsc::channel;
+    use tempfile::{tempdir, NamedTempFile};
+
+    #[test]
+    fn test_write_range_transcodes_utf16() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        let utf16: Vec<u8> = "héllo\nwörld"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        file.write_all(&utf16)?;
+        let reader = FileReader::new(file.path().to_path_buf(), UTF_16LE)?;
+        let cancel = AtomicBool::new(false);
+
+        let mut out = Vec::new();
+        let written = write_range(&reader, 12..22, true, &mut out, &cancel, |_| {})?;
+        assert_eq!(String::from_utf8(out)?, "wörld");
+        assert_eq!(written, 6);
+
+        let mut raw = Vec::new();
+        write_range(&reader, 12..22, false, &mut raw, &cancel, |_| {})?;
+        assert_eq!(raw, &utf16[12..22]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_range_to_file() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "line one\nline two\nline three\n")?;
+        let reader = FileReader::new(file.path().to_path_buf(), UTF_8)?;
+        let dir = tempdir()?;
+        let output = dir.path().join("out.txt");
+
+        let (tx, rx) = channel();
+        export_range(
+            &reader,
+            9..18,
+            &output,
+            false,
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ExportMessage::Done(9))));
+        assert_eq!(std::fs::read_to_string(&output)?, "line two\n");
+
+        // Exporting onto the viewed file is refused and leaves it intact
+        let (tx, rx) = channel();
+        export_range(
+            &reader,
+            0..4,
+            file.path(),
+            false,
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ExportMessage::Error(_))));
+        assert_eq!(reader.get_bytes(0, 4), b"line");
+        Ok(())
+    }
+
+    fn match_export(
+        reader: &Arc<FileReader>,
+        query: &str,
+        use_regex: bool,
+        options: MatchExportOptions,
+    ) -> Result<String> {
+        let mut engine = SearchEngine::new();
+        engine.set_query(query.to_string(), use_regex, true);
+        let mut out = Vec::new();
+        let cancel = Arc::new(AtomicBool::new(false));
+        write_matches(reader, &engine, options, &mut out, &cancel, |_| {})?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn test_write_matches_modes() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(
+            file,
+            "a\nerr 1 x\nb\nc\nd\nerr 2 y\nerr 3 z\ne\nf\ng\nh\nerr 4 w"
+        )?;
+        let reader = Arc::new(FileReader::new(file.path().to_path_buf(), UTF_8)?);
+
+        let lines = MatchExportOptions::default();
+        assert_eq!(
+            match_export(&reader, "err", false, lines)?,
+            "err 1 x\nerr 2 y\nerr 3 z\nerr 4 w\n"
+        );
+
+        // Context merges when it overlaps and is separated by `--` otherwise
+        let context = MatchExportOptions {
+            context: 1,
+            line_numbers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            match_export(&reader, "err", false, context)?,
+            "1-a\n2:err 1 x\n3-b\n--\n5-d\n6:err 2 y\n7:err 3 z\n8-e\n--\n11-h\n12:err 4 w\n"
+        );
+
+        let only = MatchExportOptions {
+            mode: MatchExportMode::MatchedText,
+            line_numbers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            match_export(&reader, r"err \d", true, only)?,
+            "2:err 1\n6:err 2\n7:err 3\n12:err 4\n"
+        );
+
+        let groups = MatchExportOptions {
+            mode: MatchExportMode::CaptureGroups,
+            ..Default::default()
+        };
+        assert_eq!(
+            match_export(&reader, r"err (\d) (\w)", true, groups)?,
+            "1\tx\n2\ty\n3\tz\n4\tw\n"
+        );
+        assert!(match_export(&reader, "err", false, groups).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_lines_utf16_line_breaks() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        let utf16: Vec<u8> = "a err\nb\nerr c"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        file.write_all(&utf16)?;
+        let reader = Arc::new(FileReader::new(file.path().to_path_buf(), UTF_16LE)?);
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let mut out = Vec::new();
+        let mut writer = MatchWriter {
+            reader: &reader,
+            options: MatchExportOptions {
+                to_utf8: true,
+                ..Default::default()
+            },
+            writer: &mut out,
+            cancel_token: &cancel,
+            written: 0,
+            written_until: 0,
+            pending_after: 0..0,
+            counted: (0, 0),
+        };
+        // A line with its own break gets no second one; the last line gets one
+        writer.write_lines(0..12, ':')?;
+        writer.write_lines(16..utf16.len(), ':')?;
+        assert_eq!(String::from_utf8(out)?, "a err\nerr c\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_matches_pages_through_results() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        for i in 0..(MATCH_PAGE * 2 + 5) {
+            writeln!(file, "hit {}", i)?;
+        }
+        let reader = Arc::new(FileReader::new(file.path().to_path_buf(), UTF_8)?);
+        let mut engine = SearchEngine::new();
+        engine.set_query("hit".to_string(), false, true);
+        let dir = tempdir()?;
+        let output = dir.path().join("matches.txt");
+
+        let (tx, rx) = channel();
+        let options = MatchExportOptions {
+            mode: MatchExportMode::MatchedText,
+            ..Default::default()
+        };
+        export_matches(
+            reader,
+            &engine,
+            options,
+            &output,
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ExportMessage::Done(_))));
+        let exported = std::fs::read_to_string(&output)?;
+        assert_eq!(exported.lines().count(), MATCH_PAGE * 2 + 5);
+        Ok(())
+    }
+}
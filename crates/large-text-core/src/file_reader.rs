@@ -4,6 +4,11 @@ use memmap2::Mmap;
 use std::fs::File;
 use std::path::PathBuf;
 
+/// Bytes decoded at a time by `decode_into`.
+const DECODE_CHUNK: usize = 1024 * 1024;
+/// Bytes at the start of a file checked by `FileReader::looks_binary`.
+const BINARY_SAMPLE_SIZE: usize = 8 * 1024;
+
 pub struct FileReader {
     mmap: Mmap,
     path: PathBuf,
@@ -37,6 +42,20 @@ impl FileReader {
         cow.into_owned()
     }
 
+    /// Decodes `start..end` and appends it to `out`, a chunk at a time, so large
+    /// ranges are never held as both bytes and text.
+    pub fn decode_into(&self, start: usize, end: usize, out: &mut String) {
+        let mut decoder = self.encoding.new_decoder_without_bom_handling();
+        let mut chunks = self.get_bytes(start, end).chunks(DECODE_CHUNK).peekable();
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            if let Some(needed) = decoder.max_utf8_buffer_length(chunk.len()) {
+                out.reserve(needed);
+            }
+            let _ = decoder.decode_to_string(chunk, out, last);
+        }
+    }
+
     pub fn get_bytes(&self, start: usize, end: usize) -> &[u8] {
         let end = end.min(self.mmap.len());
         if start >= end {
@@ -64,6 +83,33 @@ impl FileReader {
     pub fn all_data(&self) -> &[u8] {
         &self.mmap[..]
     }
+
+    /// Whether the start of the file looks like binary data rather than text in its
+    /// encoding (see `looks_binary`).
+    pub fn looks_binary(&self) -> bool {
+        looks_binary(self.get_bytes(0, BINARY_SAMPLE_SIZE), self.encoding)
+    }
+}
+
+/// Whether `sample` looks like binary data rather than text in `encoding`: it decodes
+/// to a NUL, or more than one character in ten is a control character other than
+/// whitespace and escape, or an invalid sequence.
+pub fn looks_binary(sample: &[u8], encoding: &'static Encoding) -> bool {
+    let (text, _) = encoding.decode_without_bom_handling(sample);
+    let mut chars = 0;
+    let mut suspicious = 0;
+    for c in text.chars() {
+        if c == '\0' {
+            return true;
+        }
+        chars += 1;
+        if c == char::REPLACEMENT_CHARACTER
+            || (c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+        {
+            suspicious += 1;
+        }
+    }
+    suspicious * 10 > chars
 }
 
 pub fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
@@ -115,6 +161,24 @@ mod tests {
         assert_eq!(detect_encoding(b"\xFF\xFF\xFF"), WINDOWS_1252);
     }
 
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(
+            b"plain text\r\n\twith \x1b[1mcolor\x1b[0m\n",
+            UTF_8
+        ));
+        assert!(looks_binary(b"\x7fELF\x02\x01\x01\x00\x00", UTF_8));
+        // A few stray control characters or invalid bytes are still text
+        assert!(!looks_binary(
+            b"caf\xe9 au lait, s'il vous pla\xeet\x07",
+            UTF_8
+        ));
+        assert!(looks_binary(b"\x01\x02\x03\x04 abc", WINDOWS_1252));
+        assert!(looks_binary(b"\xff\xfe\xfd\xfc abc", UTF_8));
+        // NUL bytes are part of UTF-16 text
+        assert!(!looks_binary(b"h\x00i\x00\n\x00", UTF_16LE));
+    }
+
     #[test]
     fn test_file_reader() -> Result<()> {
         let mut file = NamedTempFile::new()?;
@@ -127,6 +191,10 @@ mod tests {
         assert_eq!(reader.get_chunk(6, 11), "World");
         assert_eq!(reader.get_bytes(0, 5), b"Hello");
 
+        let mut text = String::new();
+        reader.decode_into(6, 18, &mut text);
+        assert_eq!(text, "World\nLine 2");
+
         Ok(())
     }
 
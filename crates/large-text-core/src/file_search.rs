@@ -0,0 +1,380 @@
+use crate::file_reader::{detect_encoding, FileReader};
+use crate::query::SearchOptions;
+use crate::search_engine::{SearchEngine, SearchMessage, SearchResult, SearchType};
+use anyhow::Result;
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{sync_channel, Sender},
+    Arc,
+};
+
+/// Longest line preview (in bytes) attached to a `FileMatch`.
+const PREVIEW_LEN: usize = 200;
+
+/// Which files a "Find in Files" search visits.
+#[derive(Clone, Debug, Default)]
+pub struct FileFilter {
+    /// Globs a file must match (by name or full path); empty means every file.
+    pub include: Vec<String>,
+    /// Globs excluding a file (by name or full path).
+    pub exclude: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FileMatch {
+    pub byte_offset: usize,
+    pub match_len: usize,
+    pub pattern_index: usize,
+    /// 0-indexed line number of the match.
+    pub line_number: usize,
+    /// The (possibly truncated) line containing the match.
+    pub line_text: String,
+}
+
+pub enum FileSearchMessage {
+    Started {
+        total_files: usize,
+    },
+    /// About to search the `index`-th file (0-based).
+    Searching {
+        index: usize,
+        path: PathBuf,
+    },
+    FileResult {
+        path: PathBuf,
+        total_matches: usize,
+        matches: Vec<FileMatch>,
+    },
+    Skipped {
+        path: PathBuf,
+        reason: String,
+    },
+    Done,
+    Error(String),
+}
+
+/// Expands `target` (a file, a directory searched recursively, or a glob such as
+/// `/var/log/app/*.log*`) into the sorted list of files accepted by `filter`.
+pub fn collect_files(target: &str, filter: &FileFilter) -> Result<Vec<PathBuf>> {
+    let include = build_glob_set(&filter.include)?;
+    let exclude = build_glob_set(&filter.exclude)?;
+
+    let (base, target_glob) = split_glob(target)?;
+    let mut files = Vec::new();
+    if base.is_file() {
+        files.push(base);
+    } else if base.is_dir() {
+        walk_dir(&base, &mut files)?;
+    } else {
+        anyhow::bail!("No such file or directory: {}", base.display());
+    }
+
+    files.retain(|path| {
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        let matches_set = |set: &GlobSet| set.is_match(path) || set.is_match(name);
+
+        target_glob.as_ref().is_none_or(|g| g.is_match(path))
+            && (filter.include.is_empty() || matches_set(&include))
+            && !matches_set(&exclude)
+    });
+    files.sort();
+    Ok(files)
+}
+
+/// Splits a glob into the directory to walk and the matcher for full paths.
+fn split_glob(target: &str) -> Result<(PathBuf, Option<GlobMatcher>)> {
+    let is_glob = |s: &str| s.contains(['*', '?', '[', '{']);
+    if !is_glob(target) {
+        return Ok((PathBuf::from(target), None));
+    }
+
+    let mut base = PathBuf::new();
+    for component in Path::new(target).components() {
+        let part = component.as_os_str().to_string_lossy();
+        if is_glob(&part) {
+            break;
+        }
+        base.push(component);
+    }
+    if base.as_os_str().is_empty() {
+        base.push(".");
+    }
+
+    let glob = GlobBuilder::new(target)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+    Ok((base, Some(glob)))
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // `file_type` does not follow symlinks, so linked directories cannot cause cycles
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            // Unreadable subdirectories are skipped rather than failing the whole search
+            let _ = walk_dir(&entry.path(), files);
+        } else if file_type.is_file() || entry.path().is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the search pipeline over each file in turn, sending one `FileResult` per file
+/// with at least one match. At most `max_matches_per_file` matches are listed per file,
+/// while `total_matches` is always the full count. Files larger than `max_file_size`
+/// are reported as skipped.
+pub fn search_files(
+    files: Vec<PathBuf>,
+    patterns: Vec<String>,
+    options: SearchOptions,
+    max_file_size: Option<u64>,
+    max_matches_per_file: usize,
+    tx: Sender<FileSearchMessage>,
+    cancel_token: Arc<AtomicBool>,
+) {
+    let _ = tx.send(FileSearchMessage::Started {
+        total_files: files.len(),
+    });
+
+    let mut engine = SearchEngine::new();
+    engine.set_patterns(patterns, options);
+
+    for (index, path) in files.into_iter().enumerate() {
+        if cancel_token.load(Ordering::Relaxed) {
+            return;
+        }
+        let _ = tx.send(FileSearchMessage::Searching {
+            index,
+            path: path.clone(),
+        });
+
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size == 0 {
+            continue;
+        }
+        if max_file_size.is_some_and(|max| size > max) {
+            let _ = tx.send(FileSearchMessage::Skipped {
+                path,
+                reason: format!("larger than size limit ({} bytes)", size),
+            });
+            continue;
+        }
+
+        match search_file(&engine, &path, max_matches_per_file, &cancel_token) {
+            Ok((total_matches, matches)) if total_matches > 0 => {
+                let _ = tx.send(FileSearchMessage::FileResult {
+                    path,
+                    total_matches,
+                    matches,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let _ = tx.send(FileSearchMessage::Skipped {
+                    path,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    if !cancel_token.load(Ordering::Relaxed) {
+        let _ = tx.send(FileSearchMessage::Done);
+    }
+}
+
+fn search_file(
+    engine: &SearchEngine,
+    path: &Path,
+    max_matches: usize,
+    cancel_token: &Arc<AtomicBool>,
+) -> Result<(usize, Vec<FileMatch>)> {
+    let mut sample = [0u8; 4096];
+    let n = std::fs::File::open(path)?.read(&mut sample)?;
+    let reader = Arc::new(FileReader::new(
+        path.to_path_buf(),
+        detect_encoding(&sample[..n]),
+    )?);
+
+    let (tx, rx) = sync_channel(1024);
+    engine.count_matches(reader.clone(), tx.clone(), cancel_token.clone());
+    engine.fetch_matches(reader.clone(), tx, 0, max_matches, cancel_token.clone());
+
+    let mut total = 0;
+    let mut results = Vec::new();
+    // Ends when both tasks report Done, or when their senders are dropped after a cancel
+    let mut pending = 2;
+    while pending > 0 {
+        match rx.recv() {
+            Ok(SearchMessage::CountResult(count)) => total += count,
+            Ok(SearchMessage::ChunkResult(chunk)) => results.extend(chunk.matches),
+            Ok(SearchMessage::Done(SearchType::Count | SearchType::Fetch)) => pending -= 1,
+            Ok(SearchMessage::PatternCountResult(_) | SearchMessage::HistogramResult(_)) => {}
+            Ok(SearchMessage::Error(e)) => anyhow::bail!(e),
+            Err(_) => break,
+        }
+    }
+
+    results.sort_by_key(|r| r.byte_offset);
+    Ok((
+        total.max(results.len()),
+        annotate_matches(&reader, &results),
+    ))
+}
+
+/// Adds line numbers and a preview of the containing line to sorted results.
+fn annotate_matches(reader: &FileReader, results: &[SearchResult]) -> Vec<FileMatch> {
+    let data = reader.all_data();
+    let mut line_number = 0;
+    let mut counted_to = 0;
+
+    results
+        .iter()
+        .map(|r| {
+            let offset = r.byte_offset.min(data.len());
+            line_number += memchr::memchr_iter(b'\n', &data[counted_to..offset]).count();
+            counted_to = offset;
+
+            let window_start = offset.saturating_sub(PREVIEW_LEN);
+            let line_start = memchr::memrchr(b'\n', &data[window_start..offset])
+                .map(|i| window_start + i + 1)
+                .unwrap_or(window_start);
+            let window_end = (line_start + PREVIEW_LEN).min(data.len()).max(offset);
+            let line_end = memchr::memchr(b'\n', &data[offset..window_end])
+                .map(|i| offset + i)
+                .unwrap_or(window_end);
+
+            FileMatch {
+                byte_offset: r.byte_offset,
+                match_len: r.match_len,
+                pattern_index: r.pattern_index,
+                line_number,
+                line_text: reader
+                    .get_chunk(line_start, line_end)
+                    .trim_end_matches('\r')
+                    .to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    fn setup() -> Result<TempDir> {
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("app.log"), "ok\nERROR one\nok\nERROR two\n")?;
+        std::fs::write(dir.path().join("app.log.1"), "ERROR old\n")?;
+        std::fs::write(dir.path().join("notes.txt"), "no errors here\n")?;
+        std::fs::create_dir(dir.path().join("archive"))?;
+        std::fs::write(dir.path().join("archive/app.log"), "ERROR archived\n")?;
+        Ok(dir)
+    }
+
+    fn names(dir: &TempDir, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| {
+                f.strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_collect_files_glob() -> Result<()> {
+        let dir = setup()?;
+        let target = format!("{}/*.log*", dir.path().display());
+        let files = collect_files(&target, &FileFilter::default())?;
+        assert_eq!(names(&dir, &files), vec!["app.log", "app.log.1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_files_dir_with_filters() -> Result<()> {
+        let dir = setup()?;
+        let target = dir.path().display().to_string();
+        let filter = FileFilter {
+            include: vec!["*.log".to_string()],
+            exclude: vec!["**/archive/**".to_string()],
+        };
+        let files = collect_files(&target, &filter)?;
+        assert_eq!(names(&dir, &files), vec!["app.log"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_files_groups_results() -> Result<()> {
+        let dir = setup()?;
+        let files = collect_files(&dir.path().display().to_string(), &FileFilter::default())?;
+
+        let (tx, rx) = mpsc::channel();
+        search_files(
+            files,
+            vec!["ERROR".to_string()],
+            SearchOptions {
+                case_sensitive: true,
+                ..Default::default()
+            },
+            Some(20),
+            100,
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+
+        let mut found = Vec::new();
+        let mut skipped = Vec::new();
+        for msg in rx {
+            match msg {
+                FileSearchMessage::FileResult { path, matches, .. } => {
+                    let lines: Vec<_> = matches
+                        .iter()
+                        .map(|m| (m.line_number, m.line_text.clone()))
+                        .collect();
+                    found.push((
+                        path.file_name().unwrap().to_string_lossy().into_owned(),
+                        lines,
+                    ));
+                }
+                FileSearchMessage::Skipped { path, .. } => skipped.push(path),
+                FileSearchMessage::Error(e) => panic!("Error: {}", e),
+                FileSearchMessage::Started { .. }
+                | FileSearchMessage::Searching { .. }
+                | FileSearchMessage::Done => {}
+            }
+        }
+
+        // The top-level app.log (26 bytes) exceeds the size limit
+        assert_eq!(skipped, vec![dir.path().join("app.log")]);
+        assert_eq!(
+            found,
+            vec![
+                ("app.log.1".to_string(), vec![(0, "ERROR old".to_string())]),
+                (
+                    "app.log".to_string(),
+                    vec![(0, "ERROR archived".to_string())]
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,10 @@
+pub mod cursor;
+pub mod export;
 pub mod file_reader;
+pub mod file_search;
 pub mod line_indexer;
+pub mod query;
 pub mod replacer;
+pub mod save;
 pub mod search_engine;
+pub mod timeline;
@@ -1,5 +1,10 @@
 use crate::file_reader::FileReader;
 
+/// Files up to this size (in bytes) get a full line index by default.
+pub const DEFAULT_FULL_INDEX_THRESHOLD: usize = 10_000_000; // 10 MB
+/// Default distance (in bytes) between checkpoints of the sparse index.
+pub const DEFAULT_SPARSE_SAMPLE_SIZE: usize = 10_000_000; // 10 MB
+
 pub struct LineIndexer {
     line_offsets: Vec<usize>,
     total_lines: usize,
@@ -8,6 +13,8 @@ pub struct LineIndexer {
     sample_interval: usize,
     file_size: usize,
     avg_line_length: f64,
+    full_index_threshold: usize,
+    sparse_sample_size: usize,
 }
 
 impl Default for LineIndexer {
@@ -25,19 +32,26 @@ impl LineIndexer {
             sample_interval: 0,
             file_size: 0,
             avg_line_length: 80.0,
+            full_index_threshold: DEFAULT_FULL_INDEX_THRESHOLD,
+            sparse_sample_size: DEFAULT_SPARSE_SAMPLE_SIZE,
         }
     }
 
+    /// Sets the largest file size that is fully indexed and the checkpoint spacing
+    /// used for larger files. Takes effect on the next `index_file`.
+    pub fn set_thresholds(&mut self, full_index_threshold: usize, sparse_sample_size: usize) {
+        self.full_index_threshold = full_index_threshold;
+        self.sparse_sample_size = sparse_sample_size.max(1);
+    }
+
     pub fn index_file(&mut self, reader: &FileReader) {
         self.line_offsets.clear();
         self.line_offsets.push(0);
         self.file_size = reader.len();
 
-        // For small files (< 10MB), do full indexing
+        // For small files, do full indexing
         // For large files, use sparse sampling only
-        const FULL_INDEX_THRESHOLD: usize = 10_000_000; // 10 MB
-
-        if self.file_size <= FULL_INDEX_THRESHOLD {
+        if self.file_size <= self.full_index_threshold {
             // Full indexing for smaller files
             let data = reader.all_data();
             self.full_index(data);
@@ -66,9 +80,8 @@ impl LineIndexer {
     }
 
     fn sparse_sample_index(&mut self, reader: &FileReader) {
-        // Only sample every 10MB for large files - creates sparse checkpoint index
-        const SPARSE_SAMPLE_SIZE: usize = 10_000_000; // 10MB
-        self.sample_interval = SPARSE_SAMPLE_SIZE;
+        // Only sample every `sparse_sample_size` bytes - creates sparse checkpoint index
+        self.sample_interval = self.sparse_sample_size;
 
         let mut pos = 0;
         let sample_count_limit = 100; // Limit to 100 samples max
@@ -79,7 +92,7 @@ impl LineIndexer {
 
         // Sample a few chunks to estimate average line length
         while pos < self.file_size && sample_count < sample_count_limit {
-            let chunk_end = (pos + SPARSE_SAMPLE_SIZE).min(self.file_size);
+            let chunk_end = (pos + self.sparse_sample_size).min(self.file_size);
             let chunk = reader.get_bytes(pos, chunk_end);
 
             // Count newlines to estimate average line length
@@ -181,16 +194,11 @@ impl LineIndexer {
             line_start = scan_start;
         }
 
-        // Find newline after our position for line end
-        let mut line_end = scan_end;
+        // Find newline after our position; like the full index, the line ends
+        // where the next one starts
         let search_from = relative_est.min(chunk.len());
-
-        for (i, &byte) in chunk.iter().enumerate().skip(search_from) {
-            if byte == b'\n' {
-                line_end = scan_start + i;
-                break;
-            }
-        }
+        let line_end = memchr::memchr(b'\n', &chunk[search_from..])
+            .map_or(scan_end, |i| scan_start + search_from + i + 1);
 
         Some((line_start, line_end))
     }
@@ -253,4 +261,37 @@ mod tests {
         assert_eq!(indexer.line_offsets, vec![0, 1, 2, 3]);
         Ok(())
     }
+
+    #[test]
+    fn test_line_indexer_custom_thresholds() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        // 10 lines of 10 bytes each
+        write!(file, "{}", "123456789\n".repeat(10))?;
+        let path = file.path().to_path_buf();
+
+        let reader = FileReader::new(path, detect_encoding(b""))?;
+        let mut indexer = LineIndexer::new();
+        indexer.set_thresholds(50, 40);
+        indexer.index_file(&reader);
+
+        assert_eq!(indexer.sample_interval, 40);
+        assert_eq!(indexer.line_offsets, vec![0, 0, 40, 80]);
+        assert_eq!(indexer.total_lines, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_line_ends_at_next_line_start() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "{}", "123456789\n".repeat(10))?;
+        let path = file.path().to_path_buf();
+
+        let reader = FileReader::new(path, detect_encoding(b""))?;
+        let mut indexer = LineIndexer::new();
+        indexer.set_thresholds(50, 40);
+        indexer.index_file(&reader);
+
+        assert_eq!(indexer.get_line_with_reader(3, &reader), Some((30, 40)));
+        Ok(())
+    }
 }
@@ -0,0 +1,742 @@
+use encoding_rs::{Encoding, UTF_8};
+use memchr::memmem;
+use regex::Regex;
+use regex_automata::{
+    hybrid, meta,
+    nfa::thompson::{self, WhichCaptures},
+    Anchored, Input, MatchKind,
+};
+use regex_syntax::hir::{Hir, HirKind, Repetition};
+
+/// Flags controlling how a search query is interpreted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchOptions {
+    pub use_regex: bool,
+    pub case_sensitive: bool,
+    /// Only match at Unicode word boundaries.
+    pub whole_word: bool,
+    /// Approximate matching: accept matches within this many edits (literal queries only).
+    pub max_edits: Option<usize>,
+    /// `query` is a hex byte pattern (see `parse_hex_bytes`) matched against the raw
+    /// bytes of the file; the other flags do not apply.
+    pub hex_bytes: bool,
+}
+
+/// Parses a hex byte pattern: pairs of hex digits, optionally prefixed with `0x` or
+/// `\x` and separated by spaces or commas, e.g. `DE AD BE EF`, `deadbeef`,
+/// `0xde,0xad` or `\xde\xad`.
+pub fn parse_hex_bytes(query: &str) -> Result<Vec<u8>, String> {
+    let mut digits = Vec::new();
+    for token in query.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        for part in token.split("\\x") {
+            for c in part.chars() {
+                let digit = c
+                    .to_digit(16)
+                    .ok_or_else(|| format!("'{}' is not a hex digit", c))?;
+                digits.push(digit as u8);
+            }
+        }
+    }
+    if digits.is_empty() {
+        return Err("No hex bytes to search for".to_string());
+    }
+    if digits.len() % 2 != 0 {
+        return Err("A hex pattern needs two digits per byte".to_string());
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect())
+}
+
+/// Builds the regex pattern for `query` under `options`.
+pub fn build_pattern(query: &str, options: &SearchOptions) -> String {
+    let mut pattern = if options.use_regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+
+    if options.whole_word {
+        if options.use_regex {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        } else {
+            // `\b` next to a non-word character would demand a word character on the other
+            // side, so only anchor the ends of the literal that are word characters.
+            if query.chars().next().is_some_and(is_word_char) {
+                pattern = format!(r"\b{}", pattern);
+            }
+            if query.chars().next_back().is_some_and(is_word_char) {
+                pattern = format!(r"{}\b", pattern);
+            }
+        }
+    }
+
+    if !options.case_sensitive {
+        pattern = format!("(?i){}", pattern);
+    }
+    pattern
+}
+
+/// Compiles `query` under `options` for matching raw bytes, such as a streaming
+/// replace over the file. It matches exactly what `Matcher::new` matches in the
+/// decoded text of a UTF-8 file, or what `LiteralMatcher` matches for hex byte
+/// patterns; approximate queries have no byte form.
+pub fn build_bytes_regex(
+    query: &str,
+    options: &SearchOptions,
+) -> Result<regex::bytes::Regex, String> {
+    regex::bytes::Regex::new(&bytes_pattern(query, options)?).map_err(|e| e.to_string())
+}
+
+/// The pattern `build_bytes_regex` compiles.
+fn bytes_pattern(query: &str, options: &SearchOptions) -> Result<String, String> {
+    if options.hex_bytes {
+        let bytes = parse_hex_bytes(query)?;
+        let escaped: String = bytes.iter().map(|b| format!("\\x{:02X}", b)).collect();
+        return Ok(format!("(?-u:{})", escaped));
+    }
+    if options.max_edits.is_some() {
+        return Err("Approximate matches cannot be replaced".to_string());
+    }
+    Ok(build_pattern(query, options))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A match reported by a `Matcher`: byte range plus the index of the pattern that matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueryMatch {
+    pub start: usize,
+    pub end: usize,
+    pub pattern: usize,
+}
+
+impl QueryMatch {
+    fn single(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+            pattern: 0,
+        }
+    }
+}
+
+/// A compiled query that finds matches in decoded text.
+#[derive(Clone, Debug)]
+pub enum Matcher {
+    Regex(Regex),
+    /// Several patterns searched in one pass, reporting which pattern matched.
+    Multi(meta::Regex),
+    Fuzzy(FuzzyMatcher),
+}
+
+impl Matcher {
+    /// Compiles a list of patterns. A single pattern behaves exactly like `Matcher::new`.
+    pub fn new_many(patterns: &[String], options: &SearchOptions) -> Result<Self, String> {
+        match patterns {
+            [] => Err("No search pattern".to_string()),
+            [query] => Self::new(query, options),
+            _ if options.hex_bytes => Err("Hex search supports a single pattern".to_string()),
+            _ if options.max_edits.is_some() => {
+                Err("Approximate matching supports a single pattern".to_string())
+            }
+            _ => {
+                let built: Vec<String> =
+                    patterns.iter().map(|p| build_pattern(p, options)).collect();
+                meta::Regex::new_many(&built)
+                    .map(Matcher::Multi)
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Self, String> {
+        if options.hex_bytes {
+            // Searches run on the raw bytes through `LiteralMatcher`; this only finds the
+            // bytes in decoded text, which cannot hold them unless they are valid UTF-8.
+            let bytes = parse_hex_bytes(query)?;
+            let pattern = match std::str::from_utf8(&bytes) {
+                Ok(text) => regex::escape(text),
+                Err(_) => r"[^\s\S]".to_string(),
+            };
+            return Regex::new(&pattern)
+                .map(Matcher::Regex)
+                .map_err(|e| e.to_string());
+        }
+        match options.max_edits {
+            Some(_) if options.use_regex => {
+                Err("Approximate matching requires a literal query".to_string())
+            }
+            Some(max_edits) => Ok(Matcher::Fuzzy(FuzzyMatcher::new(
+                query,
+                max_edits,
+                options.case_sensitive,
+                options.whole_word,
+            ))),
+            None => Regex::new(&build_pattern(query, options))
+                .map(Matcher::Regex)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> Box<dyn Iterator<Item = QueryMatch> + 'a> {
+        match self {
+            Matcher::Regex(re) => Box::new(
+                re.find_iter(text)
+                    .map(|m| QueryMatch::single(m.start(), m.end())),
+            ),
+            Matcher::Multi(re) => Box::new(re.find_iter(text).map(|m| QueryMatch {
+                start: m.start(),
+                end: m.end(),
+                pattern: m.pattern().as_usize(),
+            })),
+            Matcher::Fuzzy(fuzzy) => Box::new(
+                fuzzy
+                    .find_all(text)
+                    .into_iter()
+                    .map(|(start, end)| QueryMatch::single(start, end)),
+            ),
+        }
+    }
+}
+
+/// Byte-level matcher for plain literals that runs directly over the mapped file,
+/// skipping the decode step of the `Matcher` pipeline.
+#[derive(Clone, Debug)]
+pub enum LiteralMatcher {
+    /// SIMD-accelerated exact substring search.
+    Exact(Box<memmem::Finder<'static>>),
+    /// Case-insensitive search for ASCII queries, run on bytes so nothing is decoded.
+    CaseInsensitive(regex::bytes::Regex),
+}
+
+impl LiteralMatcher {
+    /// Returns a fast-path matcher when `query` is a plain literal whose bytes can be
+    /// matched verbatim against a file in `encoding`.
+    pub fn new(query: &str, options: &SearchOptions, encoding: &'static Encoding) -> Option<Self> {
+        if options.hex_bytes {
+            // Raw bytes, whatever the encoding
+            let bytes = parse_hex_bytes(query).ok()?;
+            return Some(LiteralMatcher::Exact(Box::new(
+                memmem::Finder::new(&bytes).into_owned(),
+            )));
+        }
+        if query.is_empty()
+            || options.use_regex
+            || options.whole_word
+            || options.max_edits.is_some()
+        {
+            return None;
+        }
+
+        // The query's UTF-8 bytes only equal the file's bytes for UTF-8 files, or for
+        // ASCII queries in an ASCII-compatible single-byte encoding. Multi-byte
+        // encodings such as Shift_JIS are excluded, as their trailing bytes can equal
+        // ASCII bytes.
+        let byte_compatible = encoding == UTF_8
+            || (encoding.is_single_byte() && encoding.is_ascii_compatible() && query.is_ascii());
+        if !byte_compatible {
+            return None;
+        }
+
+        if options.case_sensitive {
+            Some(LiteralMatcher::Exact(Box::new(
+                memmem::Finder::new(query.as_bytes()).into_owned(),
+            )))
+        } else if query.is_ascii() {
+            regex::bytes::Regex::new(&build_pattern(query, options))
+                .ok()
+                .map(LiteralMatcher::CaseInsensitive)
+        } else {
+            // Unicode case folding needs the regex engine
+            None
+        }
+    }
+
+    pub fn find_iter<'a>(
+        &'a self,
+        haystack: &'a [u8],
+    ) -> Box<dyn Iterator<Item = QueryMatch> + 'a> {
+        match self {
+            LiteralMatcher::Exact(finder) => {
+                let len = finder.needle().len();
+                Box::new(
+                    finder
+                        .find_iter(haystack)
+                        .map(move |start| QueryMatch::single(start, start + len)),
+                )
+            }
+            LiteralMatcher::CaseInsensitive(re) => Box::new(
+                re.find_iter(haystack)
+                    .map(|m| QueryMatch::single(m.start(), m.end())),
+            ),
+        }
+    }
+}
+
+/// Tells a streaming matcher which part of a partly read haystack is settled: where,
+/// at the end of what has been read so far, a match could still be in progress.
+///
+/// It runs a reverse lazy DFA over every prefix of every match of the query, with
+/// look-around assertions dropped. That is a superset of the real partial matches, so
+/// none is ever missed; at worst a few extra bytes are held back.
+#[derive(Clone, Debug)]
+pub struct PartialMatchFinder {
+    dfa: hybrid::dfa::DFA,
+    cache: hybrid::dfa::Cache,
+}
+
+impl PartialMatchFinder {
+    /// Builds the finder for the regex `build_bytes_regex` compiles from `query`.
+    pub fn new(query: &str, options: &SearchOptions) -> Result<Self, String> {
+        let hir = regex_syntax::ParserBuilder::new()
+            .utf8(false)
+            .build()
+            .parse(&bytes_pattern(query, options)?)
+            .map_err(|e| e.to_string())?;
+        let nfa = thompson::Compiler::new()
+            .configure(
+                thompson::Config::new()
+                    .reverse(true)
+                    .utf8(false)
+                    .which_captures(WhichCaptures::None),
+            )
+            .build_from_hir(&prefixes(&hir))
+            .map_err(|e| e.to_string())?;
+        let dfa = hybrid::dfa::DFA::builder()
+            .configure(hybrid::dfa::DFA::config().match_kind(MatchKind::All))
+            .build_from_nfa(nfa)
+            .map_err(|e| e.to_string())?;
+        let cache = dfa.create_cache();
+        Ok(Self { dfa, cache })
+    }
+
+    /// The earliest position at or after `start` where a match could begin and run
+    /// past the end of `haystack`. Every match starting before it lies wholly inside
+    /// `haystack` and will not change however the text goes on.
+    pub fn earliest_partial(&mut self, haystack: &[u8], start: usize) -> usize {
+        // A haystack cut inside a multi-byte character ends partway through a character
+        // class, which the character-level prefixes do not cover, so also try the end
+        // before that character.
+        let ends = [haystack.len(), incomplete_utf8_tail(haystack)];
+        let mut earliest = haystack.len();
+        for end in ends.into_iter().filter(|&end| end >= start) {
+            let input = Input::new(&haystack[..end])
+                .range(start..end)
+                .anchored(Anchored::Yes);
+            earliest = match self.dfa.try_search_rev(&mut self.cache, &input) {
+                Ok(Some(m)) => earliest.min(m.offset()),
+                Ok(None) => earliest.min(end),
+                // The DFA gave up; hold everything back rather than guess
+                Err(_) => start,
+            };
+        }
+        earliest
+    }
+}
+
+/// Where a trailing, incomplete UTF-8 sequence begins, or `bytes.len()` if there is
+/// none.
+fn incomplete_utf8_tail(bytes: &[u8]) -> usize {
+    for back in 1..=3.min(bytes.len()) {
+        let lead = bytes[bytes.len() - back];
+        if lead & 0xC0 != 0x80 {
+            let needed = match lead {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            return if needed > back {
+                bytes.len() - back
+            } else {
+                bytes.len()
+            };
+        }
+    }
+    bytes.len()
+}
+
+/// `hir` with every look-around assertion replaced by the empty string, so it matches
+/// a superset of what `hir` matches.
+fn without_looks(hir: &Hir) -> Hir {
+    match hir.kind() {
+        HirKind::Look(_) => Hir::empty(),
+        HirKind::Repetition(rep) => Hir::repetition(Repetition {
+            min: rep.min,
+            max: rep.max,
+            greedy: rep.greedy,
+            sub: Box::new(without_looks(&rep.sub)),
+        }),
+        HirKind::Capture(capture) => without_looks(&capture.sub),
+        HirKind::Concat(subs) => Hir::concat(subs.iter().map(without_looks).collect()),
+        HirKind::Alternation(subs) => Hir::alternation(subs.iter().map(without_looks).collect()),
+        HirKind::Empty | HirKind::Literal(_) | HirKind::Class(_) => hir.clone(),
+    }
+}
+
+/// Matches every prefix of every match of `without_looks(hir)`.
+fn prefixes(hir: &Hir) -> Hir {
+    let optional = |hir: Hir| Hir::alternation(vec![Hir::empty(), hir]);
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => Hir::empty(),
+        // Nested rather than listed, so a long literal stays linear in size
+        HirKind::Literal(literal) => literal.0.iter().rev().fold(Hir::empty(), |rest, &b| {
+            optional(Hir::concat(vec![Hir::literal(vec![b]), rest]))
+        }),
+        HirKind::Class(_) => optional(hir.clone()),
+        HirKind::Repetition(rep) if rep.max == Some(0) => Hir::empty(),
+        // Some whole repetitions, then a prefix of one more
+        HirKind::Repetition(rep) => Hir::concat(vec![
+            Hir::repetition(Repetition {
+                min: 0,
+                max: rep.max.map(|max| max - 1),
+                greedy: true,
+                sub: Box::new(without_looks(&rep.sub)),
+            }),
+            prefixes(&rep.sub),
+        ]),
+        HirKind::Capture(capture) => prefixes(&capture.sub),
+        // A prefix of the first part, or all of it followed by a prefix of the rest
+        HirKind::Concat(subs) => subs
+            .iter()
+            .rev()
+            .fold(None, |rest, sub| {
+                Some(match rest {
+                    None => prefixes(sub),
+                    Some(rest) => Hir::alternation(vec![
+                        prefixes(sub),
+                        Hir::concat(vec![without_looks(sub), rest]),
+                    ]),
+                })
+            })
+            .unwrap_or_else(Hir::empty),
+        HirKind::Alternation(subs) => Hir::alternation(subs.iter().map(prefixes).collect()),
+    }
+}
+
+/// Approximate substring matcher (Sellers' algorithm) over Unicode characters.
+///
+/// Reports non-overlapping substrings within `max_edits` insertions, deletions or
+/// substitutions of the pattern, preferring the closest match in each cluster.
+#[derive(Clone, Debug)]
+pub struct FuzzyMatcher {
+    pattern: Vec<char>,
+    max_edits: usize,
+    case_sensitive: bool,
+    whole_word: bool,
+}
+
+impl FuzzyMatcher {
+    pub fn new(pattern: &str, max_edits: usize, case_sensitive: bool, whole_word: bool) -> Self {
+        let mut matcher = Self {
+            pattern: Vec::new(),
+            max_edits,
+            case_sensitive,
+            whole_word,
+        };
+        matcher.pattern = pattern.chars().map(|c| matcher.fold(c)).collect();
+        // At least one character has to match, otherwise every position would be a hit
+        matcher.max_edits = max_edits.min(matcher.pattern.len().saturating_sub(1));
+        matcher
+    }
+
+    fn fold(&self, c: char) -> char {
+        if self.case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    }
+
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize)> {
+        let m = self.pattern.len();
+        let mut matches = Vec::new();
+        if m == 0 {
+            return matches;
+        }
+
+        // Column of the DP matrix: cost[i] is the edit distance of pattern[..i] against the
+        // best substring ending at the current text position, start[i] where that substring begins.
+        let mut cost: Vec<usize> = (0..=m).collect();
+        let mut start = vec![0usize; m + 1];
+
+        // Best (distance, start, end) of the current cluster of candidate matches
+        let mut best: Option<(usize, usize, usize)> = None;
+        let mut last_end = 0;
+
+        for (byte_idx, ch) in text.char_indices() {
+            let c = self.fold(ch);
+            let end = byte_idx + ch.len_utf8();
+
+            let mut diag_cost = cost[0];
+            let mut diag_start = start[0];
+            cost[0] = 0;
+            start[0] = end;
+
+            for i in 1..=m {
+                let substitution = diag_cost + usize::from(self.pattern[i - 1] != c);
+                let insertion = cost[i] + 1;
+                let deletion = cost[i - 1] + 1;
+
+                diag_cost = cost[i];
+                let old_start = start[i];
+
+                if substitution <= insertion && substitution <= deletion {
+                    cost[i] = substitution;
+                    start[i] = diag_start;
+                } else if insertion <= deletion {
+                    cost[i] = insertion;
+                } else {
+                    cost[i] = deletion;
+                    start[i] = start[i - 1];
+                }
+                diag_start = old_start;
+            }
+
+            let distance = cost[m];
+            if distance <= self.max_edits {
+                let candidate = (distance, start[m], end);
+                best = match best {
+                    Some(current) if candidate.1 >= current.2 => {
+                        self.emit(text, current, &mut last_end, &mut matches);
+                        Some(candidate)
+                    }
+                    Some(current) if candidate.0 >= current.0 => Some(current),
+                    _ => Some(candidate),
+                };
+            } else if let Some(current) = best.take() {
+                self.emit(text, current, &mut last_end, &mut matches);
+            }
+        }
+
+        if let Some(current) = best {
+            self.emit(text, current, &mut last_end, &mut matches);
+        }
+        matches
+    }
+
+    fn emit(
+        &self,
+        text: &str,
+        (_, start, end): (usize, usize, usize),
+        last_end: &mut usize,
+        matches: &mut Vec<(usize, usize)>,
+    ) {
+        if start < *last_end {
+            return;
+        }
+        if self.whole_word {
+            let before = text[..start].chars().next_back().is_some_and(is_word_char);
+            let after = text[end..].chars().next().is_some_and(is_word_char);
+            if before || after {
+                return;
+            }
+        }
+        matches.push((start, end));
+        *last_end = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(query: &str, options: SearchOptions, text: &str) -> Vec<(usize, usize)> {
+        Matcher::new(query, &options)
+            .unwrap()
+            .find_iter(text)
+            .map(|m| (m.start, m.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            find("cat", options, "cat concat cats cat_ cat."),
+            vec![(0, 3), (21, 24)]
+        );
+        // Unicode letters are word characters
+        assert_eq!(find("über", options, "überall über"), vec![(9, 14)]);
+        // Non-word edges are not anchored
+        assert_eq!(find("-x", options, "a-x b-xy"), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn test_whole_word_regex() {
+        let options = SearchOptions {
+            use_regex: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            find(r"id\d+", options, "id12 xid3 id4"),
+            vec![(0, 4), (10, 13)]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_matches_typos() {
+        let options = SearchOptions {
+            max_edits: Some(1),
+            ..Default::default()
+        };
+        let text = "user=ABC123 user=ABD123 user=AB123 user=XYZ999";
+        assert_eq!(
+            find("abc123", options, text),
+            vec![(5, 11), (17, 23), (29, 34)]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_adjacent_and_exact() {
+        let options = SearchOptions {
+            case_sensitive: true,
+            max_edits: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(find("abc", options, "abcabc"), vec![(0, 3), (3, 6)]);
+        assert_eq!(find("abc", options, "ABC"), Vec::<(usize, usize)>::new());
+    }
+
+    #[test]
+    fn test_literal_fast_path() {
+        let exact = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let matcher = LiteralMatcher::new("ab", &exact, UTF_8).unwrap();
+        let found: Vec<_> = matcher
+            .find_iter(b"abAB ab")
+            .map(|m| (m.start, m.end))
+            .collect();
+        assert_eq!(found, vec![(0, 2), (5, 7)]);
+
+        let folded = SearchOptions::default();
+        let matcher = LiteralMatcher::new("ab", &folded, UTF_8).unwrap();
+        let found: Vec<_> = matcher
+            .find_iter(b"abAB ab")
+            .map(|m| (m.start, m.end))
+            .collect();
+        assert_eq!(found, vec![(0, 2), (2, 4), (5, 7)]);
+    }
+
+    #[test]
+    fn test_literal_fast_path_eligibility() {
+        let options = SearchOptions::default();
+        assert!(LiteralMatcher::new("é", &options, UTF_8).is_none());
+        assert!(LiteralMatcher::new("ab", &options, encoding_rs::UTF_16LE).is_none());
+        assert!(LiteralMatcher::new("ab", &options, encoding_rs::WINDOWS_1252).is_some());
+        assert!(LiteralMatcher::new("ab", &options, encoding_rs::SHIFT_JIS).is_none());
+        assert!(LiteralMatcher::new("ab", &options, encoding_rs::GBK).is_none());
+        let regex = SearchOptions {
+            use_regex: true,
+            ..Default::default()
+        };
+        assert!(LiteralMatcher::new("ab", &regex, UTF_8).is_none());
+    }
+
+    #[test]
+    fn test_multi_pattern_reports_pattern() {
+        let patterns = vec!["E100".to_string(), "e2\\d+".to_string(), "warn".to_string()];
+        let options = SearchOptions {
+            use_regex: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new_many(&patterns, &options).unwrap();
+        let found: Vec<_> = matcher
+            .find_iter("E100 E250 WARN e100")
+            .map(|m| (m.start, m.end, m.pattern))
+            .collect();
+        assert_eq!(found, vec![(0, 4, 0), (5, 9, 1), (10, 14, 2), (15, 19, 0)]);
+    }
+
+    #[test]
+    fn test_multi_pattern_literals_are_escaped() {
+        let patterns = vec!["a.c".to_string(), "(x)".to_string()];
+        let matcher = Matcher::new_many(&patterns, &SearchOptions::default()).unwrap();
+        let found: Vec<_> = matcher
+            .find_iter("abc a.c (x) x")
+            .map(|m| m.pattern)
+            .collect();
+        assert_eq!(found, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_fuzzy_rejects_regex() {
+        let options = SearchOptions {
+            use_regex: true,
+            max_edits: Some(1),
+            ..Default::default()
+        };
+        assert!(Matcher::new("a+", &options).is_err());
+    }
+
+    #[test]
+    fn test_partial_match_finder() {
+        let mut finder = PartialMatchFinder::new("error", &SearchOptions::default()).unwrap();
+        // Case-insensitive, so "ERR" may still become a match
+        assert_eq!(finder.earliest_partial(b"an ERR", 0), 3);
+        assert_eq!(finder.earliest_partial(b"an ERR", 4), 6);
+        // A match right at the end is held back too: what follows may still matter
+        assert_eq!(finder.earliest_partial(b"an error", 0), 3);
+        assert_eq!(finder.earliest_partial(b"an error.", 0), 9);
+        assert_eq!(finder.earliest_partial(b"an erx", 0), 6);
+
+        let options = SearchOptions {
+            use_regex: true,
+            ..Default::default()
+        };
+        let mut finder = PartialMatchFinder::new(r"\bBEGIN[\s\S]*?END", &options).unwrap();
+        assert_eq!(finder.earliest_partial(b"x BEGIN\nbody\nEN", 0), 2);
+        // Cut inside "é": the character-level prefix ends before it
+        let mut finder = PartialMatchFinder::new("é+x", &options).unwrap();
+        assert_eq!(finder.earliest_partial("aéé".as_bytes(), 0), 1);
+        assert_eq!(finder.earliest_partial(&"aéé".as_bytes()[..4], 0), 1);
+        assert_eq!(finder.earliest_partial(b"\x80\x80\x80", 0), 3);
+    }
+
+    #[test]
+    fn test_hex_byte_patterns() {
+        assert_eq!(
+            parse_hex_bytes("DE AD be ef"),
+            Ok(vec![0xDE, 0xAD, 0xBE, 0xEF])
+        );
+        assert_eq!(parse_hex_bytes("0xde,0xAD"), Ok(vec![0xDE, 0xAD]));
+        assert_eq!(parse_hex_bytes(r"\xde\xad00"), Ok(vec![0xDE, 0xAD, 0x00]));
+        assert!(parse_hex_bytes("DEA").is_err());
+        assert!(parse_hex_bytes("GG").is_err());
+        assert!(parse_hex_bytes(" , ").is_err());
+
+        let options = SearchOptions {
+            hex_bytes: true,
+            // Ignored for hex patterns
+            use_regex: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        // Raw bytes match whatever the encoding, invalid UTF-8 included
+        let literal = LiteralMatcher::new("ff 00", &options, encoding_rs::UTF_16LE).unwrap();
+        let found: Vec<_> = literal
+            .find_iter(b"a\xff\x00b\xff\x00")
+            .map(|m| (m.start, m.end))
+            .collect();
+        assert_eq!(found, vec![(1, 3), (4, 6)]);
+        // In decoded text only bytes that form valid UTF-8 can be found
+        assert_eq!(find("61 62", options, "xab ab"), vec![(1, 3), (4, 6)]);
+        assert_eq!(find("ff", options, "\u{fffd}"), vec![]);
+        let bytes = build_bytes_regex("ff 00", &options).unwrap();
+        assert_eq!(bytes.find(b"a\xff\x00").map(|m| m.start()), Some(1));
+    }
+}
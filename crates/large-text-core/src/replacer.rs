@@ -1,7 +1,7 @@
 use anyhow::Result;
-use regex::bytes::Regex;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::Path;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -9,234 +9,466 @@ use std::sync::{
     Arc,
 };
 
+use crate::query::{build_bytes_regex, PartialMatchFinder, SearchOptions};
+use crate::save::{write_atomic, SaveOptions};
+
 pub enum ReplaceMessage {
-    Progress(usize, usize), // bytes_processed, total_bytes
-    Done,
+    Progress(usize, usize, usize), // bytes_processed, total_bytes, replacements so far
+    Done(usize),                   // replacements made
+    Preview(Vec<ReplacePreview>),  // the first matches, nothing written
     Error(String),
 }
 
+/// One match as `Replacer::preview_replace_all` reports it: the line it starts on,
+/// before and after the replacement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplacePreview {
+    /// 1-based line number of the match start.
+    pub line: usize,
+    pub offset: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Memory bounds of the streaming replace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamLimits {
+    /// Bytes read from the input at a time.
+    pub chunk_size: usize,
+    /// Most bytes held back while a match may still be in progress. A longer match
+    /// fails the replace rather than being split.
+    pub max_match_len: usize,
+}
+
+impl Default for StreamLimits {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1024 * 1024,
+            max_match_len: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Bytes of line kept on each side of a previewed match.
+const PREVIEW_CONTEXT: usize = 120;
+
+/// One change for `Replacer::apply_edits`: the `old_len` bytes at `offset` in the
+/// original file become `new_bytes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edit {
+    pub offset: usize,
+    pub old_len: usize,
+    pub new_bytes: Vec<u8>,
+}
+
+impl Edit {
+    pub fn new(offset: usize, old_len: usize, new_bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            offset,
+            old_len,
+            new_bytes: new_bytes.into(),
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.old_len
+    }
+}
+
+/// Sorts `edits` by offset and rejects any that overlap or run past `file_len`.
+/// Adjacent edits, and several insertions at one offset, are kept in the given order.
+pub fn sort_edits(edits: &[Edit], file_len: usize) -> Result<Vec<Edit>> {
+    let mut sorted = edits.to_vec();
+    sorted.sort_by_key(|e| e.offset);
+    for pair in sorted.windows(2) {
+        if pair[1].offset < pair[0].end() {
+            anyhow::bail!(
+                "Overlapping edits at offsets {} and {}",
+                pair[0].offset,
+                pair[1].offset
+            );
+        }
+    }
+    if let Some(last) = sorted.last().filter(|e| e.end() > file_len) {
+        anyhow::bail!(
+            "Edit at offset {} runs past the end of the file",
+            last.offset
+        );
+    }
+    Ok(sorted)
+}
+
+/// Where `offset` in the original file lands once `edits` (sorted, as returned by
+/// `sort_edits`) are applied. Offsets inside a replaced span move to its start, and
+/// text inserted at `offset` ends up after it.
+pub fn shifted_offset(edits: &[Edit], offset: usize) -> usize {
+    let mut shift: isize = 0;
+    for edit in edits {
+        if edit.end() <= offset && (edit.old_len > 0 || edit.offset < offset) {
+            shift += edit.new_bytes.len() as isize - edit.old_len as isize;
+        } else if edit.offset < offset {
+            return (edit.offset as isize + shift) as usize;
+        } else {
+            break;
+        }
+    }
+    (offset as isize + shift) as usize
+}
+
 pub struct Replacer;
 
 impl Replacer {
+    /// Replaces `old_len` bytes at `offset` with `new_text`: in place when the length
+    /// is unchanged, otherwise through an atomic rewrite.
     pub fn replace_single(
         file_path: &Path,
         offset: usize,
         old_len: usize,
         new_text: &str,
     ) -> Result<()> {
-        let new_bytes = new_text.as_bytes();
+        Self::apply_edits(
+            file_path,
+            file_path,
+            &[Edit::new(offset, old_len, new_text)],
+            SaveOptions::default(),
+        )
+    }
 
-        if new_bytes.len() == old_len {
-            // In-place optimization
-            let mut file = OpenOptions::new().write(true).open(file_path)?;
-            file.seek(SeekFrom::Start(offset as u64))?;
-            file.write_all(new_bytes)?;
-            return Ok(());
-        }
+    /// Applies `edits`, given in original file offsets, writing the result to
+    /// `output_path` in one streaming pass through `save::write_atomic`. When writing
+    /// back onto `input_path` without a backup and every edit keeps its length, the
+    /// file is patched in place instead.
+    pub fn apply_edits(
+        input_path: &Path,
+        output_path: &Path,
+        edits: &[Edit],
+        options: SaveOptions,
+    ) -> Result<()> {
+        let mut input_file = File::open(input_path)?;
+        let file_len = input_file.metadata()?.len() as usize;
+        let edits = sort_edits(edits, file_len)?;
 
-        // Different length: rewrite file
-        let temp_path = file_path.with_extension("tmp");
-        {
-            let mut input_file = File::open(file_path)?;
-            let mut output_file = BufWriter::new(File::create(&temp_path)?);
-
-            // Copy before match
-            let mut buffer = vec![0u8; 8192];
-            let mut remaining = offset as u64;
-
-            while remaining > 0 {
-                let to_read = std::cmp::min(remaining, buffer.len() as u64) as usize;
-                let n = input_file.read(&mut buffer[0..to_read])?;
-                if n == 0 {
-                    break; // Unexpected EOF
-                }
-                output_file.write_all(&buffer[0..n])?;
-                remaining -= n as u64;
+        let same_file = std::fs::canonicalize(input_path)?
+            == std::fs::canonicalize(output_path).unwrap_or_else(|_| output_path.to_path_buf());
+        if same_file && !options.backup && edits.iter().all(|e| e.new_bytes.len() == e.old_len) {
+            drop(input_file);
+            let mut file = OpenOptions::new().write(true).open(output_path)?;
+            for edit in &edits {
+                file.seek(SeekFrom::Start(edit.offset as u64))?;
+                file.write_all(&edit.new_bytes)?;
             }
-
-            // Write replacement
-            output_file.write_all(new_bytes)?;
-
-            // Skip old match in input
-            input_file.seek(SeekFrom::Current(old_len as i64))?;
-
-            // Copy rest
-            std::io::copy(&mut input_file, &mut output_file)?;
+            file.sync_all()?;
+            return Ok(());
         }
 
-        // Replace original file
-        // On Windows, rename might fail if target exists.
-        if std::fs::rename(&temp_path, file_path).is_err() {
-            // Try to remove target and rename again.
-            if std::fs::remove_file(file_path).is_ok() {
-                std::fs::rename(&temp_path, file_path)?;
-            } else {
-                return Err(anyhow::anyhow!(
-                    "Failed to replace file. It might be open by another process."
-                ));
+        write_atomic(output_path, options, |output_file| {
+            let mut pos = 0;
+            for edit in &edits {
+                std::io::copy(
+                    &mut (&mut input_file).take((edit.offset - pos) as u64),
+                    output_file,
+                )?;
+                output_file.write_all(&edit.new_bytes)?;
+                input_file.seek(SeekFrom::Current(edit.old_len as i64))?;
+                pos = edit.end();
             }
-        }
-
-        Ok(())
+            std::io::copy(&mut input_file, output_file)?;
+            output_file.flush()?;
+            Ok(())
+        })
     }
 
+    /// Writes `input_path` to `output_path` with every match of `query` replaced,
+    /// compiled by `query::build_bytes_regex` so it changes exactly what a search
+    /// with the same `search_options` finds. The result goes through
+    /// `save::write_atomic`, so `output_path` may be `input_path` itself to replace in
+    /// place. When `range` is given, only matches lying entirely inside that byte
+    /// range are replaced and the bytes outside it are copied unchanged. Matches may
+    /// be any length up to `limits.max_match_len`, and the result is the same as
+    /// replacing in the whole file held in memory. A cancelled or failed run leaves
+    /// `output_path` as it was.
+    #[allow(clippy::too_many_arguments)]
     pub fn replace_all(
         input_path: &Path,
         output_path: &Path,
         query: &str,
         replace_with: &str,
-        use_regex: bool,
+        search_options: SearchOptions,
+        range: Option<Range<usize>>,
+        limits: StreamLimits,
+        options: SaveOptions,
         tx: Sender<ReplaceMessage>,
         cancel_token: Arc<AtomicBool>,
     ) {
-        match Self::replace_all_inner(
-            input_path,
-            output_path,
-            query,
-            replace_with,
-            use_regex,
-            &tx,
-            cancel_token,
-        ) {
-            Ok(_) => {
-                let _ = tx.send(ReplaceMessage::Done);
+        let mut replacements = 0;
+        let result = write_atomic(output_path, options, |output_file| {
+            replacements = Self::replace_all_inner(
+                input_path,
+                output_file,
+                query,
+                replace_with,
+                search_options,
+                range,
+                limits,
+                &tx,
+                &cancel_token,
+                |_, _, _| true,
+            )?;
+            if cancel_token.load(Ordering::Relaxed) {
+                anyhow::bail!("Replace cancelled");
+            }
+            Ok(())
+        });
+        let _ = match result {
+            Ok(()) => tx.send(ReplaceMessage::Done(replacements)),
+            Err(e) => tx.send(ReplaceMessage::Error(e.to_string())),
+        };
+    }
+
+    /// Finds the first `max_pairs` matches `replace_all` would replace, with the same
+    /// arguments, and sends them as one `ReplaceMessage::Preview` without writing
+    /// anything.
+    #[allow(clippy::too_many_arguments)]
+    pub fn preview_replace_all(
+        input_path: &Path,
+        query: &str,
+        replace_with: &str,
+        search_options: SearchOptions,
+        range: Option<Range<usize>>,
+        limits: StreamLimits,
+        max_pairs: usize,
+        tx: Sender<ReplaceMessage>,
+        cancel_token: Arc<AtomicBool>,
+    ) {
+        let result = (|| -> Result<Vec<ReplacePreview>> {
+            let mut found = Vec::new();
+            if max_pairs > 0 {
+                Self::replace_all_inner(
+                    input_path,
+                    &mut std::io::sink(),
+                    query,
+                    replace_with,
+                    search_options,
+                    range,
+                    limits,
+                    &tx,
+                    &cancel_token,
+                    |offset, old, new| {
+                        found.push((offset, old.len(), new.to_vec()));
+                        found.len() < max_pairs
+                    },
+                )?;
             }
-            Err(e) => {
-                let _ = tx.send(ReplaceMessage::Error(e.to_string()));
+            if cancel_token.load(Ordering::Relaxed) {
+                anyhow::bail!("Preview cancelled");
             }
-        }
+            preview_lines(input_path, &found)
+        })();
+        let _ = match result {
+            Ok(previews) => tx.send(ReplaceMessage::Preview(previews)),
+            Err(e) => tx.send(ReplaceMessage::Error(e.to_string())),
+        };
     }
 
-    fn replace_all_inner(
+    /// Streams the replaced content into `output_file`, returning the number of
+    /// replacements made. `on_match` sees the absolute offset, old bytes and new
+    /// bytes of each replacement; returning `false` stops the run early.
+    #[allow(clippy::too_many_arguments)]
+    fn replace_all_inner<W: Write, F: FnMut(usize, &[u8], &[u8]) -> bool>(
         input_path: &Path,
-        output_path: &Path,
+        output_file: &mut W,
         query: &str,
         replace_with: &str,
-        use_regex: bool,
+        search_options: SearchOptions,
+        range: Option<Range<usize>>,
+        limits: StreamLimits,
         tx: &Sender<ReplaceMessage>,
-        cancel_token: Arc<AtomicBool>,
-    ) -> Result<()> {
+        cancel_token: &AtomicBool,
+        mut on_match: F,
+    ) -> Result<usize> {
+        if search_options.hex_bytes {
+            // The replacement is text, which cannot stand in for raw bytes
+            anyhow::bail!("Replace is not available for hex byte searches");
+        }
+        let regex = build_bytes_regex(query, &search_options).map_err(anyhow::Error::msg)?;
+        let mut partial =
+            PartialMatchFinder::new(query, &search_options).map_err(anyhow::Error::msg)?;
         let mut input_file = File::open(input_path)?;
         let file_len = input_file.metadata()?.len() as usize;
-        let mut output_file = BufWriter::new(File::create(output_path)?);
+        let mut replacements = 0;
 
-        let regex = if use_regex {
-            Regex::new(query)?
-        } else {
-            let pattern = format!("(?i){}", regex::escape(query));
-            Regex::new(&pattern)?
+        let (range_start, range_end) = match range {
+            Some(range) => {
+                let end = range.end.min(file_len);
+                (range.start.min(end), end)
+            }
+            None => (0, file_len),
         };
 
-        let replace_with_bytes = replace_with.as_bytes();
+        // Bytes before the range are copied unchanged
+        std::io::copy(&mut (&mut input_file).take(range_start as u64), output_file)?;
+        // Only the range itself is fed through the matcher
+        let mut section = (&mut input_file).take((range_end - range_start) as u64);
 
-        // Buffer size: 1MB
-        const BUFFER_SIZE: usize = 1024 * 1024;
-        // Overlap: enough to cover max match length.
-        const OVERLAP_SIZE: usize = 4096;
+        let replace_with_bytes = replace_with.as_bytes();
+        // Bytes kept before `pos` so look-behind assertions see the text they follow
+        const CONTEXT: usize = 4;
 
-        let mut buffer = vec![0u8; BUFFER_SIZE + OVERLAP_SIZE];
+        // `buffer` holds the section from `base` on; everything before `pos` is written.
+        // It grows by a chunk at a time for as long as a match may still be in
+        // progress at its end.
+        let mut buffer = Vec::new();
+        let mut base = 0;
+        let mut pos = 0;
+        let mut last_match_end = None;
         let mut eof = false;
 
-        // Initial fill
-        let n = input_file.read(&mut buffer[0..BUFFER_SIZE])?;
-        let mut buffer_len = n;
-        if n < BUFFER_SIZE {
-            eof = true;
-        }
-
-        let mut processed_offset = 0;
-
-        while buffer_len > 0 {
+        while !eof {
             if cancel_token.load(Ordering::Relaxed) {
-                return Ok(());
+                return Ok(replacements);
             }
 
-            // Ensure we end at a char boundary to avoid splitting UTF-8 chars
-            // even though we use bytes regex, we want to respect text boundaries if possible.
-            let mut valid_len = buffer_len;
-            while valid_len > 0 && !is_utf8_char_boundary(buffer[valid_len]) {
-                valid_len -= 1;
-            }
-            if valid_len == 0 && buffer_len > 0 {
-                valid_len = buffer_len;
-            }
+            let filled = buffer.len();
+            buffer.resize(filled + limits.chunk_size, 0);
+            let n = read_full(&mut section, &mut buffer[filled..])?;
+            buffer.truncate(filled + n);
+            eof = n < limits.chunk_size;
 
-            let chunk_bytes = &buffer[..valid_len];
-
-            let safe_zone_end = if eof {
-                valid_len
+            // Matches starting before `settled` come out the same however the text goes on
+            let settled = if eof {
+                buffer.len()
             } else {
-                valid_len.saturating_sub(OVERLAP_SIZE)
+                partial.earliest_partial(&buffer, pos)
             };
 
-            let mut last_match_end = 0;
-
-            for cap in regex.captures_iter(chunk_bytes) {
+            let mut written = pos;
+            let mut at = pos;
+            while at <= buffer.len() {
+                let Some(cap) = regex.captures_at(&buffer, at) else {
+                    break;
+                };
                 let mat = cap.get(0).unwrap();
-                if mat.start() >= safe_zone_end {
+                if mat.is_empty() && Some(mat.end()) == last_match_end {
+                    // As in `captures_iter`, no empty match right where the last one ended
+                    at = mat.end() + 1;
+                    continue;
+                }
+                // At the end of input even an empty match right at `settled` counts
+                if mat.start() >= settled && !eof {
                     break;
                 }
 
                 // Write text before match
-                output_file.write_all(&chunk_bytes[last_match_end..mat.start()])?;
+                output_file.write_all(&buffer[written..mat.start()])?;
 
                 // Expand replacement
                 let mut dst = Vec::new();
                 cap.expand(replace_with_bytes, &mut dst);
                 output_file.write_all(&dst)?;
+                replacements += 1;
 
-                last_match_end = mat.end();
+                written = mat.end();
+                at = mat.end();
+                last_match_end = Some(mat.end());
+                if !on_match(range_start + base + mat.start(), mat.as_bytes(), &dst) {
+                    return Ok(replacements);
+                }
             }
 
-            // If last_match_end > safe_zone_end, it means we processed a match that crossed the boundary.
-            // In that case, we have already written the replacement, so we should NOT write the original text.
-            // And we should shift the buffer starting from last_match_end.
+            // Nothing between the last match and `settled` can start a match
+            let flushed = settled.max(written);
+            output_file.write_all(&buffer[written..flushed])?;
+            if buffer.len() - flushed > limits.max_match_len {
+                anyhow::bail!(
+                    "A match at offset {} may be longer than the {} byte limit",
+                    range_start + base + flushed,
+                    limits.max_match_len
+                );
+            }
 
-            let shift_start = if last_match_end > safe_zone_end {
-                last_match_end
-            } else {
-                // Write remaining text in safe zone
-                output_file.write_all(&chunk_bytes[last_match_end..safe_zone_end])?;
-                safe_zone_end
-            };
+            let drop_len = flushed.saturating_sub(CONTEXT);
+            buffer.drain(..drop_len);
+            base += drop_len;
+            pos = flushed - drop_len;
+            last_match_end = last_match_end.and_then(|end: usize| end.checked_sub(drop_len));
 
-            // Shift remaining bytes to start
-            let remaining_bytes = &buffer[shift_start..buffer_len];
-            let remaining_len = remaining_bytes.len();
-
-            let remaining_vec = remaining_bytes.to_vec();
-            buffer[0..remaining_len].copy_from_slice(&remaining_vec);
-
-            // Fill the rest of the buffer
-            if !eof {
-                let bytes_to_read = BUFFER_SIZE - remaining_len;
-                let n =
-                    input_file.read(&mut buffer[remaining_len..remaining_len + bytes_to_read])?;
-                buffer_len = remaining_len + n;
-                if n == 0 {
-                    eof = true;
-                }
-            } else {
-                buffer_len = remaining_len; // We keep the remaining bytes if EOF (should be 0 if we processed everything)
-                if remaining_len == 0 {
-                    buffer_len = 0;
-                }
-            }
+            let _ = tx.send(ReplaceMessage::Progress(
+                range_start + base + pos,
+                file_len,
+                replacements,
+            ));
+        }
 
-            processed_offset += shift_start;
-            let _ = tx.send(ReplaceMessage::Progress(processed_offset, file_len));
+        if cancel_token.load(Ordering::Relaxed) {
+            return Ok(replacements);
         }
 
+        // Bytes after the range are copied unchanged
+        std::io::copy(&mut input_file, output_file)?;
+
         output_file.flush()?;
-        Ok(())
+        Ok(replacements)
     }
 }
 
-fn is_utf8_char_boundary(b: u8) -> bool {
-    // In UTF-8, continuation bytes start with 10xxxxxx (0x80 to 0xBF)
-    // So a byte is a char boundary if it is NOT a continuation byte.
-    // i.e. it is < 0x80 or >= 0xC0.
-    (b as i8) >= -0x40
+/// Turns `(offset, old_len, new_bytes)` matches, in file order, into previews of the
+/// lines they sit on.
+fn preview_lines(
+    input_path: &Path,
+    found: &[(usize, usize, Vec<u8>)],
+) -> Result<Vec<ReplacePreview>> {
+    if found.is_empty() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(input_path)?;
+    // SAFETY: the mapping is only read, and only for the duration of this call
+    let data = unsafe { memmap2::Mmap::map(&file)? };
+    let mut line = 1;
+    let mut counted = 0;
+    let mut previews = Vec::with_capacity(found.len());
+    for (offset, old_len, new_bytes) in found {
+        let offset = (*offset).min(data.len());
+        let end = (offset + old_len).min(data.len());
+        line += memchr::memchr_iter(b'\n', &data[counted..offset]).count();
+        counted = offset;
+
+        let line_start = memchr::memrchr(b'\n', &data[..offset]).map_or(0, |i| i + 1);
+        let line_end = memchr::memchr(b'\n', &data[end..]).map_or(data.len(), |i| end + i);
+        let start = line_start.max(offset.saturating_sub(PREVIEW_CONTEXT));
+        let stop = line_end.min(end + PREVIEW_CONTEXT);
+        let prefix = if start > line_start { "…" } else { "" };
+        let suffix = if stop < line_end { "…" } else { "" };
+
+        let before_match = String::from_utf8_lossy(&data[start..offset]);
+        let after_match = String::from_utf8_lossy(&data[end..stop]);
+        previews.push(ReplacePreview {
+            line,
+            offset,
+            before: format!(
+                "{prefix}{before_match}{}{after_match}{suffix}",
+                String::from_utf8_lossy(&data[offset..end])
+            ),
+            after: format!(
+                "{prefix}{before_match}{}{after_match}{suffix}",
+                String::from_utf8_lossy(new_bytes)
+            ),
+        });
+    }
+    Ok(previews)
+}
+
+/// Reads until `buf` is full or the input ends, returning the number of bytes read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
 }
 
 #[cfg(test)]
@@ -246,6 +478,13 @@ mod tests {
     use std::sync::mpsc;
     use tempfile::NamedTempFile;
 
+    fn regex_options() -> SearchOptions {
+        SearchOptions {
+            use_regex: true,
+            ..SearchOptions::default()
+        }
+    }
+
     #[test]
     fn test_replace_all_simple() -> Result<()> {
         let mut input = NamedTempFile::new()?;
@@ -263,7 +502,10 @@ mod tests {
             &output_path,
             "Hello",
             "Hi",
-            false,
+            SearchOptions::default(),
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
             tx,
             cancel_token,
         );
@@ -271,9 +513,9 @@ mod tests {
         // Wait for done
         loop {
             match rx.recv() {
-                Ok(ReplaceMessage::Done) => break,
+                Ok(ReplaceMessage::Done(_)) => break,
                 Ok(ReplaceMessage::Error(e)) => panic!("Error: {}", e),
-                Ok(ReplaceMessage::Progress(_, _)) => continue,
+                Ok(ReplaceMessage::Progress(..)) | Ok(ReplaceMessage::Preview(_)) => continue,
                 Err(_) => break,
             }
         }
@@ -300,16 +542,19 @@ mod tests {
             &output_path,
             r"Item (\d)",
             "Object $1",
-            true,
+            regex_options(),
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
             tx,
             cancel_token,
         );
 
         loop {
             match rx.recv() {
-                Ok(ReplaceMessage::Done) => break,
+                Ok(ReplaceMessage::Done(_)) => break,
                 Ok(ReplaceMessage::Error(e)) => panic!("Error: {}", e),
-                Ok(ReplaceMessage::Progress(_, _)) => continue,
+                Ok(ReplaceMessage::Progress(..)) | Ok(ReplaceMessage::Preview(_)) => continue,
                 Err(_) => break,
             }
         }
@@ -318,4 +563,476 @@ mod tests {
         assert_eq!(content, "Object 1, Object 2, Object 3");
         Ok(())
     }
+
+    #[test]
+    fn test_replace_all_in_range() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "a1 a2 a3 a4")?;
+        let input_path = input.path().to_path_buf();
+
+        let output = NamedTempFile::new()?;
+        let output_path = output.path().to_path_buf();
+
+        let (tx, rx) = mpsc::channel();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+
+        // Covers "a2 a" only: the trailing "a" of "a3" is cut off by the range end
+        Replacer::replace_all(
+            &input_path,
+            &output_path,
+            r"a\d",
+            "b",
+            regex_options(),
+            Some(3..7),
+            StreamLimits::default(),
+            SaveOptions::default(),
+            tx,
+            cancel_token,
+        );
+
+        loop {
+            match rx.recv() {
+                Ok(ReplaceMessage::Done(_)) => break,
+                Ok(ReplaceMessage::Error(e)) => panic!("Error: {}", e),
+                Ok(ReplaceMessage::Progress(..)) | Ok(ReplaceMessage::Preview(_)) => continue,
+                Err(_) => break,
+            }
+        }
+
+        let content = std::fs::read_to_string(&output_path)?;
+        assert_eq!(content, "a1 b a3 a4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_edits_single_pass() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "one two three four")?;
+        let output = NamedTempFile::new()?;
+
+        // Given out of order; offsets all refer to the original file
+        let edits = vec![
+            Edit::new(14, 4, "4"),
+            Edit::new(0, 3, "1"),
+            Edit::new(8, 0, "and "),
+            Edit::new(4, 3, "second"),
+        ];
+        Replacer::apply_edits(input.path(), output.path(), &edits, SaveOptions::default())?;
+        assert_eq!(
+            std::fs::read_to_string(output.path())?,
+            "1 second and three 4"
+        );
+
+        let sorted = sort_edits(&edits, 18)?;
+        assert_eq!(shifted_offset(&sorted, 8), 9);
+        assert_eq!(shifted_offset(&sorted, 9), 14);
+        assert_eq!(shifted_offset(&sorted, 15), 19);
+        assert_eq!(shifted_offset(&sorted, 18), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_edits_adjacent_and_overlapping() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "aaabbbccc")?;
+        let output = NamedTempFile::new()?;
+
+        // Adjacent edits touch but do not overlap
+        let adjacent = vec![Edit::new(3, 3, "B"), Edit::new(0, 3, "AAAA")];
+        Replacer::apply_edits(
+            input.path(),
+            output.path(),
+            &adjacent,
+            SaveOptions::default(),
+        )?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "AAAABccc");
+
+        let overlapping = vec![Edit::new(0, 4, "x"), Edit::new(3, 3, "y")];
+        assert!(Replacer::apply_edits(
+            input.path(),
+            output.path(),
+            &overlapping,
+            SaveOptions::default()
+        )
+        .is_err());
+        let past_end = vec![Edit::new(8, 2, "z")];
+        assert!(Replacer::apply_edits(
+            input.path(),
+            output.path(),
+            &past_end,
+            SaveOptions::default()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_edits_in_place() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "cat bat rat")?;
+        let path = input.path().to_path_buf();
+
+        let same_length = [Edit::new(8, 3, "hat"), Edit::new(0, 3, "dog")];
+        Replacer::apply_edits(&path, &path, &same_length, SaveOptions::default())?;
+        assert_eq!(std::fs::read_to_string(&path)?, "dog bat hat");
+
+        let shorter = [Edit::new(4, 3, "b")];
+        Replacer::apply_edits(&path, &path, &shorter, SaveOptions { backup: true })?;
+        assert_eq!(std::fs::read_to_string(&path)?, "dog b hat");
+        assert_eq!(
+            std::fs::read_to_string(crate::save::backup_path(&path))?,
+            "dog bat hat"
+        );
+        let _ = std::fs::remove_file(crate::save::backup_path(&path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_all_in_place_with_backup() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "warn a\nwarn b\ninfo c\n")?;
+
+        let (tx, rx) = mpsc::channel();
+        Replacer::replace_all(
+            &path,
+            &path,
+            "warn",
+            "WARNING",
+            SearchOptions::default(),
+            None,
+            StreamLimits::default(),
+            SaveOptions { backup: true },
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ReplaceMessage::Done(2))));
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "WARNING a\nWARNING b\ninfo c\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(crate::save::backup_path(&path))?,
+            "warn a\nwarn b\ninfo c\n"
+        );
+
+        // A cancelled run leaves the file untouched
+        let (tx, rx) = mpsc::channel();
+        Replacer::replace_all(
+            &path,
+            &path,
+            "info",
+            "INFO",
+            SearchOptions::default(),
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
+            tx,
+            Arc::new(AtomicBool::new(true)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ReplaceMessage::Error(_))));
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "WARNING a\nWARNING b\ninfo c\n"
+        );
+
+        // So does a hex byte search, whose matches a text replacement cannot stand for
+        let (tx, rx) = mpsc::channel();
+        Replacer::replace_all(
+            &path,
+            &path,
+            "69 6E",
+            "XX",
+            SearchOptions {
+                hex_bytes: true,
+                ..Default::default()
+            },
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ReplaceMessage::Error(_))));
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            "WARNING a\nWARNING b\ninfo c\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_and_running_counts() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("app.log");
+        let content = "start\nerror 1 and error 2\nok\nerror 3\n";
+        std::fs::write(&path, content)?;
+
+        let (tx, rx) = mpsc::channel();
+        Replacer::preview_replace_all(
+            &path,
+            r"error (\d)",
+            "E$1",
+            regex_options(),
+            None,
+            StreamLimits::default(),
+            2,
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        let previews = match rx.iter().last() {
+            Some(ReplaceMessage::Preview(previews)) => previews,
+            _ => panic!("expected a preview"),
+        };
+        assert_eq!(
+            previews,
+            vec![
+                ReplacePreview {
+                    line: 2,
+                    offset: 6,
+                    before: "error 1 and error 2".to_string(),
+                    after: "E1 and error 2".to_string(),
+                },
+                ReplacePreview {
+                    line: 2,
+                    offset: 18,
+                    before: "error 1 and error 2".to_string(),
+                    after: "error 1 and E2".to_string(),
+                },
+            ]
+        );
+        // Previewing writes nothing
+        assert_eq!(std::fs::read_to_string(&path)?, content);
+
+        let (tx, rx) = mpsc::channel();
+        Replacer::replace_all(
+            &path,
+            &path,
+            r"error (\d)",
+            "E$1",
+            regex_options(),
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        let messages: Vec<_> = rx.iter().collect();
+        assert!(messages
+            .iter()
+            .any(|m| matches!(m, ReplaceMessage::Progress(_, _, 3))));
+        assert!(matches!(messages.last(), Some(ReplaceMessage::Done(3))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_all_changes_what_search_finds() -> Result<()> {
+        use crate::search_engine::SearchEngine;
+
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.log");
+        let output = dir.path().join("output.log");
+        let text = "Error: error_code ERROR errors\nTERROR (err) Straße STRASSE err\n";
+        std::fs::write(&input, text)?;
+
+        for (query, use_regex) in [
+            ("error", false),
+            ("(err)", false),
+            ("straße", false),
+            (r"err\w*", true),
+            ("E.ROR", true),
+        ] {
+            for case_sensitive in [false, true] {
+                for whole_word in [false, true] {
+                    let options = SearchOptions {
+                        use_regex,
+                        case_sensitive,
+                        whole_word,
+                        ..SearchOptions::default()
+                    };
+                    let mut engine = SearchEngine::new();
+                    engine.set_query_with_options(query.to_string(), options);
+                    let mut expected = String::new();
+                    let mut pos = 0;
+                    for (start, end) in engine.find_in_text(text) {
+                        expected.push_str(&text[pos..start]);
+                        expected.push_str("<>");
+                        pos = end;
+                    }
+                    expected.push_str(&text[pos..]);
+
+                    let (tx, rx) = mpsc::channel();
+                    Replacer::replace_all(
+                        &input,
+                        &output,
+                        query,
+                        "<>",
+                        options,
+                        None,
+                        StreamLimits::default(),
+                        SaveOptions::default(),
+                        tx,
+                        Arc::new(AtomicBool::new(false)),
+                    );
+                    assert!(matches!(rx.iter().last(), Some(ReplaceMessage::Done(_))));
+                    assert_eq!(
+                        std::fs::read_to_string(&output)?,
+                        expected,
+                        "{query:?} with {options:?}"
+                    );
+                }
+            }
+        }
+
+        // Approximate matches have no exact replacement
+        let (tx, rx) = mpsc::channel();
+        Replacer::replace_all(
+            &input,
+            &output,
+            "eror",
+            "<>",
+            SearchOptions {
+                max_edits: Some(1),
+                ..SearchOptions::default()
+            },
+            None,
+            StreamLimits::default(),
+            SaveOptions::default(),
+            tx,
+            Arc::new(AtomicBool::new(false)),
+        );
+        assert!(matches!(rx.iter().last(), Some(ReplaceMessage::Error(_))));
+        Ok(())
+    }
+
+    /// Small deterministic generator, so a failing case can be replayed from its seed.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn stream(
+        path: &Path,
+        query: &str,
+        replace_with: &str,
+        options: SearchOptions,
+        limits: StreamLimits,
+    ) -> Result<(Vec<u8>, usize)> {
+        let (tx, _rx) = mpsc::channel();
+        let mut output = Vec::new();
+        let count = Replacer::replace_all_inner(
+            path,
+            &mut output,
+            query,
+            replace_with,
+            options,
+            None,
+            limits,
+            &tx,
+            &AtomicBool::new(false),
+            |_, _, _| true,
+        )?;
+        Ok((output, count))
+    }
+
+    #[test]
+    fn test_streaming_replace_fuzz() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("fuzz.bin");
+        let literal = |whole_word| SearchOptions {
+            case_sensitive: true,
+            whole_word,
+            ..SearchOptions::default()
+        };
+        let cases = [
+            ("ab", literal(false)),
+            ("ab", literal(true)),
+            ("é", SearchOptions::default()),
+            ("a+", regex_options()),
+            ("a.*b", regex_options()),
+            (r"(?s)a.*?b", regex_options()),
+            ("b*", regex_options()),
+            (r"(?m)^a|b$", regex_options()),
+            ("a{3,}", regex_options()),
+            ("(a|ab)(c|bcd)", regex_options()),
+            (r"[^\n]{5}", regex_options()),
+            (r"\w+\b", regex_options()),
+            ("c|", regex_options()),
+        ];
+        let alphabet: [&[u8]; 10] = [
+            b"a",
+            b"b",
+            b"c",
+            b"d",
+            b"\n",
+            b" ",
+            "é".as_bytes(),
+            "É".as_bytes(),
+            b"\xff",
+            b"\x80",
+        ];
+
+        for seed in 1..=12u64 {
+            let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut text = Vec::new();
+            for _ in 0..rng.below(300) {
+                text.extend_from_slice(alphabet[rng.below(alphabet.len())]);
+            }
+            std::fs::write(&path, &text)?;
+
+            for (query, options) in cases {
+                let regex = build_bytes_regex(query, &options).unwrap();
+                let expected = regex.replace_all(&text, b"[$0]".as_slice());
+                for chunk_size in [1, 2, 3, 7, 64, 1024] {
+                    let limits = StreamLimits {
+                        chunk_size,
+                        max_match_len: usize::MAX,
+                    };
+                    let (output, count) = stream(&path, query, "[$0]", options, limits)?;
+                    assert_eq!(
+                        output,
+                        expected.as_ref(),
+                        "{query:?}, seed {seed}, chunk {chunk_size}"
+                    );
+                    assert_eq!(count, regex.find_iter(&text).count());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_replace_long_match_and_limit() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("long.log");
+        let body = "x".repeat(20_000);
+        std::fs::write(&path, format!("head BEGIN{body}END tail BEGIN"))?;
+        let limits = StreamLimits {
+            chunk_size: 1024,
+            max_match_len: 1024 * 1024,
+        };
+
+        // Far longer than a chunk, still replaced whole
+        let (output, count) = stream(&path, r"BEGIN[\s\S]*?END", "-", regex_options(), limits)?;
+        assert_eq!(count, 1);
+        assert_eq!(output, b"head - tail BEGIN");
+
+        // A match that may run past the limit fails instead of being split
+        let limits = StreamLimits {
+            chunk_size: 1024,
+            max_match_len: 4096,
+        };
+        let err = stream(&path, r"BEGIN[\s\S]*?END", "-", regex_options(), limits).unwrap_err();
+        assert!(err.to_string().contains("4096 byte limit"), "{err}");
+        // Text that only looks like the start of a match is let go once ruled out
+        let (_, count) = stream(&path, "BEGINNING", "-", regex_options(), limits)?;
+        assert_eq!(count, 0);
+        Ok(())
+    }
 }
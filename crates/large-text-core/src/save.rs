@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How `write_atomic` treats the file it replaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SaveOptions {
+    /// Keep the previous content as `<name>.bak` next to the file.
+    pub backup: bool,
+}
+
+/// Distinguishes temp files created by one process in the same directory.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Path of the backup kept for `path`: the full file name plus `.bak`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    append_to_file_name(path, ".bak")
+}
+
+/// Replaces `target` with the content produced by `write`, crash-safely.
+///
+/// The content goes to a uniquely named temp file in the same directory, takes on the
+/// permissions, ownership and extended attributes of the existing file, and is
+/// fsynced before being renamed over `target`; the directory is fsynced after. Until
+/// the rename, `target` is untouched, so a failure at any point leaves the old content
+/// in place and removes the temp file.
+pub fn write_atomic(
+    target: &Path,
+    options: SaveOptions,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+    let (file, temp_path) = create_temp(target)?;
+    let result = fill_temp(file, target, &temp_path, options, write)
+        .and_then(|()| {
+            std::fs::rename(&temp_path, target)
+                .with_context(|| format!("Failed to replace {}", target.display()))
+        })
+        .and_then(|()| sync_dir(target));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn fill_temp(
+    file: File,
+    target: &Path,
+    temp_path: &Path,
+    options: SaveOptions,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+
+    if let Ok(metadata) = std::fs::metadata(target) {
+        file.set_permissions(metadata.permissions())?;
+        copy_owner_and_xattrs(&file, &metadata, target, temp_path);
+    }
+    file.sync_all()?;
+
+    if options.backup && target.exists() {
+        make_backup(target)?;
+    }
+    Ok(())
+}
+
+/// Creates `.<name>.<pid>-<n>.tmp` next to `target`, never reusing an existing file.
+fn create_temp(target: &Path) -> Result<(File, PathBuf)> {
+    let dir = parent_dir(target);
+    let name = target
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", target.display()))?;
+    loop {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp_path = dir.join(temp_name);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((file, temp_path)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to create a temp file in {}", dir.display()))
+            }
+        }
+    }
+}
+
+/// Points `<name>.bak` at the current content of `target`. A hard link costs nothing
+/// and keeps the old inode alive once the new file is renamed over `target`.
+fn make_backup(target: &Path) -> Result<()> {
+    let backup = backup_path(target);
+    match std::fs::remove_file(&backup) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    if std::fs::hard_link(target, &backup).is_err() {
+        std::fs::copy(target, &backup)?;
+        File::open(&backup)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_owner_and_xattrs(file: &File, metadata: &std::fs::Metadata, target: &Path, temp: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    // Best effort: only root may give a file away, and not every filesystem has xattrs
+    let _ = std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+    if let Ok(names) = xattr::list(target) {
+        for name in names {
+            if let Ok(Some(value)) = xattr::get(target, &name) {
+                let _ = xattr::set(temp, &name, &value);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_owner_and_xattrs(_: &File, _: &std::fs::Metadata, _: &Path, _: &Path) {}
+
+/// Makes the rename itself durable.
+#[cfg(unix)]
+fn sync_dir(target: &Path) -> Result<()> {
+    File::open(parent_dir(target))?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn append_to_file_name(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_atomic_keeps_neighbours_and_backup() -> Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("data.log");
+        std::fs::write(&target, "old")?;
+        // A file the old `.tmp` scheme would have clobbered
+        let neighbour = dir.path().join("data.tmp");
+        std::fs::write(&neighbour, "keep me")?;
+
+        write_atomic(&target, SaveOptions { backup: true }, |w| {
+            w.write_all(b"new")?;
+            Ok(())
+        })?;
+        assert_eq!(std::fs::read_to_string(&target)?, "new");
+        assert_eq!(std::fs::read_to_string(backup_path(&target))?, "old");
+        assert_eq!(std::fs::read_to_string(&neighbour)?, "keep me");
+        // Nothing but the file, its backup and the neighbour is left behind
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original() -> Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("data.log");
+        std::fs::write(&target, "old")?;
+
+        let result = write_atomic(&target, SaveOptions::default(), |w| {
+            w.write_all(b"partial")?;
+            anyhow::bail!("disk full")
+        });
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&target)?, "old");
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_preserves_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir()?;
+        let target = dir.path().join("script.sh");
+        std::fs::write(&target, "old")?;
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o751))?;
+        // Extended attributes are carried over where the filesystem supports them
+        let has_xattr = xattr::set(&target, "user.origin", b"test").is_ok();
+
+        write_atomic(&target, SaveOptions::default(), |w| {
+            w.write_all(b"new")?;
+            Ok(())
+        })?;
+        let mode = std::fs::metadata(&target)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o751);
+        if has_xattr {
+            assert_eq!(xattr::get(&target, "user.origin")?, Some(b"test".to_vec()));
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 use crate::file_reader::FileReader;
-use regex::Regex;
+use crate::query::{LiteralMatcher, Matcher, QueryMatch, SearchOptions};
+use crate::timeline::{HistogramBuilder, TimeHistogram, TimelineConfig};
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+use std::ops::Range;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc::SyncSender,
@@ -7,11 +11,24 @@ use std::sync::{
 };
 use std::thread;
 
+/// Default bytes each `count_matches` worker scans per batch.
+pub const DEFAULT_BATCH_SIZE: usize = 4 * 1024 * 1024; // 4MB
+
 pub struct SearchEngine {
-    query: String,
-    use_regex: bool,
-    case_sensitive: bool,
-    regex: Option<Regex>,
+    // One or more patterns; each result records which one matched
+    patterns: Vec<String>,
+    options: SearchOptions,
+    matcher: Option<Matcher>,
+    // Compile error for the current query, reported by count/fetch
+    error: Option<String>,
+    // Byte range to search in; None searches the whole file
+    range: Option<Range<usize>>,
+    // When set, `count_matches` also buckets matches by their line's timestamp
+    timeline: Option<TimelineConfig>,
+    // Bytes scanned per batch by each `count_matches` worker
+    batch_size: usize,
+    // Upper bound on `count_matches` workers; None uses every core
+    max_threads: Option<usize>,
     results: Vec<SearchResult>,
     total_results: usize,
 }
@@ -20,6 +37,8 @@ pub struct SearchEngine {
 pub struct SearchResult {
     pub byte_offset: usize,
     pub match_len: usize,
+    /// Index into the engine's pattern list (always 0 for single-pattern searches).
+    pub pattern_index: usize,
 }
 
 pub struct ChunkSearchResult {
@@ -35,6 +54,11 @@ pub enum SearchType {
 pub enum SearchMessage {
     ChunkResult(ChunkSearchResult),
     CountResult(usize),
+    /// Per-pattern counts from one worker, sent alongside `CountResult` for multi-pattern searches.
+    PatternCountResult(Vec<usize>),
+    /// Matches from one worker bucketed by time, sent alongside `CountResult` when a
+    /// timeline is configured.
+    HistogramResult(TimeHistogram),
     Done(SearchType),
     Error(String),
 }
@@ -48,49 +72,128 @@ impl Default for SearchEngine {
 impl SearchEngine {
     pub fn new() -> Self {
         Self {
-            query: String::new(),
-            use_regex: false,
-            case_sensitive: false,
-            regex: None,
+            patterns: Vec::new(),
+            options: SearchOptions::default(),
+            matcher: None,
+            error: None,
+            range: None,
+            timeline: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_threads: None,
             results: Vec::new(),
             total_results: 0,
         }
     }
 
-    pub fn set_query(&mut self, query: String, use_regex: bool, case_sensitive: bool) {
-        self.query = query;
-        self.use_regex = use_regex;
-        self.case_sensitive = case_sensitive;
+    /// Limits `count_matches` and `fetch_matches` to matches lying entirely inside `range`.
+    pub fn set_range(&mut self, range: Option<Range<usize>>) {
+        self.range = range;
+    }
 
-        let pattern = if use_regex {
-            if !case_sensitive {
-                format!("(?i){}", self.query)
-            } else {
-                self.query.clone()
+    pub fn range(&self) -> Option<Range<usize>> {
+        self.range.clone()
+    }
+
+    /// Sets how many bytes each `count_matches` worker scans at a time, trading memory
+    /// for fewer batch boundaries.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Caps the number of `count_matches` workers; None uses every core.
+    pub fn set_max_threads(&mut self, max_threads: Option<usize>) {
+        self.max_threads = max_threads.map(|n| n.max(1));
+    }
+
+    /// Makes `count_matches` also send a `HistogramResult` of matches over time.
+    pub fn set_timeline(&mut self, timeline: Option<TimelineConfig>) {
+        self.timeline = timeline;
+    }
+
+    /// Resolves the configured range against the file length.
+    fn bounds(&self, file_len: usize) -> (usize, usize) {
+        match &self.range {
+            Some(range) => {
+                let end = range.end.min(file_len);
+                (range.start.min(end), end)
             }
-        } else if !case_sensitive {
-            format!("(?i){}", regex::escape(&self.query))
-        } else {
-            regex::escape(&self.query)
-        };
+            None => (0, file_len),
+        }
+    }
+
+    pub fn set_query(&mut self, query: String, use_regex: bool, case_sensitive: bool) {
+        self.set_query_with_options(
+            query,
+            SearchOptions {
+                use_regex,
+                case_sensitive,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn set_query_with_options(&mut self, query: String, options: SearchOptions) {
+        self.set_patterns(vec![query], options);
+    }
 
-        self.regex = Regex::new(&pattern).ok();
+    /// Searches for several patterns at once. Empty patterns are ignored.
+    pub fn set_patterns(&mut self, patterns: Vec<String>, options: SearchOptions) {
+        self.patterns = patterns.into_iter().filter(|p| !p.is_empty()).collect();
+        self.options = options;
+
+        match Matcher::new_many(&self.patterns, &options) {
+            Ok(matcher) => {
+                self.matcher = Some(matcher);
+                self.error = None;
+            }
+            Err(e) => {
+                self.matcher = None;
+                self.error = Some(e);
+            }
+        }
 
         self.results.clear();
     }
 
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
     pub fn find_in_text(&self, text: &str) -> Vec<(usize, usize)> {
-        let mut matches = Vec::new();
-        if self.query.is_empty() {
-            return matches;
+        self.find_matches_in_text(text)
+            .into_iter()
+            .map(|m| (m.start, m.end))
+            .collect()
+    }
+
+    /// Like `find_in_text`, but also reports which pattern each match belongs to.
+    pub fn find_matches_in_text(&self, text: &str) -> Vec<QueryMatch> {
+        if self.patterns.is_empty() {
+            return Vec::new();
         }
 
-        if let Some(re) = &self.regex {
-            for m in re.find_iter(text) {
-                matches.push((m.start(), m.end()));
-            }
+        match &self.matcher {
+            Some(matcher) => matcher.find_iter(text).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Bytes re-scanned at batch boundaries so matches straddling them are not lost.
+    fn overlap(&self) -> usize {
+        let longest = self.patterns.iter().map(|p| p.len()).max().unwrap_or(0);
+        let query_len = longest + self.options.max_edits.unwrap_or(0);
+        query_len.saturating_sub(1).max(1000)
+    }
+
+    fn literal_matcher(&self, reader: &FileReader) -> Option<LiteralMatcher> {
+        match self.patterns.as_slice() {
+            [query] => LiteralMatcher::new(query, &self.options, reader.encoding()),
+            _ => None,
         }
-        matches
     }
 
     pub fn count_matches(
@@ -99,8 +202,8 @@ impl SearchEngine {
         tx: SyncSender<SearchMessage>,
         cancel_token: Arc<AtomicBool>,
     ) {
-        let file_len = reader.len();
-        if file_len == 0 || self.query.is_empty() {
+        let (range_start, range_end) = self.bounds(reader.len());
+        if range_start >= range_end || self.patterns.is_empty() {
             let _ = tx.send(SearchMessage::CountResult(0));
             let _ = tx.send(SearchMessage::Done(SearchType::Count));
             return;
@@ -109,60 +212,72 @@ impl SearchEngine {
         let num_threads = std::thread::available_parallelism()
             .map(|n| n.get())
             .unwrap_or(1)
+            .min(self.max_threads.unwrap_or(usize::MAX))
             .max(1);
+        let batch_size = self.batch_size;
 
-        let chunk_size = file_len.div_ceil(num_threads);
-        let query_len = self.query.len();
-        let overlap = query_len.saturating_sub(1).max(1000);
+        let chunk_size = (range_end - range_start).div_ceil(num_threads);
+        let overlap = self.overlap();
+        let pattern_count = self.patterns.len();
 
-        let regex = self.regex.clone();
+        let matcher = self.matcher.clone();
+        let literal = self.literal_matcher(&reader);
+        let error = self.error.clone().unwrap_or_default();
+        let timeline = self.timeline.clone();
 
         thread::spawn(move || {
             let mut handles = vec![];
 
             for i in 0..num_threads {
-                let thread_start = i * chunk_size;
-                if thread_start >= file_len {
+                let thread_start = range_start + i * chunk_size;
+                if thread_start >= range_end {
                     break;
                 }
-                let thread_end = (thread_start + chunk_size).min(file_len);
+                let thread_end = (thread_start + chunk_size).min(range_end);
 
                 let reader_clone = reader.clone();
                 let tx_clone = tx.clone();
-                let regex_clone = regex.clone();
+                let matcher_clone = matcher.clone();
+                let literal_clone = literal.clone();
+                let error_clone = error.clone();
+                let timeline_clone = timeline.clone();
                 let cancel_token_clone = cancel_token.clone();
 
                 let handle = thread::spawn(move || {
-                    if let Some(regex) = regex_clone {
+                    if let Some(matcher) = matcher_clone {
                         let mut pos = thread_start;
                         // Process in smaller batches to avoid high memory usage
-                        const BATCH_SIZE: usize = 4 * 1024 * 1024; // 4MB
                         let mut local_count = 0;
+                        let mut local_pattern_counts = vec![0usize; pattern_count];
+                        let mut histogram = timeline_clone
+                            .as_ref()
+                            .map(|config| HistogramBuilder::new(&reader_clone, config));
 
                         while pos < thread_end {
                             if cancel_token_clone.load(Ordering::Relaxed) {
                                 return;
                             }
 
-                            let batch_end = (pos + BATCH_SIZE).min(thread_end);
+                            let batch_end = (pos + batch_size).min(thread_end);
                             // Add overlap to catch matches crossing batch boundaries
-                            let read_end = (batch_end + overlap).min(file_len);
+                            let read_end = (batch_end + overlap).min(range_end);
 
                             let chunk_bytes = reader_clone.get_bytes(pos, read_end);
-                            let chunk_text = match std::str::from_utf8(chunk_bytes) {
-                                Ok(t) => t.to_string(),
-                                Err(_) => {
-                                    let (cow, _, _) = reader_clone.encoding().decode(chunk_bytes);
-                                    cow.into_owned()
+                            // Plain literals are matched on the raw bytes without decoding
+                            let chunk_text;
+                            let matches = match &literal_clone {
+                                Some(literal) => literal.find_iter(chunk_bytes),
+                                None => {
+                                    chunk_text = decode_chunk(chunk_bytes, reader_clone.encoding());
+                                    matcher.find_iter(&chunk_text)
                                 }
                             };
 
-                            for mat in regex.find_iter(&chunk_text) {
+                            for mat in matches {
                                 if cancel_token_clone.load(Ordering::Relaxed) {
                                     return;
                                 }
-                                let match_start = mat.start();
-                                let absolute_start = pos + match_start;
+                                let absolute_start = pos + mat.start;
 
                                 // Only accept matches starting in [pos, batch_end)
                                 if absolute_start >= batch_end {
@@ -170,13 +285,25 @@ impl SearchEngine {
                                 }
 
                                 local_count += 1;
+                                local_pattern_counts[mat.pattern] += 1;
+                                if let Some(histogram) = histogram.as_mut() {
+                                    histogram.add_match(absolute_start);
+                                }
                             }
 
                             pos = batch_end;
                         }
                         let _ = tx_clone.send(SearchMessage::CountResult(local_count));
+                        if pattern_count > 1 {
+                            let _ = tx_clone
+                                .send(SearchMessage::PatternCountResult(local_pattern_counts));
+                        }
+                        if let Some(histogram) = histogram {
+                            let _ =
+                                tx_clone.send(SearchMessage::HistogramResult(histogram.finish()));
+                        }
                     } else {
-                        let _ = tx_clone.send(SearchMessage::Error("Invalid regex".to_string()));
+                        let _ = tx_clone.send(SearchMessage::Error(error_clone));
                     }
                 });
                 handles.push(handle);
@@ -199,35 +326,37 @@ impl SearchEngine {
         max_results: usize,
         cancel_token: Arc<AtomicBool>,
     ) {
-        let file_len = reader.len();
-        if file_len == 0 || self.query.is_empty() {
+        let (range_start, range_end) = self.bounds(reader.len());
+        if range_start >= range_end || self.patterns.is_empty() {
             let _ = tx.send(SearchMessage::Done(SearchType::Fetch));
             return;
         }
 
-        let regex = self.regex.clone();
-        let query_len = self.query.len();
-        let overlap = query_len.saturating_sub(1).max(1000);
+        let matcher = self.matcher.clone();
+        let literal = self.literal_matcher(&reader);
+        let error = self.error.clone().unwrap_or_default();
+        let overlap = self.overlap();
 
         thread::spawn(move || {
-            if let Some(regex) = regex {
+            if let Some(matcher) = matcher {
                 const CHUNK_SIZE: usize = 10 * 1024 * 1024; // 10 MB chunks
-                let mut chunk_start = start_offset;
+                let mut chunk_start = start_offset.max(range_start);
                 let mut results_found = 0;
 
-                while chunk_start < file_len && results_found < max_results {
+                while chunk_start < range_end && results_found < max_results {
                     if cancel_token.load(Ordering::Relaxed) {
                         return;
                     }
 
-                    let chunk_end = (chunk_start + CHUNK_SIZE).min(file_len);
+                    let chunk_end = (chunk_start + CHUNK_SIZE).min(range_end);
                     let chunk_bytes = reader.get_bytes(chunk_start, chunk_end);
 
-                    let chunk_text = match std::str::from_utf8(chunk_bytes) {
-                        Ok(t) => t.to_string(),
-                        Err(_) => {
-                            let (cow, _, _) = reader.encoding().decode(chunk_bytes);
-                            cow.into_owned()
+                    let chunk_text;
+                    let matches = match &literal {
+                        Some(literal) => literal.find_iter(chunk_bytes),
+                        None => {
+                            chunk_text = decode_chunk(chunk_bytes, reader.encoding());
+                            matcher.find_iter(&chunk_text)
                         }
                     };
 
@@ -235,14 +364,14 @@ impl SearchEngine {
 
                     // Define the valid range for starting positions in this chunk
                     // We want to process matches that start in [chunk_start, chunk_end - overlap)
-                    // Unless we are at the end of the file, then [chunk_start, chunk_end)
-                    let valid_end = if chunk_end >= file_len {
-                        file_len
+                    // Unless we are at the end of the range, then [chunk_start, chunk_end)
+                    let valid_end = if chunk_end >= range_end {
+                        range_end
                     } else {
                         chunk_end - overlap
                     };
 
-                    for mat in regex.find_iter(&chunk_text) {
+                    for mat in matches {
                         if cancel_token.load(Ordering::Relaxed) {
                             return;
                         }
@@ -250,8 +379,7 @@ impl SearchEngine {
                             break;
                         }
 
-                        let match_start = mat.start();
-                        let absolute_start = chunk_start + match_start;
+                        let absolute_start = chunk_start + mat.start;
 
                         // Skip matches that start beyond our valid range for this chunk
                         // They will be picked up by the next chunk which starts at `valid_end`
@@ -261,7 +389,8 @@ impl SearchEngine {
 
                         local_matches.push(SearchResult {
                             byte_offset: absolute_start,
-                            match_len: mat.end() - mat.start(),
+                            match_len: mat.end - mat.start,
+                            pattern_index: mat.pattern,
                         });
                         results_found += 1;
                     }
@@ -277,7 +406,7 @@ impl SearchEngine {
                     }
 
                     // Move to next chunk with overlap
-                    if chunk_end >= file_len {
+                    if chunk_end >= range_end {
                         break;
                     }
 
@@ -287,19 +416,32 @@ impl SearchEngine {
                     let _ = tx.send(SearchMessage::Done(SearchType::Fetch));
                 }
             } else {
-                let _ = tx.send(SearchMessage::Error("Invalid regex".to_string()));
+                let _ = tx.send(SearchMessage::Error(error));
             }
         });
     }
 
     pub fn clear(&mut self) {
-        self.query.clear();
+        self.patterns.clear();
         self.results.clear();
-        self.regex = None;
+        self.matcher = None;
+        self.error = None;
+        self.range = None;
+        self.timeline = None;
         self.total_results = 0;
     }
 }
 
+fn decode_chunk<'a>(bytes: &'a [u8], encoding: &'static Encoding) -> Cow<'a, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => {
+            let (cow, _, _) = encoding.decode(bytes);
+            cow
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,6 +474,21 @@ mod tests {
         assert_eq!(matches[1], (25, 28)); // "456"
     }
 
+    #[test]
+    fn test_find_in_text_whole_word() {
+        let mut engine = SearchEngine::new();
+        engine.set_query_with_options(
+            "test".to_string(),
+            SearchOptions {
+                whole_word: true,
+                ..Default::default()
+            },
+        );
+
+        let matches = engine.find_in_text("test testing attest test");
+        assert_eq!(matches, vec![(0, 4), (20, 24)]);
+    }
+
     #[test]
     fn test_count_matches() -> anyhow::Result<()> {
         let mut file = NamedTempFile::new()?;
@@ -361,4 +518,245 @@ mod tests {
         assert_eq!(count, 3);
         Ok(())
     }
+
+    fn collect_count(rx: &mpsc::Receiver<SearchMessage>) -> usize {
+        let mut count = 0;
+        loop {
+            match rx.recv() {
+                Ok(SearchMessage::CountResult(c)) => count += c,
+                Ok(SearchMessage::Done(SearchType::Count)) => break,
+                Ok(SearchMessage::Error(e)) => panic!("Error: {}", e),
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+        count
+    }
+
+    fn collect_fetch(rx: &mpsc::Receiver<SearchMessage>) -> Vec<SearchResult> {
+        let mut results = Vec::new();
+        loop {
+            match rx.recv() {
+                Ok(SearchMessage::ChunkResult(chunk)) => results.extend(chunk.matches),
+                Ok(SearchMessage::Done(SearchType::Fetch)) => break,
+                Ok(SearchMessage::Error(e)) => panic!("Error: {}", e),
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn test_fetch_fuzzy_matches() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "id=ORD-1234\nid=ORD-1243\nid=ORD-9999\nid=ORD-124")?;
+        let path = file.path().to_path_buf();
+
+        let reader = Arc::new(FileReader::new(path, detect_encoding(b""))?);
+        let mut engine = SearchEngine::new();
+        engine.set_query_with_options(
+            "ORD-1234".to_string(),
+            SearchOptions {
+                max_edits: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let (tx, rx) = mpsc::sync_channel(10);
+        engine.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+        // "ORD-1243" contains "ORD-124", which is a single deletion away
+        assert_eq!(collect_count(&rx), 3);
+
+        let (tx, rx) = mpsc::sync_channel(10);
+        engine.fetch_matches(reader, tx, 0, 100, Arc::new(AtomicBool::new(false)));
+        let offsets: Vec<usize> = collect_fetch(&rx).iter().map(|r| r.byte_offset).collect();
+        assert_eq!(offsets, vec![3, 15, 39]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_literal_fast_path_matches_regex_path() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        // Enough data to span several batches and chunks
+        for i in 0..200_000 {
+            writeln!(file, "line {} ERROR code=E{} Error error", i, i % 7)?;
+        }
+        let path = file.path().to_path_buf();
+        let reader = Arc::new(FileReader::new(path, detect_encoding(b""))?);
+
+        for case_sensitive in [false, true] {
+            let mut literal = SearchEngine::new();
+            literal.set_query("error".to_string(), false, case_sensitive);
+            let mut regex = SearchEngine::new();
+            regex.set_query("error".to_string(), true, case_sensitive);
+
+            let (tx, rx) = mpsc::sync_channel(100);
+            literal.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+            let literal_count = collect_count(&rx);
+            let (tx, rx) = mpsc::sync_channel(100);
+            regex.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+            assert_eq!(literal_count, collect_count(&rx));
+
+            let (tx, rx) = mpsc::sync_channel(100);
+            literal.fetch_matches(
+                reader.clone(),
+                tx,
+                0,
+                5000,
+                Arc::new(AtomicBool::new(false)),
+            );
+            let literal_results = collect_fetch(&rx);
+            let (tx, rx) = mpsc::sync_channel(100);
+            regex.fetch_matches(
+                reader.clone(),
+                tx,
+                0,
+                5000,
+                Arc::new(AtomicBool::new(false)),
+            );
+            let regex_results = collect_fetch(&rx);
+            assert_eq!(literal_results.len(), regex_results.len());
+            for (a, b) in literal_results.iter().zip(&regex_results) {
+                assert_eq!((a.byte_offset, a.match_len), (b.byte_offset, b.match_len));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_pattern_counts() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "E1 ok E2\nE1 E3\nE1")?;
+        let path = file.path().to_path_buf();
+
+        let reader = Arc::new(FileReader::new(path, detect_encoding(b""))?);
+        let mut engine = SearchEngine::new();
+        let patterns = ["E1", "E2", "E3", "E4"].map(String::from).to_vec();
+        engine.set_patterns(patterns, SearchOptions::default());
+
+        let (tx, rx) = mpsc::sync_channel(100);
+        engine.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+        let mut total = 0;
+        let mut per_pattern = vec![0; 4];
+        loop {
+            match rx.recv() {
+                Ok(SearchMessage::CountResult(c)) => total += c,
+                Ok(SearchMessage::PatternCountResult(counts)) => {
+                    for (sum, c) in per_pattern.iter_mut().zip(counts) {
+                        *sum += c;
+                    }
+                }
+                Ok(SearchMessage::Done(SearchType::Count)) => break,
+                Ok(SearchMessage::Error(e)) => panic!("Error: {}", e),
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+        assert_eq!(total, 5);
+        assert_eq!(per_pattern, vec![3, 1, 1, 0]);
+
+        let (tx, rx) = mpsc::sync_channel(100);
+        engine.fetch_matches(reader, tx, 0, 100, Arc::new(AtomicBool::new(false)));
+        let found: Vec<(usize, usize)> = collect_fetch(&rx)
+            .iter()
+            .map(|r| (r.byte_offset, r.pattern_index))
+            .collect();
+        assert_eq!(found, vec![(0, 0), (6, 1), (9, 0), (12, 2), (15, 0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_in_range() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        // "test" starts at 0, 5, 10 and 15
+        write!(file, "test\ntest\ntest\ntest")?;
+        let path = file.path().to_path_buf();
+
+        let reader = Arc::new(FileReader::new(path, detect_encoding(b""))?);
+        let mut engine = SearchEngine::new();
+        engine.set_query("test".to_string(), false, false);
+        // Ends in the middle of the third "test", which must not be counted
+        engine.set_range(Some(3..12));
+
+        let (tx, rx) = mpsc::sync_channel(10);
+        engine.count_matches(reader.clone(), tx, Arc::new(AtomicBool::new(false)));
+        assert_eq!(collect_count(&rx), 1);
+
+        engine.set_range(Some(5..19));
+        let (tx, rx) = mpsc::sync_channel(10);
+        engine.fetch_matches(reader, tx, 0, 100, Arc::new(AtomicBool::new(false)));
+        let offsets: Vec<usize> = collect_fetch(&rx).iter().map(|r| r.byte_offset).collect();
+        assert_eq!(offsets, vec![5, 10, 15]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_matches_small_batches() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "{}", "abc test ".repeat(100))?;
+        let reader = Arc::new(FileReader::new(
+            file.path().to_path_buf(),
+            detect_encoding(b""),
+        )?);
+
+        let mut engine = SearchEngine::new();
+        engine.set_query("test".to_string(), false, false);
+        // Batches much smaller than the overlap still count every match exactly once
+        engine.set_batch_size(7);
+        engine.set_max_threads(Some(2));
+
+        let (tx, rx) = mpsc::sync_channel(100);
+        engine.count_matches(reader, tx, Arc::new(AtomicBool::new(false)));
+        assert_eq!(collect_count(&rx), 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_matches_histogram() -> anyhow::Result<()> {
+        use crate::timeline::TimestampParser;
+
+        let mut file = NamedTempFile::new()?;
+        write!(
+            file,
+            "2024-01-01T00:00:10 timeout a\n\
+             2024-01-01T00:00:50 ok\n\
+             2024-01-01T00:01:05 timeout b timeout c\n\
+             no timestamp timeout\n\
+             2024-01-01T00:03:00 timeout d\n"
+        )?;
+        let reader = Arc::new(FileReader::new(
+            file.path().to_path_buf(),
+            detect_encoding(b""),
+        )?);
+
+        let mut engine = SearchEngine::new();
+        engine.set_query("timeout".to_string(), false, false);
+        engine.set_timeline(Some(TimelineConfig {
+            parser: TimestampParser::new("%Y-%m-%dT%H:%M:%S")?,
+            bucket_secs: 60,
+        }));
+
+        let (tx, rx) = mpsc::sync_channel(100);
+        engine.count_matches(reader, tx, Arc::new(AtomicBool::new(false)));
+        let mut histogram = TimeHistogram::new(60);
+        loop {
+            match rx.recv() {
+                Ok(SearchMessage::HistogramResult(h)) => histogram.merge(h),
+                Ok(SearchMessage::Done(SearchType::Count)) | Err(_) => break,
+                Ok(SearchMessage::Error(e)) => panic!("Error: {}", e),
+                Ok(_) => {}
+            }
+        }
+
+        let base = 1704067200; // 2024-01-01T00:00:00Z
+        let buckets: Vec<_> = histogram
+            .buckets
+            .iter()
+            .map(|(k, b)| (k - base, b.count, b.first_offset))
+            .collect();
+        assert_eq!(buckets, vec![(0, 1, 20), (60, 2, 73), (180, 1, 134)]);
+        assert_eq!(histogram.untimed, 1);
+        Ok(())
+    }
 }
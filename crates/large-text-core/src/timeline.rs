@@ -0,0 +1,444 @@
+use crate::file_reader::FileReader;
+use anyhow::{bail, Result};
+use regex::bytes::Regex;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Only this many bytes of a line are scanned for its timestamp.
+const MAX_LINE_SCAN: usize = 4096;
+/// Bytes sampled at each end of the file by `file_time_span` and `TimestampParser::detect`.
+const SAMPLE_LEN: usize = 64 * 1024;
+
+/// Common formats tried by `TimestampParser::detect`, most specific first.
+pub const TIMESTAMP_PRESETS: &[(&str, &str)] = &[
+    ("ISO 8601", "%Y-%m-%dT%H:%M:%S"),
+    ("Date and time", "%Y-%m-%d %H:%M:%S"),
+    ("Apache / nginx", "%d/%b/%Y:%H:%M:%S %z"),
+    ("Syslog", "%b %d %H:%M:%S"),
+    ("Unix epoch", "%s"),
+];
+
+/// Bucket widths (in seconds) chosen from by `auto_bucket_secs`.
+const NICE_BUCKETS: &[i64] = &[
+    1,
+    5,
+    10,
+    30,
+    60,
+    300,
+    600,
+    900,
+    1800,
+    3600,
+    3 * 3600,
+    6 * 3600,
+    12 * 3600,
+    86400,
+    7 * 86400,
+    30 * 86400,
+];
+
+/// Extracts a timestamp from a line using a strftime-like format.
+///
+/// Supported fields: `%Y` `%y` `%m` `%d` `%e` `%H` `%M` `%S` `%b` (month name), `%f`
+/// (fraction, ignored), `%z` (`Z`, `+HH:MM` or `+HHMM`), `%s` (Unix seconds) and `%%`.
+/// A space matches any run of spaces, so padded syslog days parse. Times without `%z`
+/// are treated as UTC, and a missing year defaults to 1970.
+#[derive(Clone, Debug)]
+pub struct TimestampParser {
+    format: String,
+    regex: Regex,
+}
+
+impl TimestampParser {
+    pub fn new(format: &str) -> Result<Self> {
+        let mut pattern = String::new();
+        let mut chars = format.chars();
+        let mut has_field = false;
+        while let Some(c) = chars.next() {
+            if c == ' ' {
+                pattern.push_str(" +");
+                continue;
+            }
+            if c != '%' {
+                pattern.push_str(&regex::escape(&c.to_string()));
+                continue;
+            }
+            let field = match chars.next() {
+                Some('Y') => r"(?P<Y>\d{4})",
+                Some('y') => r"(?P<y>\d{2})",
+                Some('m') => r"(?P<m>\d{1,2})",
+                Some('d') | Some('e') => r"(?P<d>\d{1,2})",
+                Some('H') => r"(?P<H>\d{1,2})",
+                Some('M') => r"(?P<M>\d{2})",
+                Some('S') => r"(?P<S>\d{2})",
+                Some('b') => r"(?P<b>(?i:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*)",
+                Some('f') => r"\d+",
+                Some('z') => r"(?P<z>Z|[+-]\d{2}:?\d{2})",
+                Some('s') => r"(?P<s>\d{9,11})",
+                Some('%') => "%",
+                Some(other) => bail!("Unsupported timestamp field: %{}", other),
+                None => bail!("Timestamp format ends with '%'"),
+            };
+            has_field |= field != "%";
+            pattern.push_str(field);
+        }
+        if !has_field {
+            bail!("Timestamp format has no fields");
+        }
+
+        // A named group may only appear once
+        let regex = Regex::new(&pattern).map_err(|e| anyhow::anyhow!("Invalid format: {}", e))?;
+        Ok(Self {
+            format: format.to_string(),
+            regex,
+        })
+    }
+
+    /// Picks the first preset that parses a timestamp near the start of the file.
+    pub fn detect(reader: &FileReader) -> Option<Self> {
+        let sample = reader.get_bytes(0, reader.len().min(SAMPLE_LEN));
+        TIMESTAMP_PRESETS
+            .iter()
+            .filter_map(|(_, format)| Self::new(format).ok())
+            .find(|parser| {
+                sample
+                    .split(|&b| b == b'\n')
+                    .take(100)
+                    .any(|line| parser.parse(line).is_some())
+            })
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// Seconds since the Unix epoch of the first timestamp in `line`.
+    pub fn parse(&self, line: &[u8]) -> Option<i64> {
+        let line = &line[..line.len().min(MAX_LINE_SCAN)];
+        let caps = self.regex.captures(line)?;
+        let num = |name: &str| -> Option<i64> {
+            caps.name(name)
+                .and_then(|m| std::str::from_utf8(m.as_bytes()).ok())
+                .and_then(|s| s.parse().ok())
+        };
+
+        if let Some(secs) = num("s") {
+            return Some(secs);
+        }
+
+        let year = num("Y")
+            .or_else(|| num("y").map(|y| 2000 + y))
+            .unwrap_or(1970);
+        let month = match caps.name("b") {
+            Some(m) => month_from_name(m.as_bytes())?,
+            None => num("m").unwrap_or(1),
+        };
+        let day = num("d").unwrap_or(1);
+        let (hour, minute, second) = (
+            num("H").unwrap_or(0),
+            num("M").unwrap_or(0),
+            num("S").unwrap_or(0),
+        );
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+            return None;
+        }
+
+        let offset = caps.name("z").map_or(0, |z| parse_utc_offset(z.as_bytes()));
+        Some(
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset,
+        )
+    }
+}
+
+fn month_from_name(name: &[u8]) -> Option<i64> {
+    const MONTHS: [&[u8]; 12] = [
+        b"jan", b"feb", b"mar", b"apr", b"may", b"jun", b"jul", b"aug", b"sep", b"oct", b"nov",
+        b"dec",
+    ];
+    let prefix = name.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|m| *m == prefix.as_slice())
+        .map(|i| i as i64 + 1)
+}
+
+/// Offset of `Z`, `+HH:MM` or `+HHMM` from UTC, in seconds.
+fn parse_utc_offset(z: &[u8]) -> i64 {
+    if z == b"Z" {
+        return 0;
+    }
+    let digits: Vec<i64> = z
+        .iter()
+        .filter(|b| b.is_ascii_digit())
+        .map(|b| (b - b'0') as i64)
+        .collect();
+    let [h1, h2, m1, m2] = digits[..] else {
+        return 0;
+    };
+    let secs = (h1 * 10 + h2) * 3600 + (m1 * 10 + m2) * 60;
+    if z[0] == b'-' {
+        -secs
+    } else {
+        secs
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS` (UTC).
+pub fn format_timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let time = secs.rem_euclid(86400);
+
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Timestamps of the first and last timestamped lines, sampled from each end of the file.
+pub fn file_time_span(reader: &FileReader, parser: &TimestampParser) -> Option<(i64, i64)> {
+    let len = reader.len();
+    let head = reader.get_bytes(0, len.min(SAMPLE_LEN));
+    let tail = reader.get_bytes(len.saturating_sub(SAMPLE_LEN), len);
+
+    let first = head
+        .split(|&b| b == b'\n')
+        .find_map(|line| parser.parse(line))?;
+    let last = tail
+        .rsplit(|&b| b == b'\n')
+        .find_map(|line| parser.parse(line))
+        .unwrap_or(first);
+    Some((first.min(last), first.max(last)))
+}
+
+/// The smallest "nice" bucket width giving at most `max_buckets` buckets over `span` seconds.
+pub fn auto_bucket_secs(span: i64, max_buckets: usize) -> i64 {
+    let max_buckets = max_buckets.max(1) as i64;
+    NICE_BUCKETS
+        .iter()
+        .copied()
+        .find(|&bucket| span / bucket < max_buckets)
+        .unwrap_or_else(|| (span / max_buckets).max(1))
+}
+
+/// How the search pipeline builds a `TimeHistogram` of its matches.
+#[derive(Clone, Debug)]
+pub struct TimelineConfig {
+    pub parser: TimestampParser,
+    pub bucket_secs: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeBucket {
+    pub count: usize,
+    /// Offset of the earliest match (by file position) in this bucket.
+    pub first_offset: usize,
+}
+
+/// Match counts per time bucket.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeHistogram {
+    pub bucket_secs: i64,
+    /// Keyed by bucket start, in seconds since the Unix epoch.
+    pub buckets: BTreeMap<i64, TimeBucket>,
+    /// Matches on lines without a parseable timestamp.
+    pub untimed: usize,
+}
+
+impl TimeHistogram {
+    pub fn new(bucket_secs: i64) -> Self {
+        Self {
+            bucket_secs: bucket_secs.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, timestamp: i64, offset: usize) {
+        let key = timestamp.div_euclid(self.bucket_secs) * self.bucket_secs;
+        let bucket = self.buckets.entry(key).or_insert(TimeBucket {
+            count: 0,
+            first_offset: offset,
+        });
+        bucket.count += 1;
+        bucket.first_offset = bucket.first_offset.min(offset);
+    }
+
+    /// Folds in a histogram from another worker with the same bucket width.
+    pub fn merge(&mut self, other: TimeHistogram) {
+        for (key, other_bucket) in other.buckets {
+            self.buckets
+                .entry(key)
+                .and_modify(|b| {
+                    b.count += other_bucket.count;
+                    b.first_offset = b.first_offset.min(other_bucket.first_offset);
+                })
+                .or_insert(other_bucket);
+        }
+        self.untimed += other.untimed;
+    }
+
+    pub fn total(&self) -> usize {
+        self.buckets.values().map(|b| b.count).sum::<usize>() + self.untimed
+    }
+}
+
+/// Adds matches to a histogram, parsing each line's timestamp once.
+pub(crate) struct HistogramBuilder<'a> {
+    reader: &'a FileReader,
+    parser: &'a TimestampParser,
+    histogram: TimeHistogram,
+    line: Range<usize>,
+    line_time: Option<i64>,
+}
+
+impl<'a> HistogramBuilder<'a> {
+    pub(crate) fn new(reader: &'a FileReader, config: &'a TimelineConfig) -> Self {
+        Self {
+            reader,
+            parser: &config.parser,
+            histogram: TimeHistogram::new(config.bucket_secs),
+            line: 0..0,
+            line_time: None,
+        }
+    }
+
+    pub(crate) fn add_match(&mut self, offset: usize) {
+        if !self.line.contains(&offset) {
+            let data = self.reader.all_data();
+            let scan_start = offset.saturating_sub(MAX_LINE_SCAN);
+            let line_start = match memchr::memrchr(b'\n', &data[scan_start..offset]) {
+                Some(i) => Some(scan_start + i + 1),
+                None => (scan_start == 0).then_some(0),
+            };
+            let start = line_start.unwrap_or(scan_start);
+            let scan_end = (start + MAX_LINE_SCAN).min(data.len()).max(offset + 1);
+            let end = memchr::memchr(b'\n', &data[offset..scan_end])
+                .map(|i| offset + i)
+                .unwrap_or(scan_end);
+            self.line = start..end.max(offset + 1);
+            // A match further than the scan from its line start counts as untimed
+            self.line_time = line_start.and_then(|start| self.parser.parse(&data[start..end]));
+        }
+
+        match self.line_time {
+            Some(time) => self.histogram.add(time, offset),
+            None => self.histogram.untimed += 1,
+        }
+    }
+
+    pub(crate) fn finish(self) -> TimeHistogram {
+        self.histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() -> Result<()> {
+        let iso = TimestampParser::new("%Y-%m-%dT%H:%M:%S")?;
+        assert_eq!(
+            iso.parse(b"2024-01-02T03:04:05.123Z INFO start"),
+            Some(1704164645)
+        );
+        assert_eq!(iso.parse(b"no timestamp here"), None);
+
+        let apache = TimestampParser::new("%d/%b/%Y:%H:%M:%S %z")?;
+        assert_eq!(
+            apache.parse(b"127.0.0.1 - - [02/Jan/2024:05:04:05 +0200] \"GET / HTTP/1.1\""),
+            Some(1704164645)
+        );
+
+        let syslog = TimestampParser::new("%b %d %H:%M:%S")?;
+        assert_eq!(
+            syslog.parse(b"Jan  2 03:04:05 host sshd[1]: ok"),
+            Some(86400 + 3 * 3600 + 4 * 60 + 5)
+        );
+
+        let epoch = TimestampParser::new("%s")?;
+        assert_eq!(epoch.parse(b"ts=1704164645 level=info"), Some(1704164645));
+
+        assert!(TimestampParser::new("%Q").is_err());
+        assert!(TimestampParser::new("plain").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_timestamp_roundtrip() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1704164645), "2024-01-02 03:04:05");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00");
+    }
+
+    #[test]
+    fn test_histogram_buckets_and_merge() {
+        let mut a = TimeHistogram::new(60);
+        a.add(125, 40);
+        a.add(130, 10);
+        let mut b = TimeHistogram::new(60);
+        b.add(61, 5);
+        b.add(179, 90);
+        b.untimed = 2;
+        a.merge(b);
+
+        let buckets: Vec<_> = a
+            .buckets
+            .iter()
+            .map(|(k, b)| (*k, b.count, b.first_offset))
+            .collect();
+        assert_eq!(buckets, vec![(60, 1, 5), (120, 3, 10)]);
+        assert_eq!(a.total(), 6);
+        assert_eq!(auto_bucket_secs(3600, 100), 60);
+        assert_eq!(auto_bucket_secs(86400, 100), 900);
+    }
+
+    #[test]
+    fn test_histogram_builder_bounds_line_scan() -> Result<()> {
+        use crate::file_reader::detect_encoding;
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new()?;
+        let long_line = "x".repeat(2 * MAX_LINE_SCAN);
+        writeln!(file, "2024-01-01T00:00:10 a {} b", long_line)?;
+        let reader = FileReader::new(file.path().to_path_buf(), detect_encoding(b""))?;
+        let config = TimelineConfig {
+            parser: TimestampParser::new("%Y-%m-%dT%H:%M:%S")?,
+            bucket_secs: 60,
+        };
+
+        let mut builder = HistogramBuilder::new(&reader, &config);
+        builder.add_match(20);
+        builder.add_match(22 + long_line.len() + 1);
+        let histogram = builder.finish();
+        assert_eq!(histogram.buckets.len(), 1);
+        assert_eq!(histogram.untimed, 1);
+        Ok(())
+    }
+}
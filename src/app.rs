@@ -1,6 +1,7 @@
 use eframe::egui;
 use encoding_rs::Encoding;
 use notify::{RecursiveMode, Result as NotifyResult, Watcher};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver};
 use std::sync::{
@@ -8,12 +9,171 @@ use std::sync::{
     Arc,
 };
 
+use large_text_core::cursor::{self, Selection};
+use large_text_core::export::{
+    export_matches, export_range, ExportMessage, MatchExportMode, MatchExportOptions,
+};
 use large_text_core::file_reader::{available_encodings, detect_encoding, FileReader};
+use large_text_core::file_search::{
+    collect_files, search_files, FileFilter, FileMatch, FileSearchMessage,
+};
 use large_text_core::line_indexer::LineIndexer;
-use large_text_core::replacer::{ReplaceMessage, Replacer};
+use large_text_core::query::SearchOptions;
+use large_text_core::replacer::{
+    shifted_offset, sort_edits, Edit, ReplaceMessage, ReplacePreview, Replacer,
+};
+use large_text_core::save::{backup_path, SaveOptions};
 use large_text_core::search_engine::{SearchEngine, SearchMessage, SearchResult, SearchType};
+use large_text_core::timeline::{
+    auto_bucket_secs, file_time_span, format_timestamp, TimeHistogram, TimelineConfig,
+    TimestampParser, TIMESTAMP_PRESETS,
+};
+
+use crate::bookmarks::{BookmarkStore, Bookmarks};
+use crate::command_palette::{CommandPalette, PaletteCommand, PaletteEntry};
+use crate::config::{Config, MAX_HEX_BYTES_PER_ROW, MB};
+use crate::hex_view::HexLayout;
+use crate::keymap::{Action, Keymap, KeymapConfig, KeymapPreset};
+use crate::pending_edits::{self, PendingEdits, PendingReplacement, Piece};
+use crate::search_history::{SearchHistory, SearchQuery};
+use crate::session::{FileSession, Session};
+
+// Highlight colors for multi-pattern searches, indexed by pattern
+const PATTERN_COLORS: [egui::Color32; 8] = [
+    egui::Color32::YELLOW,
+    egui::Color32::from_rgb(120, 220, 255),
+    egui::Color32::from_rgb(140, 240, 140),
+    egui::Color32::from_rgb(255, 150, 200),
+    egui::Color32::from_rgb(200, 170, 255),
+    egui::Color32::from_rgb(255, 170, 120),
+    egui::Color32::from_rgb(170, 255, 230),
+    egui::Color32::from_rgb(230, 230, 140),
+];
+
+// Text of pending replacements, and the gutter of lines that contain them
+const MODIFIED_COLOR: egui::Color32 = egui::Color32::from_rgb(60, 170, 90);
+
+// Escapes standing in for control characters, and the background of escaped bytes
+// that are not valid text in the file's encoding
+const ESCAPE_COLOR: egui::Color32 = egui::Color32::from_rgb(120, 150, 220);
+const INVALID_COLOR: egui::Color32 = egui::Color32::from_rgb(170, 50, 50);
+
+// Bucket widths offered for the match timeline (None picks one from the file's time span)
+const TIMELINE_BUCKETS: [Option<i64>; 8] = [
+    None,
+    Some(1),
+    Some(60),
+    Some(300),
+    Some(900),
+    Some(3600),
+    Some(6 * 3600),
+    Some(86400),
+];
+// Largest selection copied to the clipboard
+const MAX_COPY_BYTES: usize = 64 * 1024 * 1024;
+// Bytes of a line the text view lays out; longer lines end in a "…" marker
+const MAX_RENDERED_LINE_BYTES: usize = 16 * 1024;
+
+// Upper bound on buckets when the width is picked automatically
+const TIMELINE_AUTO_BUCKETS: usize = 200;
+const TIMELINE_HEIGHT: f32 = 60.0;
+
+#[derive(Default)]
+struct FindInFiles {
+    show: bool,
+    target: String,      // File, directory or glob
+    include: String,     // Comma-separated globs
+    exclude: String,     // Comma-separated globs
+    max_size_mb: String, // Blank means no limit
+    results: Vec<FileHits>,
+    skipped: Vec<(PathBuf, String)>,
+    total_files: usize,
+    current_file: Option<(usize, PathBuf)>,
+    in_progress: bool,
+    message_rx: Option<Receiver<FileSearchMessage>>,
+    cancellation_token: Option<Arc<AtomicBool>>,
+    status: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum RangeSource {
+    #[default]
+    Selection,
+    Lines,
+    Bytes,
+}
+
+impl RangeSource {
+    const ALL: [RangeSource; 3] = [
+        RangeSource::Selection,
+        RangeSource::Lines,
+        RangeSource::Bytes,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            RangeSource::Selection => "Selection",
+            RangeSource::Lines => "Line range",
+            RangeSource::Bytes => "Byte range",
+        }
+    }
+}
+
+// Copy or export part of the file without loading it into the UI
+#[derive(Default)]
+struct RangeExport {
+    show: bool,
+    source: RangeSource,
+    from: String,
+    to: String,
+    to_utf8: bool,
+    in_progress: bool,
+    progress: Option<f32>,
+    message_rx: Option<Receiver<ExportMessage>>,
+    cancellation_token: Option<Arc<AtomicBool>>,
+    status: String,
+}
+
+const MATCH_EXPORT_MODES: [(MatchExportMode, &str); 3] = [
+    (MatchExportMode::Lines, "Matching lines"),
+    (MatchExportMode::MatchedText, "Matched text only"),
+    (MatchExportMode::CaptureGroups, "Capture groups"),
+];
+
+// Write the matches of the last search to a file
+#[derive(Default)]
+struct MatchExport {
+    show: bool,
+    options: MatchExportOptions,
+    in_progress: bool,
+    progress: Option<f32>,
+    message_rx: Option<Receiver<ExportMessage>>,
+    cancellation_token: Option<Arc<AtomicBool>>,
+    status: String,
+}
+
+// Matches shown before Replace All commits
+const REPLACE_PREVIEW_PAIRS: usize = 20;
+
+// A Replace All waiting for confirmation, with the arguments its preview ran with
+struct ReplacePlan {
+    query: String,
+    replace_with: String,
+    options: SearchOptions,
+    range: Option<Range<usize>>,
+    scope_label: Option<String>,
+    previews: Vec<ReplacePreview>,
+}
+
+struct FileHits {
+    path: PathBuf,
+    total_matches: usize,
+    matches: Vec<FileMatch>,
+}
 
 pub struct TextViewerApp {
+    config: Config,
+    keymap: Keymap,
     file_reader: Option<Arc<FileReader>>,
     line_indexer: LineIndexer,
     search_engine: SearchEngine,
@@ -25,6 +185,12 @@ pub struct TextViewerApp {
     wrap_mode: bool,
     dark_mode: bool,
     show_line_numbers: bool,
+    // Hex view: rows of offset, hex bytes and ASCII instead of text lines
+    hex_view: bool,
+    hex_bytes_per_row: usize,
+    hex_decimal_offsets: bool,
+    hex_top_row: usize,               // First hex row displayed last frame
+    hex_scroll_target: Option<usize>, // Hex row to scroll to
 
     // Search UI
     search_query: String,
@@ -33,10 +199,23 @@ pub struct TextViewerApp {
     show_replace: bool,
     use_regex: bool,
     case_sensitive: bool,
+    whole_word: bool,
+    fuzzy_search: bool,
+    fuzzy_max_edits: usize,
+    multi_pattern: bool,        // search_query holds one pattern per line
+    hex_search: bool,           // search_query is a hex byte pattern
+    pattern_counts: Vec<usize>, // Per-pattern totals of a multi-pattern Find All
+    // Match timeline (histogram of Find All matches over time)
+    show_timeline: bool,
+    timestamp_format: String,          // Empty means auto-detect
+    timeline_bucket_secs: Option<i64>, // None picks a width automatically
+    timeline: Option<TimeHistogram>,
+    timeline_note: Option<String>, // Why the last search has no timeline
     search_results: Vec<SearchResult>,
     current_result_index: usize, // Global index (0 to total_results - 1)
     total_search_results: usize,
     search_page_start_index: usize, // Global index of the first result in search_results
+    search_page_size: usize,        // Results per page, fixed for the duration of a search
     page_offsets: Vec<usize>,       // Map of page_index -> start_byte_offset
     search_error: Option<String>,
     search_in_progress: bool,
@@ -46,12 +225,45 @@ pub struct TextViewerApp {
     search_count_done: bool,
     search_fetch_done: bool,
 
+    // Search scope (limits search and replace-all to part of the file)
+    search_scope: SearchScope,
+    scope_from_input: String,
+    scope_to_input: String,
+    search_scope_label: Option<String>, // Description of the scope used by the last search
+
+    // Search history and saved searches
+    search_history: SearchHistory,
+    show_saved_searches: bool,
+    saved_search_name: String,
+
+    // Find in Files
+    find_in_files: FindInFiles,
+
+    // Copy or export a range
+    range_export: RangeExport,
+
+    // Export search matches
+    match_export: MatchExport,
+
+    // Recent files with their view state, and the reopen-on-startup option
+    session: Session,
+
+    // Bookmarks of the open file, persisted per file in bookmark_store
+    bookmarks: Bookmarks,
+    bookmark_store: BookmarkStore,
+    show_bookmarks: bool,
+
     // Replace UI
     replace_in_progress: bool,
     replace_message_rx: Option<Receiver<ReplaceMessage>>,
     replace_cancellation_token: Option<Arc<AtomicBool>>,
     replace_progress: Option<f32>,
     replace_status_message: Option<String>,
+    replace_in_place: bool, // Replace All rewrites the open file instead of a copy
+    replace_output: Option<PathBuf>, // Where the running Replace All writes; None while previewing
+    replace_reopen_line: Option<usize>, // Line to reopen at after an in-place run released the file
+    replace_plan: Option<ReplacePlan>, // Previewed Replace All awaiting confirmation
+    replace_pending_plan: Option<ReplacePlan>, // Arguments of the running preview
 
     // Go to line
     goto_line_input: String,
@@ -59,6 +271,16 @@ pub struct TextViewerApp {
     // File info
     show_file_info: bool,
 
+    // Offer of the hex view for a file that looks binary
+    show_binary_prompt: bool,
+
+    // Preferences dialog, editing a copy of the config until saved
+    show_preferences: bool,
+    preferences_draft: Config,
+
+    // Command palette
+    command_palette: CommandPalette,
+
     // Tail mode
     tail_mode: bool,
     watcher: Option<Box<dyn Watcher>>,
@@ -71,6 +293,12 @@ pub struct TextViewerApp {
     selected_encoding: &'static Encoding,
     show_encoding_selector: bool,
 
+    // Caret and selection, as byte offsets into the file
+    selection: Option<Selection>,
+    // Column kept while moving the caret through shorter lines
+    caret_column: Option<usize>,
+    mouse_selecting: bool,
+
     // Programmatic scroll control
     scroll_to_row: Option<usize>,
     // Correction for f32 scroll precision issues in large files
@@ -80,26 +308,52 @@ pub struct TextViewerApp {
 
     // Focus control
     focus_search_input: bool,
+    focus_goto_input: bool,
 
     // Unsaved changes
     unsaved_changes: bool,
-    pending_replacements: Vec<PendingReplacement>,
+    pending_edits: PendingEdits,
+    show_pending_edits: bool,
 
     // Performance measurement
     open_start_time: Option<std::time::Instant>,
     search_count_start_time: Option<std::time::Instant>,
 }
 
-#[derive(Clone)]
-struct PendingReplacement {
-    offset: usize,
-    old_len: usize,
-    new_text: String,
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SearchScope {
+    WholeFile,
+    Lines,
+    Bytes,
+    FromCursor,
+    Selection,
+}
+
+impl SearchScope {
+    const ALL: [SearchScope; 5] = [
+        SearchScope::WholeFile,
+        SearchScope::Lines,
+        SearchScope::Bytes,
+        SearchScope::FromCursor,
+        SearchScope::Selection,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SearchScope::WholeFile => "Whole file",
+            SearchScope::Lines => "Line range",
+            SearchScope::Bytes => "Byte range",
+            SearchScope::FromCursor => "From cursor to end",
+            SearchScope::Selection => "Selection",
+        }
+    }
 }
 
 impl Default for TextViewerApp {
     fn default() -> Self {
         Self {
+            config: Config::default(),
+            keymap: Keymap::default(),
             file_reader: None,
             line_indexer: LineIndexer::new(),
             search_engine: SearchEngine::new(),
@@ -109,16 +363,33 @@ impl Default for TextViewerApp {
             wrap_mode: false,
             dark_mode: true,
             show_line_numbers: true,
+            hex_view: false,
+            hex_bytes_per_row: 16,
+            hex_decimal_offsets: false,
+            hex_top_row: 0,
+            hex_scroll_target: None,
             search_query: String::new(),
             replace_query: String::new(),
             show_search_bar: false,
             show_replace: false,
             use_regex: false,
             case_sensitive: false,
+            whole_word: false,
+            fuzzy_search: false,
+            fuzzy_max_edits: 1,
+            multi_pattern: false,
+            hex_search: false,
+            pattern_counts: Vec::new(),
+            show_timeline: false,
+            timestamp_format: String::new(),
+            timeline_bucket_secs: None,
+            timeline: None,
+            timeline_note: None,
             search_results: Vec::new(),
             current_result_index: 0,
             total_search_results: 0,
             search_page_start_index: 0,
+            search_page_size: Config::default().memory.search_page_size,
             page_offsets: Vec::new(),
             search_error: None,
             search_in_progress: false,
@@ -127,26 +398,54 @@ impl Default for TextViewerApp {
             search_cancellation_token: None,
             search_count_done: false,
             search_fetch_done: false,
+            search_scope: SearchScope::WholeFile,
+            scope_from_input: String::new(),
+            scope_to_input: String::new(),
+            search_scope_label: None,
+            search_history: SearchHistory::default(),
+            show_saved_searches: false,
+            saved_search_name: String::new(),
+            find_in_files: FindInFiles::default(),
+            range_export: RangeExport::default(),
+            match_export: MatchExport::default(),
+            session: Session::default(),
+            bookmarks: Bookmarks::default(),
+            bookmark_store: BookmarkStore::default(),
+            show_bookmarks: false,
             replace_in_progress: false,
             replace_message_rx: None,
             replace_cancellation_token: None,
             replace_progress: None,
             replace_status_message: None,
+            replace_in_place: false,
+            replace_output: None,
+            replace_reopen_line: None,
+            replace_plan: None,
+            replace_pending_plan: None,
             goto_line_input: String::new(),
             show_file_info: false,
+            show_binary_prompt: false,
+            show_preferences: false,
+            preferences_draft: Config::default(),
+            command_palette: CommandPalette::default(),
             tail_mode: false,
             watcher: None,
             file_change_rx: None,
             status_message: String::new(),
             selected_encoding: encoding_rs::UTF_8,
             show_encoding_selector: false,
+            selection: None,
+            caret_column: None,
+            mouse_selecting: false,
             focus_search_input: false,
+            focus_goto_input: false,
             scroll_to_row: None,
             scroll_correction: 0,
             pending_scroll_target: None,
             last_scroll_offset: 0.0,
             unsaved_changes: false,
-            pending_replacements: Vec::new(),
+            pending_edits: PendingEdits::default(),
+            show_pending_edits: false,
             open_start_time: None,
             search_count_start_time: None,
         }
@@ -154,10 +453,463 @@ impl Default for TextViewerApp {
 }
 
 impl TextViewerApp {
+    /// Creates the app with persisted state (config, search history, bookmarks, recent
+    /// files) loaded from disk, reopening the last file if enabled.
+    /// Files that fail to load are replaced by defaults and reported in the status bar.
+    pub fn new() -> Self {
+        let mut load_errors = Vec::new();
+        let mut app = Self {
+            config: loaded_or_default(Config::load(), &mut load_errors),
+            search_history: loaded_or_default(SearchHistory::load(), &mut load_errors),
+            bookmark_store: loaded_or_default(BookmarkStore::load(), &mut load_errors),
+            session: loaded_or_default(Session::load(), &mut load_errors),
+            ..Default::default()
+        };
+        load_errors.extend(
+            app.config
+                .keymap
+                .errors()
+                .into_iter()
+                .map(|e| format!("Ignoring key binding ({})", e)),
+        );
+        app.apply_config();
+        if app.session.reopen_last_session {
+            if let Some(last) = app.session.last_file().map(|f| f.path.clone()) {
+                app.open_recent_file(last);
+            }
+        }
+        if !load_errors.is_empty() {
+            app.status_message = load_errors.join("; ");
+        }
+        app
+    }
+
+    /// Opens `path`, restoring its encoding, view settings, scroll position and search
+    /// from the last session. Files never opened before get their encoding detected.
+    fn open_recent_file(&mut self, path: PathBuf) {
+        let remembered = self.remember_file_session();
+        self.restore_file_session(path);
+        // Reported after opening so that the open status does not hide it
+        if let Err(e) = remembered {
+            self.status_message = format!("Failed to save session: {}", e);
+        }
+    }
+
+    fn restore_file_session(&mut self, path: PathBuf) {
+        let Some(state) = self.session.get(&path).cloned() else {
+            self.open_file_default_encoding(path);
+            return;
+        };
+        if let Some(encoding) = Encoding::for_label(state.encoding.as_bytes()) {
+            self.selected_encoding = encoding;
+        }
+        self.wrap_mode = state.wrap_mode;
+        self.font_size = state.font_size;
+        self.open_file(path);
+        if self.file_reader.is_none() {
+            return;
+        }
+
+        if state.scroll_offset > 0 {
+            self.jump_to_offset(state.scroll_offset);
+        }
+        if let Some(query) = state.search.filter(|q| !q.query.is_empty()) {
+            self.apply_search_query(&query);
+            self.show_search_bar = true;
+            self.perform_search(true);
+        }
+    }
+
+    /// Records the open file's view state in the recent files and saves the session.
+    fn remember_file_session(&mut self) -> anyhow::Result<()> {
+        let Some(ref reader) = self.file_reader else {
+            return Ok(());
+        };
+        let state = FileSession {
+            path: reader.path().clone(),
+            scroll_offset: self.line_start_offset(self.scroll_line).unwrap_or(0),
+            encoding: reader.encoding().name().to_string(),
+            wrap_mode: self.wrap_mode,
+            font_size: self.font_size,
+            search: (!self.search_query.is_empty()).then(|| self.current_search_query()),
+        };
+        self.session.record(state);
+        self.session.save()
+    }
+
+    fn persist_session(&mut self) {
+        if let Err(e) = self.session.save() {
+            self.status_message = format!("Failed to save session: {}", e);
+        }
+    }
+
+    /// Applies the config's view defaults and indexer settings.
+    fn apply_config(&mut self) {
+        let ui = &self.config.ui;
+        self.font_size = ui.font_size;
+        self.dark_mode = ui.dark_mode;
+        self.wrap_mode = ui.wrap_mode;
+        self.show_line_numbers = ui.show_line_numbers;
+        self.hex_bytes_per_row = ui.hex_bytes_per_row;
+        self.hex_decimal_offsets = ui.hex_decimal_offsets;
+        self.line_indexer.set_thresholds(
+            self.config.full_index_threshold(),
+            self.config.sparse_sample_size(),
+        );
+        self.keymap = Keymap::from_config(&self.config.keymap);
+    }
+
+    /// Runs a command triggered from a key binding.
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::CommandPalette => self.command_palette.show(),
+            Action::OpenFile => {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.open_recent_file(path);
+                }
+            }
+            Action::Save => self.save_file(),
+            Action::Undo => self.undo_replacement(),
+            Action::Redo => self.redo_replacement(),
+            Action::TogglePendingEditsPanel => self.show_pending_edits = !self.show_pending_edits,
+            Action::ClearRecentFiles => {
+                self.session.clear_recent();
+                self.persist_session();
+            }
+            Action::ShowFileInfo => self.show_file_info = !self.show_file_info,
+            Action::Preferences => self.open_preferences(),
+            Action::Exit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Action::Find => {
+                self.show_search_bar = !self.show_search_bar;
+                if self.show_search_bar {
+                    self.focus_search_input = true;
+                }
+            }
+            Action::Replace => {
+                self.show_search_bar = true;
+                self.show_replace = !self.show_replace;
+            }
+            Action::FocusSearch => {
+                self.show_search_bar = true;
+                self.focus_search_input = true;
+            }
+            Action::FindNext => self.go_to_next_result(),
+            Action::FindPrevious => self.go_to_previous_result(),
+            Action::FindInFiles => self.open_find_in_files(),
+            Action::ExportMatches => self.open_match_export(),
+            Action::ManageSavedSearches => self.show_saved_searches = true,
+            Action::RunSavedSearch1
+            | Action::RunSavedSearch2
+            | Action::RunSavedSearch3
+            | Action::RunSavedSearch4
+            | Action::RunSavedSearch5
+            | Action::RunSavedSearch6
+            | Action::RunSavedSearch7
+            | Action::RunSavedSearch8
+            | Action::RunSavedSearch9 => {
+                if let Some(index) = action.saved_search_index() {
+                    self.run_saved_search(index);
+                }
+            }
+            Action::GoToLine => {
+                self.show_search_bar = true;
+                self.focus_goto_input = true;
+            }
+            Action::ToggleWrap => self.wrap_mode = !self.wrap_mode,
+            Action::ToggleLineNumbers => self.show_line_numbers = !self.show_line_numbers,
+            Action::ToggleHexView => self.set_hex_view(!self.hex_view),
+            Action::ToggleDarkMode => self.dark_mode = !self.dark_mode,
+            Action::ToggleTimeline => self.show_timeline = !self.show_timeline,
+            Action::SelectEncoding => self.show_encoding_selector = true,
+            Action::ToggleTail => self.set_tail_mode(!self.tail_mode),
+            Action::ToggleBookmark => self.toggle_bookmark_at_caret(),
+            Action::NextBookmark => self.go_to_bookmark(true),
+            Action::PreviousBookmark => self.go_to_bookmark(false),
+            Action::ToggleBookmarksPanel => self.show_bookmarks = !self.show_bookmarks,
+            Action::ClearBookmarks => {
+                self.bookmarks.clear();
+                self.persist_bookmarks();
+            }
+            Action::MoveLeft => self.move_caret(false, self.horizontal_step(false)),
+            Action::MoveRight => self.move_caret(false, self.horizontal_step(true)),
+            Action::MoveUp => self.move_caret_vertically(false, false),
+            Action::MoveDown => self.move_caret_vertically(true, false),
+            Action::MoveWordLeft => self.move_caret(false, cursor::prev_word),
+            Action::MoveWordRight => self.move_caret(false, cursor::next_word),
+            Action::MoveLineStart => self.move_caret(false, cursor::line_start),
+            Action::MoveLineEnd => self.move_caret(false, cursor::line_end),
+            Action::SelectLeft => self.move_caret(true, self.horizontal_step(false)),
+            Action::SelectRight => self.move_caret(true, self.horizontal_step(true)),
+            Action::SelectUp => self.move_caret_vertically(false, true),
+            Action::SelectDown => self.move_caret_vertically(true, true),
+            Action::SelectWordLeft => self.move_caret(true, cursor::prev_word),
+            Action::SelectWordRight => self.move_caret(true, cursor::next_word),
+            Action::SelectLineStart => self.move_caret(true, cursor::line_start),
+            Action::SelectLineEnd => self.move_caret(true, cursor::line_end),
+            Action::SelectAll => {
+                if let Some(ref reader) = self.file_reader {
+                    self.selection = Some(Selection {
+                        anchor: 0,
+                        caret: reader.len(),
+                    });
+                }
+            }
+            Action::Copy => self.copy_selection(ctx),
+            Action::ExportRange => self.open_range_export(),
+            Action::ScrollDown => self.scroll_rows(true, 1),
+            Action::ScrollUp => self.scroll_rows(false, 1),
+            Action::PageDown => self.scroll_rows(true, self.visible_lines.saturating_sub(3).max(1)),
+            Action::PageUp => self.scroll_rows(false, self.visible_lines.saturating_sub(3).max(1)),
+            Action::GoToTop => self.scroll_rows(false, usize::MAX),
+            Action::GoToBottom => self.scroll_rows(true, usize::MAX),
+        }
+    }
+
+    /// Scrolls `count` lines down or up, or rows in the hex view.
+    fn scroll_rows(&mut self, down: bool, count: usize) {
+        let top = if self.hex_view {
+            self.hex_top_row
+        } else {
+            self.scroll_line
+        };
+        let target = if down {
+            top.saturating_add(count)
+        } else {
+            top.saturating_sub(count)
+        };
+        if self.hex_view {
+            self.scroll_to_hex_row(target);
+        } else {
+            self.scroll_to(target);
+        }
+    }
+
+    /// Scrolls so that `line` (clamped to the file) is the top visible line.
+    fn scroll_to(&mut self, line: usize) {
+        let last_line = self.line_indexer.total_lines().saturating_sub(1);
+        let target_line = line.min(last_line);
+        self.scroll_line = target_line;
+        self.scroll_to_row = Some(target_line);
+        self.pending_scroll_target = Some(target_line);
+    }
+
+    /// Scrolls so that the hex view row `row` (clamped to the file) is the top visible row.
+    fn scroll_to_hex_row(&mut self, row: usize) {
+        let len = self.file_reader.as_ref().map_or(0, |r| r.len());
+        let layout = self.hex_layout();
+        let target_row = row.min(layout.rows(len).saturating_sub(1));
+        self.hex_top_row = target_row;
+        self.hex_scroll_target = Some(target_row);
+        self.scroll_line = self
+            .line_indexer
+            .find_line_at_offset(layout.row_start(target_row));
+    }
+
+    /// Scrolls to the line holding `offset`, or its row in the hex view.
+    fn scroll_to_offset(&mut self, offset: usize) {
+        let target_line = self.line_indexer.find_line_at_offset(offset);
+        self.scroll_line = target_line;
+        self.scroll_to_row = Some(target_line);
+        self.pending_scroll_target = Some(target_line);
+        let row = self.hex_layout().row_of(offset);
+        self.hex_scroll_target = Some(row);
+    }
+
+    fn hex_layout(&self) -> HexLayout {
+        let len = self.file_reader.as_ref().map_or(0, |r| r.len());
+        HexLayout::new(self.hex_bytes_per_row, self.hex_decimal_offsets, len)
+    }
+
+    /// File offset at the top of the view.
+    fn top_offset(&self) -> usize {
+        if self.hex_view {
+            self.hex_layout().row_start(self.hex_top_row)
+        } else {
+            self.line_start_offset(self.scroll_line).unwrap_or(0)
+        }
+    }
+
+    /// Switches between the text and the hex view, keeping the same part of the file in view.
+    fn set_hex_view(&mut self, enabled: bool) {
+        let offset = self.top_offset();
+        self.hex_view = enabled;
+        self.scroll_correction = 0;
+        self.scroll_to_offset(offset);
+    }
+
+    /// Changes how many bytes each hex view row shows, keeping the top row's bytes in view.
+    fn set_hex_bytes_per_row(&mut self, bytes_per_row: usize) {
+        let offset = self.top_offset();
+        self.hex_bytes_per_row = bytes_per_row.clamp(1, MAX_HEX_BYTES_PER_ROW);
+        self.scroll_correction = 0;
+        self.scroll_to_offset(offset);
+    }
+
+    /// The caret offset, or the start of the top visible line when there is no caret.
+    fn caret_offset(&self) -> usize {
+        self.selection
+            .map(|s| s.caret)
+            .or_else(|| self.line_start_offset(self.scroll_line))
+            .unwrap_or(0)
+    }
+
+    /// Moves the caret to `step(reader, caret)`, extending the selection if `extend` is set.
+    fn move_caret(&mut self, extend: bool, step: fn(&FileReader, usize) -> usize) {
+        let Some(reader) = self.file_reader.clone() else {
+            return;
+        };
+        let mut selection = self
+            .selection
+            .unwrap_or_else(|| Selection::new(self.caret_offset()));
+        selection.move_to(step(&reader, selection.caret), extend);
+        self.selection = Some(selection);
+        self.caret_column = None;
+        self.scroll_to_caret();
+    }
+
+    /// One character left or right, or one byte in the hex view.
+    fn horizontal_step(&self, forward: bool) -> fn(&FileReader, usize) -> usize {
+        match (self.hex_view, forward) {
+            (false, false) => cursor::prev_position,
+            (false, true) => cursor::next_position,
+            (true, false) => |_, offset| offset.saturating_sub(1),
+            (true, true) => |reader, offset| (offset + 1).min(reader.len()),
+        }
+    }
+
+    /// Moves the caret one line down or up, staying in the column it started from.
+    fn move_caret_vertically(&mut self, down: bool, extend: bool) {
+        let Some(reader) = self.file_reader.clone() else {
+            return;
+        };
+        let mut selection = self
+            .selection
+            .unwrap_or_else(|| Selection::new(self.caret_offset()));
+        let caret = selection.caret;
+        if self.hex_view {
+            // One row of bytes at a time
+            let target = if down {
+                caret
+                    .saturating_add(self.hex_bytes_per_row)
+                    .min(reader.len())
+            } else {
+                caret.saturating_sub(self.hex_bytes_per_row)
+            };
+            selection.move_to(target, extend);
+            self.selection = Some(selection);
+            self.scroll_to_caret();
+            return;
+        }
+        let column = *self.caret_column.get_or_insert_with(|| {
+            cursor::column(&reader, cursor::line_start(&reader, caret), caret)
+        });
+        let target = if down {
+            cursor::line_down(&reader, caret, column)
+        } else {
+            cursor::line_up(&reader, caret, column)
+        };
+        selection.move_to(target, extend);
+        self.selection = Some(selection);
+        self.scroll_to_caret();
+    }
+
+    /// Scrolls just far enough to bring the caret's line into view.
+    fn scroll_to_caret(&mut self) {
+        let Some(selection) = self.selection else {
+            return;
+        };
+        let page = self
+            .visible_lines
+            .saturating_sub(self.config.ui.overscan_lines + 1)
+            .max(1);
+        if self.hex_view {
+            let row = self.hex_layout().row_of(selection.caret);
+            if row < self.hex_top_row {
+                self.scroll_to_hex_row(row);
+            } else if row >= self.hex_top_row + page {
+                self.scroll_to_hex_row(row + 1 - page);
+            }
+            return;
+        }
+        let line = self.line_indexer.find_line_at_offset(selection.caret);
+        if line < self.scroll_line {
+            self.scroll_to(line);
+        } else if line >= self.scroll_line + page {
+            self.scroll_to(line + 1 - page);
+        }
+    }
+
+    /// Copies the selection to the clipboard.
+    fn copy_selection(&mut self, ctx: &egui::Context) {
+        if let Ok(range) = self.selected_range() {
+            self.copy_range(ctx, range);
+        }
+    }
+
+    /// Copies `range` to the clipboard, decoding it straight from the file.
+    fn copy_range(&mut self, ctx: &egui::Context, range: Range<usize>) {
+        let Some(ref reader) = self.file_reader else {
+            return;
+        };
+        if range.len() > MAX_COPY_BYTES {
+            self.status_message = format!(
+                "Too large to copy ({:.1} MB, limit {} MB); export it to a file instead",
+                range.len() as f64 / (1024.0 * 1024.0),
+                MAX_COPY_BYTES / (1024 * 1024)
+            );
+            return;
+        }
+        let mut text = String::new();
+        reader.decode_into(range.start, range.end, &mut text);
+        ctx.copy_text(text);
+        self.status_message = format!("Copied {} bytes", range.len());
+    }
+
+    /// Switches to `encoding`, reloading the open file with it.
+    fn set_encoding(&mut self, encoding: &'static Encoding) {
+        self.selected_encoding = encoding;
+        if let Some(ref reader) = self.file_reader {
+            let path = reader.path().clone();
+            self.open_file(path);
+        }
+    }
+
+    fn set_tail_mode(&mut self, enabled: bool) {
+        self.tail_mode = enabled;
+        if enabled {
+            self.setup_file_watcher();
+        } else {
+            self.watcher = None;
+            self.file_change_rx = None;
+        }
+    }
+
+    /// Opens `path` with the configured default encoding, or the detected one when
+    /// the default is "auto".
+    fn open_file_default_encoding(&mut self, path: PathBuf) {
+        if let Some(encoding) = Encoding::for_label(self.config.ui.default_encoding.as_bytes()) {
+            self.selected_encoding = encoding;
+            self.open_file(path);
+            return;
+        }
+        if let Ok(mut file) = std::fs::File::open(&path) {
+            let mut buffer = [0; 4096];
+            if let Ok(n) = std::io::Read::read(&mut file, &mut buffer) {
+                self.selected_encoding = detect_encoding(&buffer[..n]);
+            }
+        }
+        self.open_file(path);
+    }
+
     fn open_file(&mut self, path: PathBuf) {
         self.open_start_time = Some(std::time::Instant::now());
         match FileReader::new(path.clone(), self.selected_encoding) {
             Ok(reader) => {
+                // Reloads (tail mode, a new encoding) keep the answer given for the file
+                let reopened = self.file_reader.as_ref().is_some_and(|r| r.path() == &path);
+                if !reopened {
+                    self.show_binary_prompt = !self.hex_view && reader.looks_binary();
+                }
                 self.file_reader = Some(Arc::new(reader));
                 self.line_indexer
                     .index_file(self.file_reader.as_ref().unwrap());
@@ -170,6 +922,9 @@ impl TextViewerApp {
                 self.search_page_start_index = 0;
                 self.page_offsets.clear();
                 self.current_result_index = 0;
+                self.bookmarks = self.bookmark_store.get(&path);
+                self.selection = None;
+                self.caret_column = None;
 
                 // Setup file watcher if tail mode is enabled
                 if self.tail_mode {
@@ -232,32 +987,43 @@ impl TextViewerApp {
         self.search_page_start_index = 0;
         self.page_offsets.clear();
         self.search_engine.clear();
+        self.pattern_counts.clear();
+        self.timeline = None;
+        self.timeline_note = None;
 
         if self.search_in_progress {
             self.status_message = "Search already running...".to_string();
             return;
         }
 
-        let Some(ref reader) = self.file_reader else {
+        let Some(reader) = self.file_reader.clone() else {
             self.status_message = "Open a file before searching".to_string();
             return;
         };
 
-        if self.search_query.is_empty() {
+        let patterns = self.search_patterns();
+        if patterns.is_empty() {
             self.status_message = "Enter a search query first".to_string();
             return;
         }
 
-        self.search_engine.set_query(
-            self.search_query.clone(),
-            self.use_regex,
-            self.case_sensitive,
-        );
+        let (range, scope_label) = match self.resolve_search_range() {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        self.search_scope_label = scope_label;
+
+        self.search_engine
+            .set_patterns(patterns.clone(), self.search_options());
+        self.search_engine.set_range(range.clone());
 
-        let reader = reader.clone();
         // Use a bounded channel to provide backpressure to search threads
         // This prevents memory explosion if the UI thread can't keep up with results
-        let (tx, rx) = std::sync::mpsc::sync_channel(10_000);
+        let (tx, rx) = std::sync::mpsc::sync_channel(self.config.memory.search_channel_capacity);
+        self.search_page_size = self.config.memory.search_page_size;
 
         self.search_message_rx = Some(rx);
         self.search_in_progress = true;
@@ -273,55 +1039,338 @@ impl TextViewerApp {
         } else {
             "Searching first match...".to_string()
         };
+        // After the status so that a failed save stays visible
+        self.search_history.record(self.current_search_query());
+        self.persist_search_history();
 
         if find_all {
             self.search_count_start_time = Some(std::time::Instant::now());
+            let timeline = if self.show_timeline {
+                match self.timeline_config(&reader) {
+                    Ok(config) => {
+                        self.timeline = Some(TimeHistogram::new(config.bucket_secs));
+                        Some(config)
+                    }
+                    Err(note) => {
+                        self.timeline_note = Some(note);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
             // Start two tasks:
             // 1. Count all matches (parallel)
             // 2. Fetch first page of matches (sequential/chunked)
 
             let tx_count = tx.clone();
             let reader_count = reader.clone();
-            let query = self.search_query.clone();
-            let use_regex = self.use_regex;
-            let case_sensitive = self.case_sensitive;
+            let query = patterns.clone();
+            let options = self.search_options();
             let cancel_token_count = cancel_token.clone();
+            let range_count = range.clone();
+            let batch_size = self.config.search_batch_size();
+            let max_threads = self.config.search_threads();
 
             std::thread::spawn(move || {
                 // Task 1: Count
                 let mut engine = SearchEngine::new();
-                engine.set_query(query, use_regex, case_sensitive);
+                engine.set_patterns(query, options);
+                engine.set_range(range_count);
+                engine.set_timeline(timeline);
+                engine.set_batch_size(batch_size);
+                engine.set_max_threads(max_threads);
                 engine.count_matches(reader_count, tx_count, cancel_token_count);
             });
 
             let tx_fetch = tx.clone();
             let reader_fetch = reader.clone();
-            let query_fetch = self.search_query.clone();
+            let query_fetch = patterns.clone();
             let cancel_token_fetch = cancel_token.clone();
+            let page_size = self.search_page_size;
 
             std::thread::spawn(move || {
                 // Task 2: Fetch first page
                 let mut engine = SearchEngine::new();
-                engine.set_query(query_fetch, use_regex, case_sensitive);
-                engine.fetch_matches(reader_fetch, tx_fetch, 0, 1000, cancel_token_fetch);
+                engine.set_patterns(query_fetch, options);
+                engine.set_range(range);
+                engine.fetch_matches(reader_fetch, tx_fetch, 0, page_size, cancel_token_fetch);
             });
         } else {
             // Find first match only
             let tx_fetch = tx.clone();
             let reader_fetch = reader.clone();
-            let query = self.search_query.clone();
-            let use_regex = self.use_regex;
-            let case_sensitive = self.case_sensitive;
+            let query = patterns;
+            let options = self.search_options();
             let cancel_token_fetch = cancel_token.clone();
 
             std::thread::spawn(move || {
                 let mut engine = SearchEngine::new();
-                engine.set_query(query, use_regex, case_sensitive);
+                engine.set_patterns(query, options);
+                engine.set_range(range);
                 engine.fetch_matches(reader_fetch, tx_fetch, 0, 1, cancel_token_fetch);
             });
         }
     }
 
+    /// Timestamp parser and bucket width for the timeline of a Find All over `reader`.
+    fn timeline_config(&self, reader: &FileReader) -> Result<TimelineConfig, String> {
+        let parser = if self.timestamp_format.trim().is_empty() {
+            TimestampParser::detect(reader)
+                .ok_or_else(|| "No timestamps recognised; set a format".to_string())?
+        } else {
+            TimestampParser::new(self.timestamp_format.trim()).map_err(|e| e.to_string())?
+        };
+        let bucket_secs = match self.timeline_bucket_secs {
+            Some(secs) => secs,
+            None => file_time_span(reader, &parser)
+                .map(|(first, last)| auto_bucket_secs(last - first, TIMELINE_AUTO_BUCKETS))
+                .unwrap_or(60),
+        };
+        Ok(TimelineConfig {
+            parser,
+            bucket_secs,
+        })
+    }
+
+    /// Scrolls to `offset`, selecting it if it is one of the loaded search results.
+    fn jump_to_offset(&mut self, offset: usize) {
+        self.selection = Some(Selection::new(offset));
+        self.caret_column = None;
+        self.scroll_to_offset(offset);
+        if let Some(i) = self
+            .search_results
+            .iter()
+            .position(|r| r.byte_offset == offset)
+        {
+            self.current_result_index = self.search_page_start_index + i;
+        }
+    }
+
+    /// Adds or removes a bookmark on the line spanning `line`.
+    fn toggle_bookmark(&mut self, line: Range<usize>) {
+        let added = self.bookmarks.toggle_line(line);
+        self.status_message = if added {
+            "Bookmark added".to_string()
+        } else {
+            "Bookmark removed".to_string()
+        };
+        self.persist_bookmarks();
+    }
+
+    /// Toggles a bookmark on the caret's line, or on the top visible line without a caret.
+    fn toggle_bookmark_at_caret(&mut self) {
+        let Some(reader) = self.file_reader.clone() else {
+            return;
+        };
+        let line = match self.selection {
+            Some(selection) => {
+                let start = cursor::line_start(&reader, selection.caret);
+                start..cursor::next_line_start(&reader, selection.caret)
+            }
+            None => match self
+                .line_indexer
+                .get_line_with_reader(self.scroll_line, &reader)
+            {
+                Some((start, end)) => start.min(reader.len())..end,
+                None => return,
+            },
+        };
+        self.toggle_bookmark(line.start..line.end.max(line.start + 1));
+    }
+
+    fn go_to_bookmark(&mut self, forward: bool) {
+        let current = self.caret_offset();
+        let target = if forward {
+            self.bookmarks.next_after(current)
+        } else {
+            self.bookmarks.previous_before(current)
+        };
+        match target.map(|b| b.offset) {
+            Some(offset) => self.jump_to_offset(offset),
+            None => self.status_message = "No bookmarks".to_string(),
+        }
+    }
+
+    fn persist_bookmarks(&mut self) {
+        let Some(ref reader) = self.file_reader else {
+            return;
+        };
+        self.bookmark_store.set(reader.path(), &self.bookmarks);
+        if let Err(e) = self.bookmark_store.save() {
+            self.status_message = format!("Failed to save bookmarks: {}", e);
+        }
+    }
+
+    /// Byte offset where `line` (0-indexed) starts, resolved against the file for sparse indexes.
+    fn line_start_offset(&self, line: usize) -> Option<usize> {
+        let reader = self.file_reader.as_ref()?;
+        self.line_indexer
+            .get_line_with_reader(line, reader)
+            .map(|(start, _)| start.min(reader.len()))
+    }
+
+    /// Byte range covering lines `from` to `to` (1-based, inclusive; empty `to` means
+    /// the last line) plus a short description.
+    fn parse_line_range(&self, from: &str, to: &str) -> Result<(Range<usize>, String), String> {
+        let Some(ref reader) = self.file_reader else {
+            return Err("No file open".to_string());
+        };
+        let file_len = reader.len();
+        let first: usize = from.parse().map_err(|_| "Invalid start line".to_string())?;
+        let last: usize = if to.is_empty() {
+            self.line_indexer.total_lines()
+        } else {
+            to.parse().map_err(|_| "Invalid end line".to_string())?
+        };
+        if first == 0 || last < first {
+            return Err("Line range is empty".to_string());
+        }
+        let start = self
+            .line_start_offset(first - 1)
+            .ok_or_else(|| "Start line out of range".to_string())?;
+        let end = self
+            .line_indexer
+            .get_line_with_reader(last - 1, reader)
+            .map(|(_, end)| end.min(file_len))
+            .unwrap_or(file_len);
+        Ok((start..end, format!("lines {}–{}", first, last)))
+    }
+
+    /// Byte range `from..to` (empty `to` means the end of the file) plus a short description.
+    fn parse_byte_range(&self, from: &str, to: &str) -> Result<(Range<usize>, String), String> {
+        let file_len = self.file_reader.as_ref().map_or(0, |r| r.len());
+        let start: usize = from
+            .parse()
+            .map_err(|_| "Invalid start offset".to_string())?;
+        let end: usize = if to.is_empty() {
+            file_len
+        } else {
+            to.parse().map_err(|_| "Invalid end offset".to_string())?
+        };
+        if end <= start {
+            return Err("Byte range is empty".to_string());
+        }
+        Ok((start..end.min(file_len), format!("bytes {}–{}", start, end)))
+    }
+
+    /// The selection's byte range, if anything is selected.
+    fn selected_range(&self) -> Result<Range<usize>, String> {
+        self.selection
+            .map(|s| s.range())
+            .filter(|r| !r.is_empty())
+            .ok_or_else(|| "Nothing is selected".to_string())
+    }
+
+    /// Converts the selected search scope into a byte range plus a short description.
+    fn resolve_search_range(&self) -> Result<(Option<Range<usize>>, Option<String>), String> {
+        let Some(ref reader) = self.file_reader else {
+            return Ok((None, None));
+        };
+        let file_len = reader.len();
+        let from = self.scope_from_input.trim();
+        let to = self.scope_to_input.trim();
+
+        match self.search_scope {
+            SearchScope::WholeFile => Ok((None, None)),
+            SearchScope::Lines => {
+                let (range, label) = self.parse_line_range(from, to)?;
+                Ok((Some(range), Some(label)))
+            }
+            SearchScope::Bytes => {
+                let (range, label) = self.parse_byte_range(from, to)?;
+                Ok((Some(range), Some(label)))
+            }
+            SearchScope::FromCursor => {
+                let start = self.caret_offset();
+                let line = self.line_indexer.find_line_at_offset(start);
+                Ok((
+                    Some(start..file_len),
+                    Some(format!("line {} to end", line + 1)),
+                ))
+            }
+            SearchScope::Selection => {
+                let range = self.selected_range()?;
+                let label = format!("selection ({} bytes)", range.len());
+                Ok((Some(range), Some(label)))
+            }
+        }
+    }
+
+    fn current_search_query(&self) -> SearchQuery {
+        SearchQuery {
+            query: self.search_query.clone(),
+            use_regex: self.use_regex,
+            case_sensitive: self.case_sensitive,
+            whole_word: self.whole_word,
+            max_edits: self.fuzzy_search.then_some(self.fuzzy_max_edits),
+            multi_pattern: self.multi_pattern,
+            hex_bytes: self.hex_search,
+        }
+    }
+
+    /// Patterns to search for: one per line in multi-pattern mode, otherwise the whole query.
+    fn search_patterns(&self) -> Vec<String> {
+        if self.multi_pattern {
+            self.search_query
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect()
+        } else if self.search_query.is_empty() {
+            Vec::new()
+        } else {
+            vec![self.search_query.clone()]
+        }
+    }
+
+    fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            use_regex: self.use_regex && !self.fuzzy_search,
+            case_sensitive: self.case_sensitive,
+            whole_word: self.whole_word,
+            max_edits: self.fuzzy_search.then_some(self.fuzzy_max_edits),
+            hex_bytes: self.hex_search,
+        }
+    }
+
+    fn apply_search_query(&mut self, query: &SearchQuery) {
+        self.search_query = query.query.clone();
+        self.use_regex = query.use_regex;
+        self.case_sensitive = query.case_sensitive;
+        self.whole_word = query.whole_word;
+        self.multi_pattern = query.multi_pattern;
+        self.hex_search = query.hex_bytes;
+        self.fuzzy_search = query.max_edits.is_some();
+        if let Some(max_edits) = query.max_edits {
+            self.fuzzy_max_edits = max_edits;
+        }
+    }
+
+    fn run_saved_search(&mut self, index: usize) {
+        let Some(saved) = self.search_history.saved.get(index).cloned() else {
+            return;
+        };
+        self.apply_search_query(&saved.query);
+        self.show_search_bar = true;
+        self.perform_search(true);
+    }
+
+    /// Keys bound to running the saved search at `index`, if any.
+    fn saved_search_shortcut(&self, index: usize) -> String {
+        Action::RUN_SAVED_SEARCH
+            .get(index)
+            .map(|&action| self.keymap.shortcut_text(action))
+            .unwrap_or_default()
+    }
+
+    fn persist_search_history(&mut self) {
+        if let Err(e) = self.search_history.save() {
+            self.status_message = format!("Failed to save search history: {}", e);
+        }
+    }
+
     fn poll_search_results(&mut self) {
         if !self.search_in_progress {
             return;
@@ -332,10 +1381,23 @@ impl TextViewerApp {
             // Process all available messages
             while let Ok(msg) = rx.try_recv() {
                 match msg {
-                    SearchMessage::CountResult(count) => {
-                        self.total_search_results += count;
-                        if self.search_find_all {
-                            self.status_message =
+                    SearchMessage::PatternCountResult(counts) => {
+                        if self.pattern_counts.len() < counts.len() {
+                            self.pattern_counts.resize(counts.len(), 0);
+                        }
+                        for (total, count) in self.pattern_counts.iter_mut().zip(counts) {
+                            *total += count;
+                        }
+                    }
+                    SearchMessage::HistogramResult(histogram) => {
+                        if let Some(timeline) = self.timeline.as_mut() {
+                            timeline.merge(histogram);
+                        }
+                    }
+                    SearchMessage::CountResult(count) => {
+                        self.total_search_results += count;
+                        if self.search_find_all {
+                            self.status_message =
                                 format!("Found {} matches...", self.total_search_results);
                         }
                     }
@@ -405,7 +1467,10 @@ impl TextViewerApp {
                 let total = self.total_search_results;
                 if total > 0 {
                     if self.search_find_all {
-                        self.status_message = format!("Found {} matches", total);
+                        self.status_message = match &self.search_scope_label {
+                            Some(scope) => format!("Found {} matches in {}", total, scope),
+                            None => format!("Found {} matches", total),
+                        };
                     } else {
                         self.status_message =
                             "Showing first match. Run Find All to see every result.".to_string();
@@ -413,11 +1478,7 @@ impl TextViewerApp {
 
                     // Ensure we scroll to the first result if we haven't yet
                     if self.scroll_to_row.is_none() && !self.search_results.is_empty() {
-                        let target_line = self
-                            .line_indexer
-                            .find_line_at_offset(self.search_results[0].byte_offset);
-                        self.scroll_line = target_line;
-                        self.scroll_to_row = Some(target_line);
+                        self.scroll_to_offset(self.search_results[0].byte_offset);
                     }
                 } else {
                     self.status_message = "No matches found".to_string();
@@ -434,11 +1495,7 @@ impl TextViewerApp {
                     && !self.search_results.is_empty()
                     && self.current_result_index == 0
                 {
-                    let target_line = self
-                        .line_indexer
-                        .find_line_at_offset(self.search_results[0].byte_offset);
-                    self.scroll_line = target_line;
-                    self.scroll_to_row = Some(target_line);
+                    self.scroll_to_offset(self.search_results[0].byte_offset);
                 }
             }
         }
@@ -450,18 +1507,26 @@ impl TextViewerApp {
         }
 
         let mut done = false;
+        let mut finished = None;
+        let mut previews = None;
         if let Some(ref rx) = self.replace_message_rx {
             while let Ok(msg) = rx.try_recv() {
                 match msg {
-                    ReplaceMessage::Progress(processed, total) => {
-                        let progress = processed as f32 / total as f32;
+                    ReplaceMessage::Progress(processed, total, count) => {
+                        let progress = processed as f32 / total.max(1) as f32;
                         self.replace_progress = Some(progress);
-                        self.replace_status_message =
-                            Some(format!("Replacing... {:.1}%", progress * 100.0));
+                        self.replace_status_message = Some(if self.replace_output.is_some() {
+                            format!("Replacing... {:.1}% ({} replaced)", progress * 100.0, count)
+                        } else {
+                            format!("Previewing... {:.1}%", progress * 100.0)
+                        });
+                    }
+                    ReplaceMessage::Done(count) => {
+                        finished = Some(count);
+                        done = true;
                     }
-                    ReplaceMessage::Done => {
-                        self.replace_status_message = Some("Replacement complete.".to_string());
-                        self.status_message = "Replacement complete.".to_string();
+                    ReplaceMessage::Preview(found) => {
+                        previews = Some(found);
                         done = true;
                     }
                     ReplaceMessage::Error(e) => {
@@ -478,10 +1543,66 @@ impl TextViewerApp {
             self.replace_message_rx = None;
             self.replace_cancellation_token = None;
             self.replace_progress = None;
+            let output = self.replace_output.take();
+            let plan = self.replace_pending_plan.take();
+            let reopen_line = self.replace_reopen_line.take();
+            if let (Some(line), Some(output)) = (reopen_line, &output) {
+                // Whatever the outcome, the released file is opened again
+                self.reopen_after_replace(output.clone(), line);
+            }
+            if let (Some(count), Some(output)) = (finished, output) {
+                self.finish_replace_all(count, output, reopen_line.is_some());
+            }
+            if let (Some(previews), Some(mut plan)) = (previews, plan) {
+                if previews.is_empty() {
+                    self.replace_status_message = Some("No matches to replace".to_string());
+                } else {
+                    self.replace_status_message = None;
+                    plan.previews = previews;
+                    self.replace_plan = Some(plan);
+                }
+            }
+        }
+    }
+
+    /// Opens the file an in-place Replace All released at `line`, keeping the run's
+    /// status message.
+    fn reopen_after_replace(&mut self, path: PathBuf, line: usize) {
+        let status = std::mem::take(&mut self.status_message);
+        self.open_file(path);
+        if self.file_reader.is_some() {
+            self.scroll_to(line);
+            // The binary file prompt was answered before the run
+            self.show_binary_prompt = false;
+            self.status_message = status;
         }
     }
 
+    /// Reports a finished Replace All.
+    fn finish_replace_all(&mut self, count: usize, output: PathBuf, in_place: bool) {
+        let summary = if in_place {
+            let backup = backup_path(&output);
+            if self.config.editing.backup_on_save && backup.exists() {
+                format!(
+                    "Replaced {} matches in place (backup: {})",
+                    count,
+                    backup.display()
+                )
+            } else {
+                format!("Replaced {} matches in place", count)
+            }
+        } else {
+            format!("Replaced {} matches into {}", count, output.display())
+        };
+        self.replace_status_message = Some(summary.clone());
+        self.status_message = summary;
+    }
+
     fn perform_single_replace(&mut self) {
+        if self.hex_search {
+            self.status_message = "Replace is not available for hex byte searches".to_string();
+            return;
+        }
         if self.search_results.is_empty() {
             return;
         }
@@ -497,9 +1618,14 @@ impl TextViewerApp {
         }
 
         let match_info = self.search_results[local_index].clone();
+        let old = match_info.byte_offset..match_info.byte_offset + match_info.match_len;
+        if self.pending_edits.overlaps(old) {
+            self.status_message = "This match already has a pending replacement".to_string();
+            return;
+        }
 
         // Queue the replacement
-        self.pending_replacements.push(PendingReplacement {
+        self.pending_edits.push(PendingReplacement {
             offset: match_info.byte_offset,
             old_len: match_info.match_len,
             new_text: self.replace_query.clone(),
@@ -508,6 +1634,45 @@ impl TextViewerApp {
         self.status_message = "Replacement pending. Save to apply changes.".to_string();
     }
 
+    fn undo_replacement(&mut self) {
+        match self.pending_edits.undo() {
+            Some(replacement) => {
+                self.jump_to_offset(replacement.offset);
+                self.status_message =
+                    format!("Undid replacement ({} pending)", self.pending_edits.len());
+            }
+            None => self.status_message = "Nothing to undo".to_string(),
+        }
+        self.unsaved_changes = !self.pending_edits.is_empty();
+    }
+
+    fn redo_replacement(&mut self) {
+        match self.pending_edits.redo() {
+            Some(replacement) => {
+                self.jump_to_offset(replacement.offset);
+                self.status_message =
+                    format!("Redid replacement ({} pending)", self.pending_edits.len());
+            }
+            None => self.status_message = "Nothing to redo".to_string(),
+        }
+        self.unsaved_changes = !self.pending_edits.is_empty();
+    }
+
+    /// Drops the open file's memory map so the file can be replaced on disk, first
+    /// cancelling the searches and exports whose threads share it.
+    fn release_file(&mut self) {
+        let tokens = [
+            &self.search_cancellation_token,
+            &self.range_export.cancellation_token,
+            &self.match_export.cancellation_token,
+        ];
+        for token in tokens.into_iter().flatten() {
+            token.store(true, Ordering::Relaxed);
+        }
+        self.search_in_progress = false;
+        self.file_reader = None;
+    }
+
     fn save_file(&mut self) {
         let Some(ref reader) = self.file_reader else {
             return;
@@ -515,121 +1680,276 @@ impl TextViewerApp {
         let input_path = reader.path().clone();
         let encoding = reader.encoding();
 
-        if let Some(output_path) = rfd::FileDialog::new()
+        let Some(output_path) = rfd::FileDialog::new()
             .set_file_name(input_path.file_name().unwrap().to_string_lossy())
             .save_file()
-        {
-            // If saving to the same file
-            if output_path == input_path {
-                // Apply pending replacements in-place if possible
-                // We need to close the reader first to release the lock
-                self.file_reader = None;
-
-                let mut success = true;
-                for replacement in &self.pending_replacements {
-                    if let Err(e) = Replacer::replace_single(
-                        &input_path,
-                        replacement.offset,
-                        replacement.old_len,
-                        &replacement.new_text,
-                    ) {
-                        self.status_message = format!("Error saving: {}", e);
-                        success = false;
-                        break;
-                    }
-                }
+        else {
+            return;
+        };
+
+        let edits: Vec<Edit> = self
+            .pending_edits
+            .as_slice()
+            .iter()
+            .map(|r| Edit::new(r.offset, r.old_len, r.new_text.as_bytes()))
+            .collect();
+        let sorted = match sort_edits(&edits, reader.len()) {
+            Ok(sorted) => sorted,
+            Err(e) => {
+                self.status_message = format!("Error saving: {}", e);
+                return;
+            }
+        };
 
-                if success {
-                    self.pending_replacements.clear();
+        let save_options = SaveOptions {
+            backup: self.config.editing.backup_on_save,
+        };
+
+        // If saving to the same file
+        if output_path == input_path {
+            let mut top_offset = self.top_offset();
+            // Release the memory map before the file is rewritten
+            self.release_file();
+
+            match Replacer::apply_edits(&input_path, &output_path, &sorted, save_options) {
+                Ok(()) => {
+                    self.pending_edits.clear();
                     self.unsaved_changes = false;
-                    self.status_message = "File saved successfully".to_string();
+                    // Keep the caret, selection, bookmarks and view on the same text
+                    if let Some(selection) = self.selection.as_mut() {
+                        selection.anchor = shifted_offset(&sorted, selection.anchor);
+                        selection.caret = shifted_offset(&sorted, selection.caret);
+                    }
+                    self.bookmarks
+                        .map_offsets(|offset| shifted_offset(&sorted, offset));
+                    top_offset = shifted_offset(&sorted, top_offset);
+                    self.status_message = format!("File saved ({} replacements)", sorted.len());
                 }
+                Err(e) => self.status_message = format!("Error saving: {}", e),
+            }
 
-                // Re-open file
-                match FileReader::new(input_path.clone(), encoding) {
-                    Ok(reader) => {
-                        self.file_reader = Some(Arc::new(reader));
-                        self.line_indexer
-                            .index_file(self.file_reader.as_ref().unwrap());
+            // Re-open file
+            match FileReader::new(input_path.clone(), encoding) {
+                Ok(reader) => {
+                    self.file_reader = Some(Arc::new(reader));
+                    self.line_indexer
+                        .index_file(self.file_reader.as_ref().unwrap());
+                    self.persist_bookmarks();
+                    self.scroll_to_offset(top_offset);
+                    if !self.search_query.is_empty() {
                         self.perform_search(self.search_find_all);
                     }
-                    Err(e) => {
-                        self.status_message = format!("Error re-opening file: {}", e);
-                    }
                 }
-            } else {
-                // Saving to a different file
-                // Fallback: Copy file to output, then apply replacements in-place on the output file.
-                if std::fs::copy(&input_path, &output_path).is_ok() {
-                    let mut success = true;
-                    for replacement in &self.pending_replacements {
-                        if let Err(e) = Replacer::replace_single(
-                            &output_path,
-                            replacement.offset,
-                            replacement.old_len,
-                            &replacement.new_text,
-                        ) {
-                            self.status_message = format!("Error saving: {}", e);
-                            success = false;
-                            break;
-                        }
-                    }
-                    if success {
-                        self.pending_replacements.clear();
-                        self.unsaved_changes = false;
-                        self.status_message = "File saved successfully".to_string();
-                        self.open_file(output_path);
-                    }
-                } else {
-                    self.status_message = "Error copying file for save".to_string();
+                Err(e) => {
+                    self.status_message = format!("Error re-opening file: {}", e);
+                }
+            }
+        } else {
+            // Saving to a different file: stream the original through the edits
+            match Replacer::apply_edits(&input_path, &output_path, &sorted, save_options) {
+                Ok(()) => {
+                    self.pending_edits.clear();
+                    self.unsaved_changes = false;
+                    self.status_message = "File saved successfully".to_string();
+                    self.open_file(output_path);
                 }
+                Err(e) => self.status_message = format!("Error saving: {}", e),
             }
         }
     }
 
+    /// Starts Replace All by previewing its first matches; the run itself waits for
+    /// `commit_replace`.
     fn perform_replace(&mut self) {
         if self.replace_in_progress {
             return;
         }
 
+        if self.hex_search {
+            self.status_message = "Replace is not available for hex byte searches".to_string();
+            return;
+        }
+        if self.multi_pattern {
+            self.status_message = "Replace All works on a single pattern".to_string();
+            return;
+        }
+        if self.fuzzy_search {
+            self.status_message = "Replace All needs an exact query, not fuzzy".to_string();
+            return;
+        }
+
         let Some(ref reader) = self.file_reader else {
             return;
         };
         let input_path = reader.path().clone();
 
-        // Ask for output file
-        if let Some(output_path) = rfd::FileDialog::new()
-            .set_file_name(format!(
-                "{}.modified",
-                input_path.file_name().unwrap().to_string_lossy()
-            ))
-            .save_file()
-        {
-            let query = self.search_query.clone();
-            let replace_with = self.replace_query.clone();
-            let use_regex = self.use_regex;
+        if self.replace_in_place && !self.pending_edits.is_empty() {
+            self.status_message =
+                "Save or undo the pending replacements before replacing in place".to_string();
+            return;
+        }
 
-            let (tx, rx) = std::sync::mpsc::channel();
-            self.replace_message_rx = Some(rx);
-            self.replace_in_progress = true;
-            self.replace_progress = Some(0.0);
-            self.replace_status_message = None;
+        let (range, scope_label) = match self.resolve_search_range() {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
 
-            let cancel_token = Arc::new(AtomicBool::new(false));
-            self.replace_cancellation_token = Some(cancel_token.clone());
+        let plan = ReplacePlan {
+            query: self.search_query.clone(),
+            replace_with: self.replace_query.clone(),
+            options: self.search_options(),
+            range,
+            scope_label,
+            previews: Vec::new(),
+        };
+        let query = plan.query.clone();
+        let replace_with = plan.replace_with.clone();
+        let options = plan.options;
+        let range = plan.range.clone();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.replace_message_rx = Some(rx);
+        self.replace_in_progress = true;
+        self.replace_progress = Some(0.0);
+        self.replace_status_message = None;
+        self.replace_output = None;
+        self.replace_plan = None;
+        self.replace_pending_plan = Some(plan);
+
+        let limits = self.config.replace_limits();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+        self.replace_cancellation_token = Some(cancel_token.clone());
 
-            std::thread::spawn(move || {
-                Replacer::replace_all(
-                    &input_path,
-                    &output_path,
-                    &query,
-                    &replace_with,
-                    use_regex,
-                    tx,
-                    cancel_token,
-                );
-            });
+        std::thread::spawn(move || {
+            Replacer::preview_replace_all(
+                &input_path,
+                &query,
+                &replace_with,
+                options,
+                range,
+                limits,
+                REPLACE_PREVIEW_PAIRS,
+                tx,
+                cancel_token,
+            );
+        });
+    }
+
+    /// Runs the Replace All confirmed in the preview.
+    fn commit_replace(&mut self) {
+        let Some(plan) = self.replace_plan.take() else {
+            return;
+        };
+        if self.replace_in_progress {
+            return;
+        }
+        let Some(ref reader) = self.file_reader else {
+            return;
+        };
+        let input_path = reader.path().clone();
+
+        let output_path = if self.replace_in_place {
+            if !self.pending_edits.is_empty() {
+                self.status_message =
+                    "Save or undo the pending replacements before replacing in place".to_string();
+                return;
+            }
+            input_path.clone()
+        } else {
+            // Ask for output file
+            let Some(output_path) = rfd::FileDialog::new()
+                .set_file_name(format!(
+                    "{}.modified",
+                    input_path.file_name().unwrap().to_string_lossy()
+                ))
+                .save_file()
+            else {
+                return;
+            };
+            output_path
+        };
+
+        let ReplacePlan {
+            query,
+            replace_with,
+            options: search_options,
+            range,
+            scope_label,
+            ..
+        } = plan;
+        let options = SaveOptions {
+            backup: self.config.editing.backup_on_save,
+        };
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.replace_message_rx = Some(rx);
+        self.replace_in_progress = true;
+        self.replace_progress = Some(0.0);
+        self.replace_status_message = None;
+        self.replace_output = Some(output_path.clone());
+
+        if let Some(scope) = scope_label {
+            self.status_message = format!("Replacing in {}", scope);
+        }
+
+        if self.replace_in_place {
+            // Release the memory map before the file is swapped out; it is reopened when
+            // the run ends
+            self.release_file();
+            self.replace_reopen_line = Some(self.scroll_line);
+        }
+
+        let limits = self.config.replace_limits();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+        self.replace_cancellation_token = Some(cancel_token.clone());
+
+        std::thread::spawn(move || {
+            Replacer::replace_all(
+                &input_path,
+                &output_path,
+                &query,
+                &replace_with,
+                search_options,
+                range,
+                limits,
+                options,
+                tx,
+                cancel_token,
+            );
+        });
+    }
+
+    /// Whether the loaded result at global `index` lies under a pending replacement,
+    /// whose text the view shows instead of the match.
+    fn result_is_replaced(&self, index: usize) -> bool {
+        index
+            .checked_sub(self.search_page_start_index)
+            .and_then(|local| self.search_results.get(local))
+            .is_some_and(|r| {
+                self.pending_edits
+                    .overlaps(r.byte_offset..r.byte_offset + r.match_len)
+            })
+    }
+
+    /// The result after (or before) the current one, skipping loaded results that a
+    /// pending replacement covers.
+    fn step_result_index(&self, forward: bool) -> usize {
+        let total = self.total_search_results;
+        let mut index = self.current_result_index;
+        for _ in 0..total {
+            index = if forward {
+                (index + 1) % total
+            } else {
+                (index + total - 1) % total
+            };
+            if !self.result_is_replaced(index) {
+                break;
+            }
         }
+        index
     }
 
     fn go_to_next_result(&mut self) {
@@ -637,7 +1957,7 @@ impl TextViewerApp {
             return;
         }
 
-        let next_index = (self.current_result_index + 1) % self.total_search_results;
+        let next_index = self.step_result_index(true);
 
         // Check if next_index is within current page
         let page_end_index = self.search_page_start_index + self.search_results.len();
@@ -646,11 +1966,8 @@ impl TextViewerApp {
             // In current page
             self.current_result_index = next_index;
             let local_index = next_index - self.search_page_start_index;
-            let result = &self.search_results[local_index];
-            let target_line = self.line_indexer.find_line_at_offset(result.byte_offset);
-            self.scroll_line = target_line;
-            self.scroll_to_row = Some(target_line);
-            self.pending_scroll_target = Some(target_line);
+            let offset = self.search_results[local_index].byte_offset;
+            self.scroll_to_offset(offset);
         } else {
             // Need to fetch next page
             // If we are wrapping around to 0
@@ -662,7 +1979,8 @@ impl TextViewerApp {
                 // If we are just moving to the next page sequentially, we can use the last result's offset.
                 if let Some(last_result) = self.search_results.last() {
                     // We should record the current page start offset before moving
-                    if self.page_offsets.len() <= next_index / 1000 && self.page_offsets.is_empty()
+                    if self.page_offsets.len() <= next_index / self.search_page_size
+                        && self.page_offsets.is_empty()
                     {
                         self.page_offsets.push(0);
                     }
@@ -683,11 +2001,7 @@ impl TextViewerApp {
             return;
         }
 
-        let prev_index = if self.current_result_index == 0 {
-            self.total_search_results - 1
-        } else {
-            self.current_result_index - 1
-        };
+        let prev_index = self.step_result_index(false);
 
         // Check if prev_index is within current page
         let page_end_index = self.search_page_start_index + self.search_results.len();
@@ -696,11 +2010,8 @@ impl TextViewerApp {
             // In current page
             self.current_result_index = prev_index;
             let local_index = prev_index - self.search_page_start_index;
-            let result = &self.search_results[local_index];
-            let target_line = self.line_indexer.find_line_at_offset(result.byte_offset);
-            self.scroll_line = target_line;
-            self.scroll_to_row = Some(target_line);
-            self.pending_scroll_target = Some(target_line);
+            let offset = self.search_results[local_index].byte_offset;
+            self.scroll_to_offset(offset);
         } else {
             // Need to fetch previous page (or last page if wrapping)
             if prev_index == self.total_search_results - 1 {
@@ -708,9 +2019,8 @@ impl TextViewerApp {
             } else {
                 // Fetch previous page
                 // We need the start offset of the page containing `prev_index`.
-                // We assume pages are 1000 items.
-                let target_page_idx = prev_index / 1000;
-                let target_page_start_index = target_page_idx * 1000;
+                let target_page_idx = prev_index / self.search_page_size;
+                let target_page_start_index = target_page_idx * self.search_page_size;
 
                 if let Some(&offset) = self.page_offsets.get(target_page_idx) {
                     self.fetch_page(target_page_start_index, offset);
@@ -737,7 +2047,7 @@ impl TextViewerApp {
         self.search_page_start_index = start_index;
 
         // Update page_offsets
-        let page_idx = start_index / 1000;
+        let page_idx = start_index / self.search_page_size;
         if page_idx >= self.page_offsets.len() {
             if page_idx == self.page_offsets.len() {
                 self.page_offsets.push(start_offset);
@@ -748,12 +2058,13 @@ impl TextViewerApp {
         }
 
         let reader = reader.clone();
-        let query = self.search_query.clone();
-        let use_regex = self.use_regex;
-        let case_sensitive = self.case_sensitive;
-        let (tx, rx) = std::sync::mpsc::sync_channel(10_000);
+        let query = self.search_engine.patterns().to_vec();
+        let options = self.search_engine.options();
+        let range = self.search_engine.range();
+        let (tx, rx) = std::sync::mpsc::sync_channel(self.config.memory.search_channel_capacity);
         self.search_message_rx = Some(rx);
         self.search_in_progress = true;
+        let page_size = self.search_page_size;
 
         let cancel_token = Arc::new(AtomicBool::new(false));
         self.search_cancellation_token = Some(cancel_token.clone());
@@ -761,13 +2072,14 @@ impl TextViewerApp {
         self.status_message = format!(
             "Loading results {}...{}",
             start_index + 1,
-            start_index + 1000
+            start_index + page_size
         );
 
         std::thread::spawn(move || {
             let mut engine = SearchEngine::new();
-            engine.set_query(query, use_regex, case_sensitive);
-            engine.fetch_matches(reader, tx, start_offset, 1000, cancel_token);
+            engine.set_patterns(query, options);
+            engine.set_range(range);
+            engine.fetch_matches(reader, tx, start_offset, page_size, cancel_token);
         });
     }
 
@@ -788,46 +2100,135 @@ impl TextViewerApp {
         }
     }
 
+    /// Adds a menu button for `action` showing its key binding. Menus, shortcuts and
+    /// the command palette all run actions through `run_action`.
+    fn menu_action(&mut self, ui: &mut egui::Ui, label: &str, action: Action) {
+        let button = egui::Button::new(label).shortcut_text(self.keymap.shortcut_text(action));
+        if ui.add(button).clicked() {
+            let ctx = ui.ctx().clone();
+            self.run_action(&ctx, action);
+            ui.close_menu();
+        }
+    }
+
     fn render_menu_bar(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("Open...").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_file() {
-                            // Auto-detect encoding
-                            if let Ok(mut file) = std::fs::File::open(&path) {
-                                let mut buffer = [0; 4096];
-                                if let Ok(n) = std::io::Read::read(&mut file, &mut buffer) {
-                                    self.selected_encoding = detect_encoding(&buffer[..n]);
-                                }
+                    self.menu_action(ui, "Open...", Action::OpenFile);
+
+                    ui.menu_button("Open Recent", |ui| {
+                        if self.session.recent_files.is_empty() {
+                            ui.label("No recent files");
+                        }
+                        let mut open_path = None;
+                        for file in &self.session.recent_files {
+                            let name = file
+                                .path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().into_owned())
+                                .unwrap_or_else(|| file.path.display().to_string());
+                            if ui
+                                .button(name)
+                                .on_hover_text(file.path.display().to_string())
+                                .clicked()
+                            {
+                                open_path = Some(file.path.clone());
                             }
-                            self.open_file(path);
                         }
-                        ui.close_menu();
-                    }
+                        if let Some(path) = open_path {
+                            self.open_recent_file(path);
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        self.menu_action(ui, "Clear Recent Files", Action::ClearRecentFiles);
+                    });
 
                     if ui
-                        .add_enabled(self.unsaved_changes, egui::Button::new("Save (Ctrl+S)"))
-                        .clicked()
+                        .checkbox(
+                            &mut self.session.reopen_last_session,
+                            "Reopen Last Session on Startup",
+                        )
+                        .changed()
                     {
-                        self.save_file();
-                        ui.close_menu();
+                        self.persist_session();
                     }
 
-                    if ui.button("File Info").clicked() {
-                        self.show_file_info = !self.show_file_info;
-                        ui.close_menu();
-                    }
+                    ui.add_enabled_ui(self.unsaved_changes, |ui| {
+                        self.menu_action(ui, "Save", Action::Save);
+                    });
 
-                    if ui.button("Exit").clicked() {
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                    }
+                    self.menu_action(ui, "File Info", Action::ShowFileInfo);
+
+                    self.menu_action(ui, "Preferences...", Action::Preferences);
+
+                    self.menu_action(ui, "Exit", Action::Exit);
+                });
+
+                ui.menu_button("Edit", |ui| {
+                    ui.add_enabled_ui(self.pending_edits.can_undo(), |ui| {
+                        self.menu_action(ui, "Undo Replacement", Action::Undo);
+                    });
+                    ui.add_enabled_ui(self.pending_edits.can_redo(), |ui| {
+                        self.menu_action(ui, "Redo Replacement", Action::Redo);
+                    });
+                    ui.checkbox(&mut self.show_pending_edits, "Show Pending Replacements")
+                        .on_hover_text(self.keymap.shortcut_text(Action::TogglePendingEditsPanel));
+                    ui.separator();
+                    let has_selection = self.selection.is_some_and(|s| !s.is_empty());
+                    ui.add_enabled_ui(has_selection, |ui| {
+                        // Ctrl+C comes from the platform's copy event, not the keymap
+                        let shortcut = match self.keymap.shortcut_text(Action::Copy) {
+                            keys if keys.is_empty() => "Ctrl+C".to_string(),
+                            keys => format!("Ctrl+C, {}", keys),
+                        };
+                        if ui
+                            .add(egui::Button::new("Copy").shortcut_text(shortcut))
+                            .clicked()
+                        {
+                            self.run_action(ctx, Action::Copy);
+                            ui.close_menu();
+                        }
+                    });
+                    self.menu_action(ui, "Select All", Action::SelectAll);
+                    ui.separator();
+                    self.menu_action(ui, "Copy or Export Range...", Action::ExportRange);
                 });
 
                 ui.menu_button("View", |ui| {
-                    ui.checkbox(&mut self.wrap_mode, "Word Wrap");
-                    ui.checkbox(&mut self.show_line_numbers, "Line Numbers");
-                    ui.checkbox(&mut self.dark_mode, "Dark Mode");
+                    ui.checkbox(&mut self.wrap_mode, "Word Wrap")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleWrap));
+                    ui.checkbox(&mut self.show_line_numbers, "Line Numbers")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleLineNumbers));
+                    ui.checkbox(&mut self.dark_mode, "Dark Mode")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleDarkMode));
+                    ui.checkbox(&mut self.show_timeline, "Match Timeline")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleTimeline));
+
+                    ui.separator();
+
+                    let mut hex_view = self.hex_view;
+                    if ui
+                        .checkbox(&mut hex_view, "Hex View")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleHexView))
+                        .changed()
+                    {
+                        self.set_hex_view(hex_view);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Bytes per Row:");
+                        let mut bytes_per_row = self.hex_bytes_per_row;
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut bytes_per_row)
+                                    .range(1..=MAX_HEX_BYTES_PER_ROW),
+                            )
+                            .changed()
+                        {
+                            self.set_hex_bytes_per_row(bytes_per_row);
+                        }
+                    });
+                    ui.checkbox(&mut self.hex_decimal_offsets, "Decimal Offsets");
 
                     ui.separator();
 
@@ -836,45 +2237,65 @@ impl TextViewerApp {
 
                     ui.separator();
 
-                    if ui.button("Select Encoding").clicked() {
-                        self.show_encoding_selector = true;
-                        ui.close_menu();
-                    }
+                    self.menu_action(ui, "Select Encoding", Action::SelectEncoding);
                 });
 
                 ui.menu_button("Search", |ui| {
-                    if ui
-                        .add(egui::Button::new("Find").shortcut_text("Ctrl+F"))
-                        .clicked()
-                    {
-                        self.show_search_bar = true;
-                        self.focus_search_input = true;
-                        ui.close_menu();
-                    }
-                    if ui
-                        .add(egui::Button::new("Replace").shortcut_text("Ctrl+R"))
-                        .clicked()
-                    {
-                        self.show_search_bar = true;
-                        self.show_replace = !self.show_replace;
-                        ui.close_menu();
-                    }
+                    self.menu_action(ui, "Find", Action::Find);
+                    self.menu_action(ui, "Replace", Action::Replace);
                     ui.separator();
                     ui.checkbox(&mut self.use_regex, "Use Regex");
                     ui.checkbox(&mut self.case_sensitive, "Match Case");
+                    ui.checkbox(&mut self.whole_word, "Whole Word");
+                    ui.checkbox(&mut self.fuzzy_search, "Approximate Match");
+                    ui.checkbox(&mut self.hex_search, "Hex Bytes");
+                    ui.separator();
+                    ui.menu_button("Saved Searches", |ui| {
+                        if self.search_history.saved.is_empty() {
+                            ui.label("No saved searches");
+                        }
+                        let mut run_index = None;
+                        for (i, saved) in self.search_history.saved.iter().enumerate() {
+                            let button = egui::Button::new(&saved.name)
+                                .shortcut_text(self.saved_search_shortcut(i));
+                            if ui.add(button).on_hover_text(&saved.query.query).clicked() {
+                                run_index = Some(i);
+                            }
+                        }
+                        if let Some(i) = run_index {
+                            self.run_saved_search(i);
+                            ui.close_menu();
+                        }
+                    });
+                    self.menu_action(ui, "Manage Saved Searches...", Action::ManageSavedSearches);
+                    ui.separator();
+                    self.menu_action(ui, "Find in Files...", Action::FindInFiles);
+                    self.menu_action(ui, "Export Matches...", Action::ExportMatches);
+                });
+
+                ui.menu_button("Bookmarks", |ui| {
+                    self.menu_action(ui, "Toggle Bookmark", Action::ToggleBookmark);
+                    self.menu_action(ui, "Next Bookmark", Action::NextBookmark);
+                    self.menu_action(ui, "Previous Bookmark", Action::PreviousBookmark);
+                    ui.separator();
+                    ui.checkbox(&mut self.show_bookmarks, "Show Bookmarks Panel")
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleBookmarksPanel));
+                    self.menu_action(ui, "Clear Bookmarks", Action::ClearBookmarks);
                 });
 
                 ui.menu_button("Tools", |ui| {
+                    self.menu_action(ui, "Command Palette...", Action::CommandPalette);
+                    ui.separator();
+                    let mut tail_mode = self.tail_mode;
                     if ui
-                        .checkbox(&mut self.tail_mode, "Tail Mode (Auto-refresh)")
+                        .add(egui::Checkbox::new(
+                            &mut tail_mode,
+                            "Tail Mode (Auto-refresh)",
+                        ))
+                        .on_hover_text(self.keymap.shortcut_text(Action::ToggleTail))
                         .changed()
                     {
-                        if self.tail_mode {
-                            self.setup_file_watcher();
-                        } else {
-                            self.watcher = None;
-                            self.file_change_rx = None;
-                        }
+                        self.set_tail_mode(tail_mode);
                     }
                 });
             });
@@ -888,20 +2309,103 @@ impl TextViewerApp {
         egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.label("Search:");
-                let response =
-                    ui.add(egui::TextEdit::singleline(&mut self.search_query).desired_width(300.0));
+                let response = if self.multi_pattern {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.search_query)
+                            .desired_width(300.0)
+                            .desired_rows(3)
+                            .hint_text("One pattern per line"),
+                    )
+                } else {
+                    ui.add(egui::TextEdit::singleline(&mut self.search_query).desired_width(300.0))
+                };
 
                 if self.focus_search_input {
                     response.request_focus();
                     self.focus_search_input = false;
                 }
 
+                let mut picked_query = None;
+                ui.menu_button("🕘", |ui| {
+                    if self.search_history.recent.is_empty() {
+                        ui.label("No recent searches");
+                    }
+                    for entry in &self.search_history.recent {
+                        if ui.button(format_search_query(entry)).clicked() {
+                            picked_query = Some(entry.clone());
+                            ui.close_menu();
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Clear History").clicked() {
+                        self.search_history.clear_recent();
+                        self.persist_search_history();
+                        ui.close_menu();
+                    }
+                })
+                .response
+                .on_hover_text("Search history");
+                if let Some(query) = picked_query {
+                    self.apply_search_query(&query);
+                }
+
+                if ui.button("☆").on_hover_text("Save this search").clicked() {
+                    self.saved_search_name = self.search_query.clone();
+                    self.show_saved_searches = true;
+                }
+
                 ui.checkbox(&mut self.case_sensitive, "Aa")
                     .on_hover_text("Match Case");
-                ui.checkbox(&mut self.use_regex, ".*")
-                    .on_hover_text("Use Regex");
+                ui.add_enabled(
+                    !self.fuzzy_search,
+                    egui::Checkbox::new(&mut self.use_regex, ".*"),
+                )
+                .on_hover_text("Use Regex");
+                ui.checkbox(&mut self.whole_word, "W")
+                    .on_hover_text("Match Whole Word");
+                ui.checkbox(&mut self.fuzzy_search, "~")
+                    .on_hover_text("Approximate Match (literal queries only)");
+                ui.checkbox(&mut self.multi_pattern, "≡")
+                    .on_hover_text("Multiple patterns (one per line)");
+                ui.checkbox(&mut self.hex_search, "0x")
+                    .on_hover_text("Hex bytes, e.g. DE AD BE EF (other options do not apply)");
+                if self.fuzzy_search {
+                    ui.add(
+                        egui::DragValue::new(&mut self.fuzzy_max_edits)
+                            .range(1..=5)
+                            .prefix("k="),
+                    )
+                    .on_hover_text(
+                        "Maximum number of edits (insertions, deletions, substitutions)",
+                    );
+                }
 
-                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                egui::ComboBox::from_id_salt("search_scope")
+                    .selected_text(self.search_scope.label())
+                    .show_ui(ui, |ui| {
+                        for scope in SearchScope::ALL {
+                            ui.selectable_value(&mut self.search_scope, scope, scope.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Limit search and Replace All to part of the file");
+                if matches!(self.search_scope, SearchScope::Lines | SearchScope::Bytes) {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.scope_from_input)
+                            .desired_width(70.0)
+                            .hint_text("from"),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.scope_to_input)
+                            .desired_width(70.0)
+                            .hint_text("to (end)"),
+                    );
+                }
+
+                if !self.multi_pattern
+                    && response.lost_focus()
+                    && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                {
                     self.perform_search(false);
                 }
 
@@ -943,7 +2447,19 @@ impl TextViewerApp {
                 if total_results > 0 {
                     // Show current position over total
                     let current = (self.current_result_index + 1).min(total_results);
-                    ui.label(format!("{}/{}", current, total_results));
+                    let mut counter = format!("{}/{}", current, total_results);
+                    if let Some(scope) = &self.search_scope_label {
+                        counter.push_str(&format!(" in {}", scope));
+                    }
+                    // Loaded matches a pending replacement covers are skipped over
+                    let replaced = (self.search_page_start_index..)
+                        .take(self.search_results.len())
+                        .filter(|&i| self.result_is_replaced(i))
+                        .count();
+                    if replaced > 0 {
+                        counter.push_str(&format!(" ({} replaced)", replaced));
+                    }
+                    ui.label(counter);
                 }
 
                 ui.separator();
@@ -951,6 +2467,10 @@ impl TextViewerApp {
                 ui.label("Go to line:");
                 let response = ui
                     .add(egui::TextEdit::singleline(&mut self.goto_line_input).desired_width(80.0));
+                if self.focus_goto_input {
+                    response.request_focus();
+                    self.focus_goto_input = false;
+                }
 
                 if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                     self.go_to_line();
@@ -961,6 +2481,27 @@ impl TextViewerApp {
                 }
             });
 
+            if self.pattern_counts.len() > 1 {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Per pattern:");
+                    for (i, (pattern, count)) in self
+                        .search_engine
+                        .patterns()
+                        .iter()
+                        .zip(&self.pattern_counts)
+                        .enumerate()
+                    {
+                        ui.label(
+                            egui::RichText::new(format!(" {} ", pattern))
+                                .monospace()
+                                .color(egui::Color32::BLACK)
+                                .background_color(PATTERN_COLORS[i % PATTERN_COLORS.len()]),
+                        );
+                        ui.label(count.to_string());
+                    }
+                });
+            }
+
             if self.show_replace {
                 ui.separator();
                 ui.horizontal(|ui| {
@@ -978,21 +2519,41 @@ impl TextViewerApp {
                             }
                         }
                         ui.spinner();
-                        if let Some(progress) = self.replace_progress {
-                            ui.label(format!("{:.1}%", progress * 100.0));
+                        if let Some(ref msg) = self.replace_status_message {
+                            ui.label(msg);
                         }
                     } else {
-                        if ui.button("Replace").clicked() {
+                        // Replacements are text, which cannot stand in for hex bytes
+                        let enabled = !self.hex_search;
+                        let hint = "Not available for hex byte searches";
+                        if ui
+                            .add_enabled(enabled, egui::Button::new("Replace"))
+                            .on_disabled_hover_text(hint)
+                            .clicked()
+                        {
                             self.perform_single_replace();
                         }
-                        if ui.button("Replace All").clicked() {
+                        if ui
+                            .add_enabled(enabled, egui::Button::new("Replace All"))
+                            .on_disabled_hover_text(hint)
+                            .clicked()
+                        {
                             self.perform_replace();
                         }
+                        ui.checkbox(&mut self.replace_in_place, "In place")
+                            .on_hover_text(
+                                "Replace All rewrites the open file instead of writing a copy; \
+                             Preferences > Editing controls the .bak backup",
+                            );
                     }
                 });
 
-                if let Some(ref msg) = self.replace_status_message {
-                    ui.label(msg);
+                if self.replace_plan.is_some() {
+                    self.render_replace_preview(ui);
+                } else if let Some(ref msg) = self.replace_status_message {
+                    if !self.replace_in_progress {
+                        ui.label(msg);
+                    }
                 }
             }
 
@@ -1002,20 +2563,408 @@ impl TextViewerApp {
         });
     }
 
-    fn render_status_bar(&mut self, ctx: &egui::Context) {
-        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if let Some(ref reader) = self.file_reader {
-                    ui.label(format!("File: {}", reader.path().display()));
-                    ui.separator();
-                    ui.label(format!("Size: {} bytes", reader.len()));
-                    ui.separator();
-                    ui.label(format!("Lines: ~{}", self.line_indexer.total_lines()));
-                    ui.separator();
-                    ui.label(format!("Encoding: {}", reader.encoding().name()));
-                    ui.separator();
-                    ui.label(format!("Line: {}", self.scroll_line + 1));
-                } else {
+    /// The previewed Replace All under the replace row, with Confirm and Cancel.
+    fn render_replace_preview(&mut self, ui: &mut egui::Ui) {
+        let Some(ref plan) = self.replace_plan else {
+            return;
+        };
+        let mut confirm = false;
+        let mut cancel = false;
+        let mut jump_to = None;
+
+        ui.horizontal(|ui| {
+            let shown = plan.previews.len();
+            if shown < REPLACE_PREVIEW_PAIRS {
+                ui.label(format!("Replace All will change {} matches:", shown));
+            } else {
+                ui.label(format!("First {} matches Replace All will change:", shown));
+            }
+            let target = if self.replace_in_place {
+                "Replace in Place"
+            } else {
+                "Replace into Copy..."
+            };
+            confirm = ui.button(target).clicked();
+            cancel = ui.button("Cancel").clicked();
+        });
+        egui::ScrollArea::vertical()
+            .id_salt("replace_preview")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                egui::Grid::new("replace_preview_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for preview in &plan.previews {
+                            if ui.link(format!("{}", preview.line)).clicked() {
+                                jump_to = Some(preview.offset);
+                            }
+                            ui.vertical(|ui| {
+                                ui.label(
+                                    egui::RichText::new(format!("- {}", preview.before))
+                                        .monospace()
+                                        .weak(),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!("+ {}", preview.after))
+                                        .monospace()
+                                        .color(MODIFIED_COLOR),
+                                );
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(offset) = jump_to {
+            let line = self.line_indexer.find_line_at_offset(offset);
+            self.scroll_to(line);
+        }
+        if cancel {
+            self.replace_plan = None;
+        } else if confirm {
+            self.commit_replace();
+        }
+    }
+
+    fn render_timeline(&mut self, ctx: &egui::Context) {
+        if !self.show_timeline {
+            return;
+        }
+
+        let mut jump_to = None;
+        egui::TopBottomPanel::top("timeline").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Timeline");
+                ui.label("Timestamp:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.timestamp_format)
+                        .hint_text("auto-detect")
+                        .desired_width(160.0),
+                )
+                .on_hover_text(
+                    "strftime-like format, e.g. %Y-%m-%d %H:%M:%S. \
+                     Fields: %Y %y %m %d %H %M %S %b %f %z %s",
+                );
+                ui.menu_button("Presets", |ui| {
+                    if ui.button("Auto-detect").clicked() {
+                        self.timestamp_format.clear();
+                        ui.close_menu();
+                    }
+                    for (name, format) in TIMESTAMP_PRESETS {
+                        if ui.button(format!("{}  ({})", name, format)).clicked() {
+                            self.timestamp_format = format.to_string();
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.label("Bucket:");
+                egui::ComboBox::from_id_salt("timeline_bucket")
+                    .selected_text(format_bucket(self.timeline_bucket_secs))
+                    .show_ui(ui, |ui| {
+                        for bucket in TIMELINE_BUCKETS {
+                            ui.selectable_value(
+                                &mut self.timeline_bucket_secs,
+                                bucket,
+                                format_bucket(bucket),
+                            );
+                        }
+                    });
+
+                if let Some(ref timeline) = self.timeline {
+                    ui.separator();
+                    ui.label(format!("per {}", format_bucket(Some(timeline.bucket_secs))));
+                    if timeline.untimed > 0 {
+                        ui.label(format!("{} without timestamp", timeline.untimed));
+                    }
+                }
+            });
+
+            let Some(ref timeline) = self.timeline else {
+                ui.label(
+                    self.timeline_note
+                        .as_deref()
+                        .unwrap_or("Run Find All to chart matches over time"),
+                );
+                return;
+            };
+            let (Some(&first), Some(&last)) = (
+                timeline.buckets.keys().next(),
+                timeline.buckets.keys().last(),
+            ) else {
+                ui.label(if self.search_in_progress {
+                    "Counting..."
+                } else {
+                    "No timestamped matches"
+                });
+                return;
+            };
+
+            // Merge adjacent buckets when there are more buckets than pixels to draw them
+            let width = ui.available_width();
+            let bucket_count = ((last - first) / timeline.bucket_secs + 1) as usize;
+            let group = bucket_count
+                .div_ceil((width / 2.0).max(1.0) as usize)
+                .max(1);
+            let bar_secs = timeline.bucket_secs * group as i64;
+            let mut bars: Vec<(usize, Option<usize>)> =
+                vec![(0, None); bucket_count.div_ceil(group)];
+            for (key, bucket) in &timeline.buckets {
+                let bar = &mut bars[((key - first) / timeline.bucket_secs) as usize / group];
+                bar.0 += bucket.count;
+                bar.1 = Some(
+                    bar.1
+                        .map_or(bucket.first_offset, |o| o.min(bucket.first_offset)),
+                );
+            }
+            let max_count = bars.iter().map(|b| b.0).max().unwrap_or(1).max(1);
+
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(width, TIMELINE_HEIGHT), egui::Sense::click());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+            let bar_width = rect.width() / bars.len() as f32;
+            let hovered = response
+                .hover_pos()
+                .map(|pos| (((pos.x - rect.left()) / bar_width) as usize).min(bars.len() - 1));
+            for (i, (count, _)) in bars.iter().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                let height = (*count as f32 / max_count as f32) * rect.height();
+                let left = rect.left() + i as f32 * bar_width;
+                let bar_rect = egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - height.max(1.0)),
+                    egui::pos2(left + (bar_width - 1.0).max(1.0), rect.bottom()),
+                );
+                let color = if hovered == Some(i) {
+                    egui::Color32::from_rgb(255, 165, 0)
+                } else {
+                    ui.visuals().selection.bg_fill
+                };
+                painter.rect_filled(bar_rect, 0.0, color);
+            }
+
+            if let Some(i) = hovered {
+                let start = first + i as i64 * bar_secs;
+                let (count, offset) = bars[i];
+                let response = response.on_hover_text(format!(
+                    "{} – {}\n{} matches",
+                    format_timestamp(start),
+                    format_timestamp(start + bar_secs),
+                    count
+                ));
+                if response.clicked() {
+                    jump_to = offset;
+                }
+            }
+
+            ui.horizontal(|ui| {
+                ui.label(format_timestamp(first));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.label(format_timestamp(last + timeline.bucket_secs));
+                });
+            });
+        });
+
+        if let Some(offset) = jump_to {
+            self.jump_to_offset(offset);
+        }
+    }
+
+    fn render_bookmarks(&mut self, ctx: &egui::Context) {
+        if !self.show_bookmarks {
+            return;
+        }
+
+        let mut jump_to = None;
+        let mut remove_index = None;
+        let mut changed = false;
+        egui::SidePanel::right("bookmarks")
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Bookmarks");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").on_hover_text("Close").clicked() {
+                            self.show_bookmarks = false;
+                        }
+                    });
+                });
+                ui.separator();
+
+                let Some(ref reader) = self.file_reader else {
+                    ui.label("No file open");
+                    return;
+                };
+                if self.bookmarks.is_empty() {
+                    let shortcut = self.keymap.shortcut_text(Action::ToggleBookmark);
+                    ui.label(if shortcut.is_empty() {
+                        "Click a line number to add a bookmark".to_string()
+                    } else {
+                        format!(
+                            "Click a line number or press {} to add a bookmark",
+                            shortcut
+                        )
+                    });
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for i in 0..self.bookmarks.as_slice().len() {
+                        let offset = self.bookmarks.as_slice()[i].offset;
+                        let line = self.line_indexer.find_line_at_offset(offset);
+                        let preview_end = (offset + 200).min(reader.len());
+                        let preview = reader.get_chunk(offset, preview_end);
+                        let preview = preview.lines().next().unwrap_or("");
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .link(format!("Line {}", line + 1))
+                                .on_hover_text(format!("Byte offset {}", offset))
+                                .clicked()
+                            {
+                                jump_to = Some(offset);
+                            }
+                            if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                                remove_index = Some(i);
+                            }
+                        });
+                        if let Some(bookmark) = self.bookmarks.get_mut(i) {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut bookmark.label)
+                                    .hint_text("Label")
+                                    .desired_width(f32::INFINITY),
+                            );
+                            changed |= response.lost_focus();
+                        }
+                        ui.label(egui::RichText::new(preview).monospace().weak());
+                        ui.separator();
+                    }
+                });
+            });
+
+        if let Some(i) = remove_index {
+            self.bookmarks.remove(i);
+            changed = true;
+        }
+        if changed {
+            self.persist_bookmarks();
+        }
+        if let Some(offset) = jump_to {
+            self.jump_to_offset(offset);
+        }
+    }
+
+    fn render_pending_edits(&mut self, ctx: &egui::Context) {
+        if !self.show_pending_edits {
+            return;
+        }
+
+        let mut jump_to = None;
+        let mut remove_index = None;
+        egui::SidePanel::right("pending_edits")
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Pending Replacements");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("✖").on_hover_text("Close").clicked() {
+                            self.show_pending_edits = false;
+                        }
+                    });
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.pending_edits.can_undo(), egui::Button::new("Undo"))
+                        .on_hover_text(self.keymap.shortcut_text(Action::Undo))
+                        .clicked()
+                    {
+                        self.undo_replacement();
+                    }
+                    if ui
+                        .add_enabled(self.pending_edits.can_redo(), egui::Button::new("Redo"))
+                        .on_hover_text(self.keymap.shortcut_text(Action::Redo))
+                        .clicked()
+                    {
+                        self.redo_replacement();
+                    }
+                });
+                ui.separator();
+
+                let Some(ref reader) = self.file_reader else {
+                    ui.label("No file open");
+                    return;
+                };
+                if self.pending_edits.is_empty() {
+                    ui.label("Replace a match to queue an edit; it is written on Save");
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, replacement) in self.pending_edits.as_slice().iter().enumerate() {
+                        let line = self.line_indexer.find_line_at_offset(replacement.offset);
+                        let old_end = replacement.offset + replacement.old_len.min(200);
+                        let old_text = reader.get_chunk(replacement.offset, old_end);
+
+                        ui.horizontal(|ui| {
+                            if ui
+                                .link(format!("Line {}", line + 1))
+                                .on_hover_text(format!("Byte offset {}", replacement.offset))
+                                .clicked()
+                            {
+                                jump_to = Some(replacement.offset);
+                            }
+                            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                                remove_index = Some(i);
+                            }
+                        });
+                        ui.label(
+                            egui::RichText::new(format!("- {}", old_text))
+                                .monospace()
+                                .weak(),
+                        );
+                        ui.label(
+                            egui::RichText::new(format!("+ {}", replacement.new_text)).monospace(),
+                        );
+                        ui.separator();
+                    }
+                });
+            });
+
+        if let Some(i) = remove_index {
+            self.pending_edits.remove(i);
+            self.unsaved_changes = !self.pending_edits.is_empty();
+            self.status_message =
+                format!("Removed replacement ({} pending)", self.pending_edits.len());
+        }
+        if let Some(offset) = jump_to {
+            self.jump_to_offset(offset);
+        }
+    }
+
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(ref reader) = self.file_reader {
+                    ui.label(format!("File: {}", reader.path().display()));
+                    ui.separator();
+                    ui.label(format!("Size: {} bytes", reader.len()));
+                    ui.separator();
+                    ui.label(format!("Lines: ~{}", self.line_indexer.total_lines()));
+                    ui.separator();
+                    ui.label(format!("Encoding: {}", reader.encoding().name()));
+                    ui.separator();
+                    ui.label(format!("Line: {}", self.scroll_line + 1));
+                    if let Some(range) = self.selection.map(|s| s.range()) {
+                        ui.separator();
+                        if range.is_empty() {
+                            ui.label(format!("Offset: {}", range.start));
+                        } else {
+                            ui.label(format!("Selected: {} bytes", range.len()));
+                        }
+                    }
+                } else {
                     ui.label("No file opened - Click File → Open to start");
                 }
 
@@ -1033,10 +2982,10 @@ impl TextViewerApp {
                 let available_height = ui.available_height();
                 let font_id = egui::FontId::monospace(self.font_size);
                 let line_height = ui.fonts(|f| f.row_height(&font_id));
-                self.visible_lines =
-                    ((available_height / line_height).ceil() as usize).saturating_add(2);
+                self.visible_lines = ((available_height / line_height).ceil() as usize)
+                    .saturating_add(self.config.ui.overscan_lines);
 
-                let mut scroll_area = if self.wrap_mode {
+                let mut scroll_area = if self.wrap_mode && !self.hex_view {
                     egui::ScrollArea::vertical()
                 } else {
                     egui::ScrollArea::both()
@@ -1050,267 +2999,536 @@ impl TextViewerApp {
                 )
                 .auto_shrink([false, false])
                 .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysVisible)
-                .drag_to_scroll(true);
+                // Dragging over the text selects it
+                .drag_to_scroll(false);
+
+                // The hex view shows rows of bytes; jumps to a text line go to the row
+                // where the line starts
+                let hex_layout = self.hex_layout();
+                let (total_rows, scroll_target, mut pending_target) = if self.hex_view {
+                    let line_row = self
+                        .scroll_to_row
+                        .take()
+                        .and_then(|line| self.line_start_offset(line))
+                        .map(|offset| hex_layout.row_of(offset));
+                    self.pending_scroll_target = None;
+                    let target = self.hex_scroll_target.take().or(line_row);
+                    (hex_layout.rows(reader.len()), target, target)
+                } else {
+                    self.hex_scroll_target = None;
+                    (
+                        self.line_indexer.total_lines(),
+                        self.scroll_to_row.take(),
+                        self.pending_scroll_target.take(),
+                    )
+                };
 
                 // Apply programmatic scroll if requested
                 let mut programmatic_scroll = false;
-                if let Some(target_row) = self.scroll_to_row.take() {
+                if let Some(target_row) = scroll_target {
                     scroll_area =
                         scroll_area.vertical_scroll_offset(target_row as f32 * line_height);
                     programmatic_scroll = true;
                 }
 
                 let mut first_visible_row = None;
+                let mut toggled_line = None;
+                let mut rendered_rows: Vec<RenderedRow> = Vec::new();
+                let mut pressed_row = None;
+                let applied_edits = self.pending_edits.applied();
+
+                let output = scroll_area.show_rows(ui, line_height, total_rows, |ui, row_range| {
+                    // Calculate scroll correction if we just jumped
+                    if let Some(target) = pending_target.take() {
+                        self.scroll_correction = target as i64 - row_range.start as i64;
+                    }
 
-                let output = scroll_area.show_rows(
-                    ui,
-                    line_height,
-                    self.line_indexer.total_lines(),
-                    |ui, row_range| {
-                        // Calculate scroll correction if we just jumped
-                        if let Some(target) = self.pending_scroll_target.take() {
-                            self.scroll_correction = target as i64 - row_range.start as i64;
-                        }
-
-                        // Apply correction to find the actual start line we want to render
-                        let corrected_start_line =
-                            (row_range.start as i64 + self.scroll_correction).max(0) as usize;
+                    // Apply correction to find the actual start line we want to render
+                    let corrected_start_line =
+                        (row_range.start as i64 + self.scroll_correction).max(0) as usize;
 
-                        // Capture the first visible row (corrected)
-                        if first_visible_row.is_none() {
-                            first_visible_row = Some(corrected_start_line);
-                        }
+                    // Capture the first visible row (corrected)
+                    if first_visible_row.is_none() {
+                        first_visible_row = Some(corrected_start_line);
+                    }
 
-                        // For contiguous rendering, we find the start offset of the first line
-                        // and then read sequentially.
-                        let mut current_offset = if let Some((start, _)) = self
-                            .line_indexer
-                            .get_line_with_reader(corrected_start_line, reader)
-                        {
-                            start
+                    let count = row_range.end - row_range.start;
+                    if self.hex_view {
+                        let text_color = if self.dark_mode {
+                            egui::Color32::LIGHT_GRAY
                         } else {
-                            return;
+                            egui::Color32::BLACK
                         };
-
-                        // We iterate over the count of rows requested, but starting from our corrected line
-                        let count = row_range.end - row_range.start;
-                        let render_range = corrected_start_line..(corrected_start_line + count);
-
-                        for line_num in render_range {
-                            // Read line starting at current_offset
-                            // We need to find the end of the line
-                            let chunk_size = 4096; // Read in chunks to find newline
-                            let mut line_end = current_offset;
-                            let mut found_newline = false;
-
-                            // Scan for newline
-                            while !found_newline {
-                                let chunk = reader.get_bytes(line_end, line_end + chunk_size);
-                                if chunk.is_empty() {
+                        for row in corrected_start_line..corrected_start_line + count {
+                            let start = hex_layout.row_start(row);
+                            if start >= reader.len() {
+                                break;
+                            }
+                            let end = (start + hex_layout.bytes_per_row).min(reader.len());
+                            let text = hex_layout.row_text(start, reader.get_bytes(start, end));
+
+                            // Results on the row, highlighted in the hex and ASCII columns.
+                            // The hex view shows the bytes on disk, without pending edits.
+                            let mut row_matches = Vec::new();
+                            let first = self
+                                .search_results
+                                .partition_point(|r| r.byte_offset + r.match_len <= start);
+                            for (idx, res) in self.search_results.iter().enumerate().skip(first) {
+                                if res.byte_offset >= end {
                                     break;
                                 }
-
-                                if let Some(pos) = chunk.iter().position(|&b| b == b'\n') {
-                                    line_end += pos + 1; // Include newline
-                                    found_newline = true;
-                                } else {
-                                    line_end += chunk.len();
+                                let is_selected =
+                                    self.search_page_start_index + idx == self.current_result_index;
+                                let bytes = res.byte_offset.saturating_sub(start)
+                                    ..res.byte_offset + res.match_len - start;
+                                for columns in hex_layout.highlight_columns(bytes) {
+                                    row_matches.push((
+                                        columns.start,
+                                        columns.end,
+                                        is_selected,
+                                        res.pattern_index,
+                                    ));
                                 }
-
-                                if line_end >= reader.len() {
-                                    break;
+                            }
+                            let selected_columns = self
+                                .selection
+                                .map(|s| s.range())
+                                .filter(|r| !r.is_empty() && r.start < end && r.end > start)
+                                .map(|r| {
+                                    hex_layout.highlight_columns(
+                                        r.start.saturating_sub(start)..r.end - start,
+                                    )
+                                })
+                                .unwrap_or_default();
+                            let caret_position = self
+                                .selection
+                                .map(|s| s.caret)
+                                .filter(|&c| c >= start && (c < end || c == reader.len()))
+                                .map(|c| c - start);
+
+                            let job = line_layout_job(
+                                &text,
+                                &row_matches,
+                                &[],
+                                &[],
+                                &selected_columns,
+                                &font_id,
+                                text_color,
+                                ui.visuals().selection.bg_fill,
+                            );
+                            let galley = ui.fonts(|f| f.layout_job(job));
+                            let size = egui::vec2(
+                                galley.size().x.max(ui.available_width()),
+                                galley.size().y.max(line_height),
+                            );
+                            let (rect, response) =
+                                ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+                            ui.painter().galley(rect.min, galley.clone(), text_color);
+
+                            // The caret sits before its byte in both columns
+                            if let Some(position) = caret_position {
+                                let hex_column = if position < hex_layout.bytes_per_row {
+                                    hex_layout.hex_column(position)
+                                } else {
+                                    hex_layout.hex_column(position - 1) + 2
+                                };
+                                for column in [hex_column, hex_layout.ascii_column(position)] {
+                                    let caret = galley
+                                        .pos_from_ccursor(egui::text::CCursor::new(column))
+                                        .translate(rect.min.to_vec2());
+                                    ui.painter().line_segment(
+                                        [caret.center_top(), caret.center_bottom()],
+                                        ui.visuals().text_cursor.stroke,
+                                    );
                                 }
                             }
 
-                            let start = current_offset;
-                            let end = line_end;
-                            current_offset = end; // Next line starts here
-
-                            if start >= reader.len() {
-                                break;
+                            if response.hovered() {
+                                ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::Text);
+                            }
+                            if response.is_pointer_button_down_on()
+                                && ui.input(|i| i.pointer.primary_pressed())
+                            {
+                                pressed_row = Some(rendered_rows.len());
                             }
+                            // Map every column to the byte position nearest to it
+                            let char_starts = (0..=text.len())
+                                .map(|column| {
+                                    hex_layout.position_at(column as f32).min(end - start)
+                                })
+                                .collect();
+                            rendered_rows.push(RenderedRow {
+                                rect,
+                                galley,
+                                start,
+                                char_starts,
+                            });
+                        }
+                        return;
+                    }
 
-                            let mut line_text_owned = reader.get_chunk(start, end);
+                    // For contiguous rendering, we find the start offset of the first line
+                    // and then read sequentially.
+                    let mut current_offset = if let Some((start, _)) = self
+                        .line_indexer
+                        .get_line_with_reader(corrected_start_line, reader)
+                    {
+                        start
+                    } else {
+                        return;
+                    };
 
-                            // Apply pending replacements to the view
-                            for replacement in &self.pending_replacements {
-                                let rep_start = replacement.offset;
-                                let rep_end = rep_start + replacement.old_len;
+                    // We iterate over the count of rows requested, but starting from our corrected line
+                    let render_range = corrected_start_line..(corrected_start_line + count);
 
-                                if rep_start >= start && rep_end <= end {
-                                    let rel_start = rep_start - start;
-                                    let rel_end = rep_end - start;
+                    for line_num in render_range {
+                        let start = current_offset;
+                        if start >= reader.len() {
+                            break;
+                        }
 
-                                    if line_text_owned.is_char_boundary(rel_start)
-                                        && line_text_owned.is_char_boundary(rel_end)
-                                    {
-                                        line_text_owned.replace_range(
-                                            rel_start..rel_end,
-                                            &replacement.new_text,
-                                        );
+                        // Look for the newline only as far as a row can show; longer
+                        // lines take their end from the index rather than a full scan
+                        let window = reader.get_bytes(start, start + MAX_RENDERED_LINE_BYTES + 4);
+                        let end = match memchr::memchr(b'\n', window) {
+                            Some(pos) => start + pos + 1, // Include newline
+                            None => self
+                                .line_indexer
+                                .get_line_with_reader(line_num, reader)
+                                .map(|(_, end)| end.min(reader.len()))
+                                .filter(|&end| end > start + window.len())
+                                .unwrap_or(start + window.len()),
+                        };
+                        current_offset = end; // Next line starts here
+
+                        // Map between file offsets and positions in the displayed text
+                        let mut content_end = end;
+                        if reader.get_bytes(start, end).ends_with(b"\n") {
+                            content_end -= 1;
+                            if reader.get_bytes(start, content_end).ends_with(b"\r") {
+                                content_end -= 1;
+                            }
+                        }
+
+                        // Show the line as it will be saved: pending replacements laid over
+                        // the file bytes. Each displayed character keeps the file offset it
+                        // comes from; replacement text maps to the start of its edit.
+                        // Control characters and bytes the decoder cannot read are shown as
+                        // `\xHH` escapes, while searches see `plain_text`, the decoded line.
+                        let mut line_text = String::new();
+                        let mut char_starts = Vec::new();
+                        let mut text_starts = Vec::new();
+                        let mut modified = Vec::new();
+                        let mut escapes = Vec::new();
+                        let mut line_modified = false;
+                        let mut plain_text = String::new();
+                        let mut plain_starts = Vec::new();
+                        let mut shown_starts = Vec::new();
+                        // Very long lines show only their start, cut between characters
+                        let shown_end = if content_end - start > MAX_RENDERED_LINE_BYTES {
+                            let bytes = reader.get_bytes(start, start + MAX_RENDERED_LINE_BYTES + 4);
+                            start
+                                + cursor::floor_char_boundary(
+                                    reader.encoding(),
+                                    bytes,
+                                    MAX_RENDERED_LINE_BYTES,
+                                )
+                        } else {
+                            content_end
+                        };
+                        for piece in pending_edits::pieces(&applied_edits, start..shown_end) {
+                            match piece {
+                                Piece::File(range) => {
+                                    let bytes = reader.get_bytes(range.start, range.end);
+                                    let text = reader.get_chunk(range.start, range.end);
+                                    let offsets = cursor::char_offsets(reader.encoding(), bytes);
+                                    for (c, o) in text.chars().zip(offsets.windows(2)) {
+                                        let source = &bytes[o[0]..o[1]];
+                                        let char_start = range.start - start + o[0];
+                                        plain_starts.push(plain_text.len());
+                                        shown_starts.push(line_text.len());
+                                        plain_text.push(c);
+
+                                        let invalid = c == char::REPLACEMENT_CHARACTER
+                                            && reader
+                                                .encoding()
+                                                .decode_without_bom_handling_and_without_replacement(
+                                                    source,
+                                                )
+                                                .is_none();
+                                        let escape: String = if invalid {
+                                            source.iter().map(|b| format!("\\x{:02x}", b)).collect()
+                                        } else if c.is_ascii_control() && c != '\t' {
+                                            format!("\\x{:02x}", c as u32)
+                                        } else {
+                                            text_starts.push(line_text.len());
+                                            char_starts.push(char_start);
+                                            line_text.push(c);
+                                            continue;
+                                        };
+                                        let from = line_text.len();
+                                        for e in escape.chars() {
+                                            text_starts.push(line_text.len());
+                                            char_starts.push(char_start);
+                                            line_text.push(e);
+                                        }
+                                        escapes.push((from..line_text.len(), invalid));
+                                    }
+                                }
+                                Piece::Edit { old, text } => {
+                                    let from = line_text.len();
+                                    for (i, _) in text.char_indices() {
+                                        text_starts.push(from + i);
+                                        char_starts.push(old.start - start);
+                                        plain_starts.push(plain_text.len() + i);
+                                        shown_starts.push(from + i);
                                     }
+                                    line_text.push_str(text);
+                                    plain_text.push_str(text);
+                                    modified.push(from..line_text.len());
+                                    line_modified = true;
                                 }
                             }
+                        }
+                        plain_starts.push(plain_text.len());
+                        shown_starts.push(line_text.len());
+                        if shown_end < content_end {
+                            // The marker stands for the rest of the line
+                            let from = line_text.len();
+                            text_starts.push(from);
+                            char_starts.push(shown_end - start);
+                            line_text.push('…');
+                            escapes.push((from..line_text.len(), false));
+                        }
+                        char_starts.push(content_end - start);
+                        text_starts.push(line_text.len());
 
-                            let line_text = line_text_owned
-                                .trim_end_matches('\n')
-                                .trim_end_matches('\r');
-
-                            // Collect matches that fall within this line's byte span; this works even with sparse line indexing
-                            let mut line_matches: Vec<(usize, usize, bool)> = Vec::new();
+                        let char_index = |offset: usize| {
+                            char_starts
+                                .partition_point(|&o| start + o < offset)
+                                .min(text_starts.len() - 1)
+                        };
+                        let text_pos = |offset: usize| text_starts[char_index(offset)];
+                        let shown_pos =
+                            |pos: usize| shown_starts[plain_starts.partition_point(|&p| p < pos)];
+                        let file_offset = |pos: usize| {
+                            let index = text_starts.partition_point(|&t| t < pos);
+                            start + char_starts[index.min(char_starts.len() - 1)]
+                        };
 
-                            // Determine the byte offset of the currently selected result
-                            let selected_offset = if self.total_search_results > 0
-                                && self.current_result_index >= self.search_page_start_index
-                            {
-                                let local_idx =
-                                    self.current_result_index - self.search_page_start_index;
-                                self.search_results.get(local_idx).map(|r| r.byte_offset)
-                            } else {
-                                None
-                            };
+                        // Collect matches that fall within this line's byte span; this works even with sparse line indexing
+                        // (start, end, is_selected, pattern_index)
+                        let mut line_matches: Vec<(usize, usize, bool, usize)> = Vec::new();
 
-                            if self.search_find_all {
-                                // Use find_in_text to find matches in the current line (highlight all visible)
-                                for (m_start, m_end) in self.search_engine.find_in_text(line_text) {
-                                    let abs_start = start + m_start;
-                                    let is_selected = Some(abs_start) == selected_offset;
-                                    line_matches.push((m_start, m_end, is_selected));
-                                }
-                            } else {
-                                // Only highlight results present in search_results (e.g. single find)
-                                // Use binary search to find the first potential match
-                                // This assumes search_results is sorted by byte_offset
-                                let start_idx = self
-                                    .search_results
-                                    .partition_point(|r| r.byte_offset < start);
-
-                                for (idx, res) in
-                                    self.search_results.iter().enumerate().skip(start_idx)
-                                {
-                                    if res.byte_offset >= end {
-                                        break;
-                                    }
+                        // Determine the byte offset of the currently selected result
+                        let selected_offset = if self.total_search_results > 0
+                            && self.current_result_index >= self.search_page_start_index
+                        {
+                            let local_idx =
+                                self.current_result_index - self.search_page_start_index;
+                            self.search_results.get(local_idx).map(|r| r.byte_offset)
+                        } else {
+                            None
+                        };
 
-                                    let rel_start = res.byte_offset.saturating_sub(start);
-                                    if rel_start >= line_text.len() {
+                        if self.search_find_all {
+                            // Use find_in_text to find matches in the current line (highlight all visible)
+                            let search_range = self.search_engine.range();
+                            for m in self.search_engine.find_matches_in_text(&plain_text) {
+                                let (m_start, m_end) = (shown_pos(m.start), shown_pos(m.end));
+                                let abs_start = file_offset(m_start);
+                                if let Some(ref range) = search_range {
+                                    if abs_start < range.start || file_offset(m_end) > range.end {
                                         continue;
                                     }
-                                    let rel_end = (rel_start + res.match_len).min(line_text.len());
-
-                                    // Check if this is the currently selected result
-                                    // We need to map local index to global index
-                                    let global_idx = self.search_page_start_index + idx;
-                                    let is_selected = global_idx == self.current_result_index;
-
-                                    line_matches.push((rel_start, rel_end, is_selected));
                                 }
+                                let is_selected = Some(abs_start) == selected_offset;
+                                line_matches.push((m_start, m_end, is_selected, m.pattern));
                             }
-
-                            ui.horizontal(|ui| {
-                                if self.show_line_numbers {
-                                    let ln_text =
-                                        egui::RichText::new(format!("{:6} ", line_num + 1))
-                                            .monospace()
-                                            .color(egui::Color32::DARK_GRAY);
-                                    // Make line numbers non-selectable so drag-select only captures the content text
-                                    ui.add(egui::Label::new(ln_text).selectable(false));
+                        } else {
+                            // Only highlight results present in search_results (e.g. single find)
+                            // Use binary search to find the first potential match
+                            // This assumes search_results is sorted by byte_offset
+                            let start_idx = self
+                                .search_results
+                                .partition_point(|r| r.byte_offset < start);
+
+                            for (idx, res) in self.search_results.iter().enumerate().skip(start_idx)
+                            {
+                                if res.byte_offset >= end {
+                                    break;
                                 }
 
-                                // Build label with highlighted search matches
-                                let label = if !line_matches.is_empty() {
-                                    // Create a LayoutJob to highlight matches within the line using their byte offsets
-                                    let mut job = egui::text::LayoutJob::default();
-                                    let mut last_end = 0;
-
-                                    for (abs_start, abs_end, is_selected) in line_matches.iter() {
-                                        if *abs_start > last_end {
-                                            job.append(
-                                                &line_text[last_end..*abs_start],
-                                                0.0,
-                                                egui::TextFormat {
-                                                    font_id: egui::FontId::monospace(
-                                                        self.font_size,
-                                                    ),
-                                                    color: if self.dark_mode {
-                                                        egui::Color32::LIGHT_GRAY
-                                                    } else {
-                                                        egui::Color32::BLACK
-                                                    },
-                                                    ..Default::default()
-                                                },
-                                            );
-                                        }
-
-                                        let match_end = (*abs_end).min(line_text.len());
-                                        job.append(
-                                            &line_text[*abs_start..match_end],
-                                            0.0,
-                                            egui::TextFormat {
-                                                font_id: egui::FontId::monospace(self.font_size),
-                                                color: egui::Color32::BLACK,
-                                                background: if *is_selected {
-                                                    egui::Color32::from_rgb(255, 200, 0)
-                                                // orange-ish for current match
-                                                } else {
-                                                    egui::Color32::YELLOW
-                                                },
-                                                ..Default::default()
-                                            },
-                                        );
-
-                                        last_end = match_end;
-                                    }
-
-                                    // Add remaining text after last match
-                                    if last_end < line_text.len() {
-                                        job.append(
-                                            &line_text[last_end..],
-                                            0.0,
-                                            egui::TextFormat {
-                                                font_id: egui::FontId::monospace(self.font_size),
-                                                color: if self.dark_mode {
-                                                    egui::Color32::LIGHT_GRAY
-                                                } else {
-                                                    egui::Color32::BLACK
-                                                },
-                                                ..Default::default()
-                                            },
-                                        );
-                                    }
+                                // Results hold file offsets; replacements before them on
+                                // the line shift where they are displayed
+                                let rel_start = text_pos(res.byte_offset);
+                                if rel_start >= line_text.len() {
+                                    continue;
+                                }
+                                let rel_end = text_pos(res.byte_offset + res.match_len);
+
+                                // Check if this is the currently selected result
+                                // We need to map local index to global index
+                                let global_idx = self.search_page_start_index + idx;
+                                let is_selected = global_idx == self.current_result_index;
+
+                                line_matches.push((
+                                    rel_start,
+                                    rel_end,
+                                    is_selected,
+                                    res.pattern_index,
+                                ));
+                            }
+                        }
 
-                                    if self.wrap_mode {
-                                        job.wrap = egui::text::TextWrapping {
-                                            max_width: ui.available_width(),
-                                            ..Default::default()
-                                        };
-                                    }
+                        let bookmark = self.bookmarks.in_line(start..end);
 
-                                    ui.add(egui::Label::new(job).extend())
+                        let selected_text = self
+                            .selection
+                            .map(|s| s.range())
+                            .filter(|r| !r.is_empty() && r.start < end && r.end > start)
+                            .map(|r| {
+                                text_starts[char_index(r.start)]
+                                    ..text_starts[char_index(r.end.min(content_end))]
+                            });
+                        let caret_char = self
+                            .selection
+                            .map(|s| s.caret)
+                            .filter(|&c| c >= start && c <= content_end)
+                            .map(char_index);
+
+                        ui.horizontal(|ui| {
+                            // Gutter: bookmark marker plus optional line number; click to toggle
+                            let marker = if bookmark.is_some() { "◆" } else { " " };
+                            let gutter = if self.show_line_numbers {
+                                format!("{}{:6} ", marker, line_num + 1)
+                            } else {
+                                format!("{} ", marker)
+                            };
+                            let gutter_text = egui::RichText::new(gutter).monospace().color(
+                                if bookmark.is_some() {
+                                    egui::Color32::from_rgb(80, 140, 255)
+                                } else if line_modified {
+                                    MODIFIED_COLOR
                                 } else {
-                                    let text = egui::RichText::new(line_text)
-                                        .monospace()
-                                        .size(self.font_size);
+                                    egui::Color32::DARK_GRAY
+                                },
+                            );
+                            let mut gutter_response = ui.add(
+                                egui::Label::new(gutter_text)
+                                    .selectable(false)
+                                    .sense(egui::Sense::click()),
+                            );
+                            if let Some(b) = bookmark.filter(|b| !b.label.is_empty()) {
+                                gutter_response = gutter_response.on_hover_text(&b.label);
+                            }
+                            if gutter_response.clicked() {
+                                toggled_line = Some(start..end);
+                            }
 
-                                    // Apply wrap mode
-                                    if self.wrap_mode {
-                                        ui.add(egui::Label::new(text).wrap())
-                                    } else {
-                                        ui.add(egui::Label::new(text).extend())
-                                    }
+                            // Lay out the line with match and selection highlights
+                            let text_color = if self.dark_mode {
+                                egui::Color32::LIGHT_GRAY
+                            } else {
+                                egui::Color32::BLACK
+                            };
+                            let mut job = line_layout_job(
+                                &line_text,
+                                &line_matches,
+                                &modified,
+                                &escapes,
+                                selected_text.as_slice(),
+                                &font_id,
+                                text_color,
+                                ui.visuals().selection.bg_fill,
+                            );
+                            if self.wrap_mode {
+                                job.wrap = egui::text::TextWrapping {
+                                    max_width: ui.available_width(),
+                                    ..Default::default()
                                 };
+                            }
+                            let galley = ui.fonts(|f| f.layout_job(job));
+
+                            // The whole rest of the row takes clicks, so clicking past the
+                            // end of a line puts the caret at its end
+                            let size = egui::vec2(
+                                galley.size().x.max(ui.available_width()),
+                                galley.size().y.max(line_height),
+                            );
+                            let (rect, response) =
+                                ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+                            ui.painter().galley(rect.min, galley.clone(), text_color);
+
+                            if let Some(index) = caret_char {
+                                let caret = galley
+                                    .pos_from_ccursor(egui::text::CCursor::new(index))
+                                    .translate(rect.min.to_vec2());
+                                ui.painter().line_segment(
+                                    [caret.center_top(), caret.center_bottom()],
+                                    ui.visuals().text_cursor.stroke,
+                                );
+                            }
 
-                                // Enable text selection for copy-paste
-                                if label.hovered() {
-                                    ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::Text);
-                                }
-
-                                // Ensure labels don't consume scroll events
-                                label.surrender_focus();
+                            if response.hovered() {
+                                ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::Text);
+                            }
+                            if response.is_pointer_button_down_on()
+                                && ui.input(|i| i.pointer.primary_pressed())
+                            {
+                                pressed_row = Some(rendered_rows.len());
+                            }
+                            rendered_rows.push(RenderedRow {
+                                rect,
+                                galley,
+                                start,
+                                char_starts,
                             });
+                        });
+                    }
+                });
+
+                if let Some(line) = toggled_line {
+                    self.toggle_bookmark(line);
+                }
+
+                // Mouse selection: press places the caret (Shift extends), dragging extends
+                let (pointer, primary_down, shift) = ctx.input(|i| {
+                    (
+                        i.pointer.interact_pos(),
+                        i.pointer.primary_down(),
+                        i.modifiers.shift,
+                    )
+                });
+                if let Some(pos) = pointer {
+                    if let Some(row) = pressed_row {
+                        let offset = rendered_rows[row].offset_at(pos);
+                        let mut selection = self.selection.unwrap_or(Selection::new(offset));
+                        selection.move_to(offset, shift);
+                        self.selection = Some(selection);
+                        self.caret_column = None;
+                        self.mouse_selecting = true;
+                    } else if self.mouse_selecting && primary_down {
+                        let row = rendered_rows
+                            .iter()
+                            .find(|r| pos.y < r.rect.bottom())
+                            .or(rendered_rows.last());
+                        if let (Some(row), Some(selection)) = (row, self.selection.as_mut()) {
+                            selection.caret = row.offset_at(pos);
                         }
-                    },
-                );
+                        // Keep scrolling while the pointer is held above or below the text
+                        if pos.y < output.inner_rect.top() {
+                            self.scroll_rows(false, 1);
+                            ctx.request_repaint();
+                        } else if pos.y > output.inner_rect.bottom() {
+                            self.scroll_rows(true, 1);
+                            ctx.request_repaint();
+                        }
+                    }
+                }
+                if !primary_down {
+                    self.mouse_selecting = false;
+                }
 
                 // Check for manual scroll
                 let current_offset = output.state.offset.y;
@@ -1323,7 +3541,14 @@ impl TextViewerApp {
 
                 // Update scroll_line to match what was actually displayed
                 if let Some(first_row) = first_visible_row {
-                    self.scroll_line = first_row;
+                    if self.hex_view {
+                        self.hex_top_row = first_row;
+                        self.scroll_line = self
+                            .line_indexer
+                            .find_line_at_offset(hex_layout.row_start(first_row));
+                    } else {
+                        self.scroll_line = first_row;
+                    }
                 }
             } else {
                 ui.centered_and_justified(|ui| {
@@ -1345,14 +3570,7 @@ impl TextViewerApp {
                             .selectable_label(std::ptr::eq(self.selected_encoding, encoding), name)
                             .clicked()
                         {
-                            self.selected_encoding = encoding;
-
-                            // Reload file with new encoding
-                            if let Some(ref reader) = self.file_reader {
-                                let path = reader.path().clone();
-                                self.open_file(path);
-                            }
-
+                            self.set_encoding(encoding);
                             self.show_encoding_selector = false;
                         }
                     }
@@ -1364,12 +3582,1193 @@ impl TextViewerApp {
         }
     }
 
-    fn render_file_info(&mut self, ctx: &egui::Context) {
-        if self.show_file_info {
-            if let Some(ref reader) = self.file_reader {
-                egui::Window::new("File Information")
-                    .collapsible(false)
-                    .resizable(false)
+    fn render_saved_searches(&mut self, ctx: &egui::Context) {
+        if !self.show_saved_searches {
+            return;
+        }
+
+        egui::Window::new("Saved Searches")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Current search: {}",
+                    format_search_query(&self.current_search_query())
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.saved_search_name);
+                    let can_save =
+                        !self.saved_search_name.trim().is_empty() && !self.search_query.is_empty();
+                    if ui
+                        .add_enabled(can_save, egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        let name = self.saved_search_name.trim().to_string();
+                        self.search_history
+                            .save_search(name, self.current_search_query());
+                        self.persist_search_history();
+                        self.saved_search_name.clear();
+                    }
+                });
+
+                ui.separator();
+
+                let mut remove_index = None;
+                let mut run_index = None;
+                for (i, saved) in self.search_history.saved.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let shortcut = self.saved_search_shortcut(i);
+                        if !shortcut.is_empty() {
+                            ui.label(shortcut);
+                        }
+                        ui.label(&saved.name);
+                        ui.label(
+                            egui::RichText::new(format_search_query(&saved.query)).monospace(),
+                        );
+                        if ui.button("Run").clicked() {
+                            run_index = Some(i);
+                        }
+                        if ui.button("🗑").on_hover_text("Delete").clicked() {
+                            remove_index = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove_index {
+                    self.search_history.remove_saved(i);
+                    self.persist_search_history();
+                }
+                if let Some(i) = run_index {
+                    self.run_saved_search(i);
+                }
+
+                if ui.button("Close").clicked() {
+                    self.show_saved_searches = false;
+                }
+            });
+    }
+
+    fn open_find_in_files(&mut self) {
+        let fif = &mut self.find_in_files;
+        fif.show = true;
+        // Default to the directory of the open file
+        if fif.target.is_empty() {
+            if let Some(dir) = self
+                .file_reader
+                .as_ref()
+                .and_then(|r| r.path().parent().map(|p| p.display().to_string()))
+            {
+                fif.target = dir;
+            }
+        }
+    }
+
+    fn start_find_in_files(&mut self) {
+        if self.find_in_files.in_progress {
+            return;
+        }
+        let patterns = self.search_patterns();
+        if patterns.is_empty() {
+            self.find_in_files.status = "Enter a search query first".to_string();
+            return;
+        }
+        let options = self.search_options();
+        self.search_history.record(self.current_search_query());
+        self.persist_search_history();
+
+        let fif = &mut self.find_in_files;
+        let max_file_size = match fif.max_size_mb.trim() {
+            "" => None,
+            mb => match mb.parse::<f64>() {
+                Ok(mb) if mb > 0.0 => Some((mb * 1024.0 * 1024.0) as u64),
+                _ => {
+                    fif.status = format!("Invalid size limit: {}", mb);
+                    return;
+                }
+            },
+        };
+        let split_globs = |s: &str| {
+            s.split(',')
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        let filter = FileFilter {
+            include: split_globs(&fif.include),
+            exclude: split_globs(&fif.exclude),
+        };
+
+        fif.results.clear();
+        fif.skipped.clear();
+        fif.total_files = 0;
+        fif.current_file = None;
+        fif.status = "Collecting files...".to_string();
+        fif.in_progress = true;
+
+        let (tx, rx) = channel();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+        fif.message_rx = Some(rx);
+        fif.cancellation_token = Some(cancel_token.clone());
+
+        let target = fif.target.trim().to_string();
+        let max_matches = self.config.memory.find_in_files_max_matches;
+        std::thread::spawn(move || match collect_files(&target, &filter) {
+            Ok(files) => search_files(
+                files,
+                patterns,
+                options,
+                max_file_size,
+                max_matches,
+                tx,
+                cancel_token,
+            ),
+            Err(e) => {
+                let _ = tx.send(FileSearchMessage::Error(e.to_string()));
+            }
+        });
+    }
+
+    fn poll_find_in_files(&mut self) {
+        let fif = &mut self.find_in_files;
+        let Some(ref rx) = fif.message_rx else {
+            return;
+        };
+
+        let mut finished = false;
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                FileSearchMessage::Started { total_files } => fif.total_files = total_files,
+                FileSearchMessage::Searching { index, path } => {
+                    fif.current_file = Some((index, path))
+                }
+                FileSearchMessage::FileResult {
+                    path,
+                    total_matches,
+                    matches,
+                } => fif.results.push(FileHits {
+                    path,
+                    total_matches,
+                    matches,
+                }),
+                FileSearchMessage::Skipped { path, reason } => fif.skipped.push((path, reason)),
+                FileSearchMessage::Done => {
+                    let total: usize = fif.results.iter().map(|r| r.total_matches).sum();
+                    fif.status = format!(
+                        "Found {} matches in {} of {} files",
+                        total,
+                        fif.results.len(),
+                        fif.total_files
+                    );
+                    finished = true;
+                }
+                FileSearchMessage::Error(e) => {
+                    fif.status = format!("Search failed: {}", e);
+                    finished = true;
+                }
+            }
+        }
+
+        if finished {
+            fif.in_progress = false;
+            fif.message_rx = None;
+            fif.cancellation_token = None;
+            fif.current_file = None;
+        }
+    }
+
+    fn stop_find_in_files(&mut self) {
+        let fif = &mut self.find_in_files;
+        if let Some(token) = fif.cancellation_token.take() {
+            token.store(true, Ordering::Relaxed);
+        }
+        fif.in_progress = false;
+        fif.message_rx = None;
+        fif.current_file = None;
+        fif.status = format!("Stopped ({} files with matches)", fif.results.len());
+    }
+
+    /// Opens `path` and jumps to a match found by Find in Files.
+    fn open_file_match(&mut self, path: PathBuf, m: &FileMatch) {
+        let already_open = self.file_reader.as_ref().is_some_and(|r| r.path() == &path);
+        if !already_open {
+            self.open_file_default_encoding(path);
+            if self.file_reader.is_none() {
+                return;
+            }
+        }
+
+        self.search_results = vec![SearchResult {
+            byte_offset: m.byte_offset,
+            match_len: m.match_len,
+            pattern_index: m.pattern_index,
+        }];
+        self.search_find_all = false;
+        self.total_search_results = 1;
+        self.current_result_index = 0;
+        self.search_page_start_index = 0;
+        self.page_offsets.clear();
+
+        self.scroll_to_offset(m.byte_offset);
+        self.status_message = format!("Line {}", m.line_number + 1);
+    }
+
+    fn render_find_in_files(&mut self, ctx: &egui::Context) {
+        if !self.find_in_files.show {
+            return;
+        }
+
+        let mut open = true;
+        let mut start = false;
+        let mut stop = false;
+        let mut jump_to = None;
+
+        egui::Window::new("Find in Files")
+            .open(&mut open)
+            .default_width(700.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let query = format_search_query(&self.current_search_query());
+                let fif = &mut self.find_in_files;
+
+                egui::Grid::new("find_in_files_inputs")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Search:");
+                        ui.label(egui::RichText::new(query).monospace())
+                            .on_hover_text("Uses the query and options from the search bar");
+                        ui.end_row();
+
+                        ui.label("Path or glob:");
+                        ui.horizontal(|ui| {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut fif.target)
+                                    .hint_text("/var/log/app or /var/log/app/*.log*")
+                                    .desired_width(400.0),
+                            );
+                            if response.lost_focus()
+                                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                            {
+                                start = true;
+                            }
+                            if ui.button("Browse...").clicked() {
+                                if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                    fif.target = dir.display().to_string();
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Include:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut fif.include)
+                                .hint_text("*.log, *.txt")
+                                .desired_width(400.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Exclude:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut fif.exclude)
+                                .hint_text("*.gz, **/archive/**")
+                                .desired_width(400.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Max file size (MB):");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut fif.max_size_mb)
+                                .hint_text("no limit")
+                                .desired_width(80.0),
+                        );
+                        ui.end_row();
+                    });
+
+                ui.horizontal(|ui| {
+                    if fif.in_progress {
+                        ui.spinner();
+                        if ui.button("Stop").clicked() {
+                            stop = true;
+                        }
+                        match &fif.current_file {
+                            Some((index, path)) => ui.label(format!(
+                                "Searching {}/{}: {}",
+                                index + 1,
+                                fif.total_files,
+                                path.display()
+                            )),
+                            None => ui.label(&fif.status),
+                        };
+                    } else {
+                        let can_search = !fif.target.trim().is_empty();
+                        if ui
+                            .add_enabled(can_search, egui::Button::new("Search"))
+                            .clicked()
+                        {
+                            start = true;
+                        }
+                        ui.label(&fif.status);
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for hits in &fif.results {
+                            let header = if hits.matches.len() < hits.total_matches {
+                                format!(
+                                    "{} ({} matches, first {} shown)",
+                                    hits.path.display(),
+                                    hits.total_matches,
+                                    hits.matches.len()
+                                )
+                            } else {
+                                format!("{} ({} matches)", hits.path.display(), hits.total_matches)
+                            };
+                            egui::CollapsingHeader::new(header)
+                                .id_salt(&hits.path)
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for m in &hits.matches {
+                                        let text = egui::RichText::new(format!(
+                                            "{:>8}: {}",
+                                            m.line_number + 1,
+                                            m.line_text
+                                        ))
+                                        .monospace()
+                                        .color(
+                                            PATTERN_COLORS[m.pattern_index % PATTERN_COLORS.len()],
+                                        );
+                                        if ui.selectable_label(false, text).clicked() {
+                                            jump_to = Some((hits.path.clone(), m.clone()));
+                                        }
+                                    }
+                                });
+                        }
+
+                        if !fif.skipped.is_empty() {
+                            egui::CollapsingHeader::new(format!(
+                                "Skipped ({} files)",
+                                fif.skipped.len()
+                            ))
+                            .id_salt("find_in_files_skipped")
+                            .show(ui, |ui| {
+                                for (path, reason) in &fif.skipped {
+                                    ui.label(format!("{}: {}", path.display(), reason));
+                                }
+                            });
+                        }
+                    });
+            });
+
+        if (stop || !open) && self.find_in_files.in_progress {
+            self.stop_find_in_files();
+        }
+        if !open {
+            self.find_in_files.show = false;
+        }
+        if start {
+            self.start_find_in_files();
+        }
+        if let Some((path, m)) = jump_to {
+            self.open_file_match(path, &m);
+        }
+    }
+
+    /// Opens the copy/export dialog on the selection, or on the top line without one.
+    fn open_range_export(&mut self) {
+        let export = &mut self.range_export;
+        export.show = true;
+        export.status.clear();
+        if self.selection.is_some_and(|s| !s.is_empty()) {
+            export.source = RangeSource::Selection;
+        } else if export.source == RangeSource::Selection {
+            export.source = RangeSource::Lines;
+            export.from = (self.scroll_line + 1).to_string();
+            export.to = export.from.clone();
+        }
+    }
+
+    fn resolve_export_range(&self) -> Result<(Range<usize>, String), String> {
+        let export = &self.range_export;
+        let (from, to) = (export.from.trim(), export.to.trim());
+        match export.source {
+            RangeSource::Selection => {
+                let range = self.selected_range()?;
+                let label = format!("selection ({} bytes)", range.len());
+                Ok((range, label))
+            }
+            RangeSource::Lines => self.parse_line_range(from, to),
+            RangeSource::Bytes => self.parse_byte_range(from, to),
+        }
+    }
+
+    fn start_range_export(&mut self) {
+        let Some(reader) = self.file_reader.clone() else {
+            return;
+        };
+        let (range, label) = match self.resolve_export_range() {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                self.range_export.status = e;
+                return;
+            }
+        };
+        let Some(output_path) = rfd::FileDialog::new()
+            .set_file_name(format!(
+                "{}.export",
+                reader.path().file_name().unwrap().to_string_lossy()
+            ))
+            .save_file()
+        else {
+            return;
+        };
+
+        let (tx, rx) = channel();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+        let export = &mut self.range_export;
+        export.message_rx = Some(rx);
+        export.cancellation_token = Some(cancel_token.clone());
+        export.in_progress = true;
+        export.progress = Some(0.0);
+        export.status = format!("Exporting {}", label);
+
+        let to_utf8 = export.to_utf8;
+        std::thread::spawn(move || {
+            export_range(&reader, range, &output_path, to_utf8, tx, cancel_token);
+        });
+    }
+
+    fn poll_range_export(&mut self) {
+        let export = &mut self.range_export;
+        let Some(ref rx) = export.message_rx else {
+            return;
+        };
+
+        let mut finished = false;
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                ExportMessage::Progress(done, total) => {
+                    export.progress = Some(done as f32 / total.max(1) as f32);
+                }
+                ExportMessage::Done(written) => {
+                    export.status = format!("Exported {} bytes", written);
+                    finished = true;
+                }
+                ExportMessage::Error(e) => {
+                    export.status = format!("Export failed: {}", e);
+                    finished = true;
+                }
+            }
+        }
+
+        if finished {
+            self.status_message = export.status.clone();
+            export.in_progress = false;
+            export.message_rx = None;
+            export.cancellation_token = None;
+            export.progress = None;
+        }
+    }
+
+    fn render_range_export(&mut self, ctx: &egui::Context) {
+        if !self.range_export.show {
+            return;
+        }
+
+        let mut open = true;
+        let mut copy = false;
+        let mut start = false;
+        let utf8_file = self
+            .file_reader
+            .as_ref()
+            .is_some_and(|r| r.encoding() == encoding_rs::UTF_8);
+
+        egui::Window::new("Copy or Export Range")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let export = &mut self.range_export;
+                ui.horizontal(|ui| {
+                    for source in RangeSource::ALL {
+                        ui.radio_value(&mut export.source, source, source.label());
+                    }
+                });
+                if export.source != RangeSource::Selection {
+                    ui.horizontal(|ui| {
+                        ui.label("From:");
+                        ui.add(egui::TextEdit::singleline(&mut export.from).desired_width(100.0));
+                        ui.label("To:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut export.to)
+                                .hint_text("end")
+                                .desired_width(100.0),
+                        );
+                    });
+                }
+                ui.add_enabled(
+                    !utf8_file,
+                    egui::Checkbox::new(&mut export.to_utf8, "Convert to UTF-8"),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if export.in_progress {
+                        ui.spinner();
+                        if let Some(progress) = export.progress {
+                            ui.add(egui::ProgressBar::new(progress).desired_width(160.0));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            if let Some(token) = &export.cancellation_token {
+                                token.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    } else {
+                        copy = ui
+                            .button("Copy")
+                            .on_hover_text(format!("Up to {} MB", MAX_COPY_BYTES / (1024 * 1024)))
+                            .clicked();
+                        start = ui.button("Export to File...").clicked();
+                    }
+                });
+                if !export.status.is_empty() {
+                    ui.label(&export.status);
+                }
+            });
+
+        if copy {
+            match self.resolve_export_range() {
+                Ok((range, _)) => {
+                    self.copy_range(ctx, range);
+                    self.range_export.status = self.status_message.clone();
+                }
+                Err(e) => self.range_export.status = e,
+            }
+        }
+        if start {
+            self.start_range_export();
+        }
+        if !open {
+            self.range_export.show = false;
+        }
+    }
+
+    fn open_match_export(&mut self) {
+        if self.search_engine.patterns().is_empty() {
+            self.status_message = "Run a search before exporting matches".to_string();
+            return;
+        }
+        self.match_export.show = true;
+        self.match_export.status.clear();
+    }
+
+    fn start_match_export(&mut self) {
+        let Some(reader) = self.file_reader.clone() else {
+            return;
+        };
+        let patterns = self.search_engine.patterns().to_vec();
+        if patterns.is_empty() {
+            return;
+        }
+        let Some(output_path) = rfd::FileDialog::new()
+            .set_file_name(format!(
+                "{}.matches",
+                reader.path().file_name().unwrap().to_string_lossy()
+            ))
+            .save_file()
+        else {
+            return;
+        };
+
+        let (tx, rx) = channel();
+        let cancel_token = Arc::new(AtomicBool::new(false));
+        let search_options = self.search_engine.options();
+        let range = self.search_engine.range();
+        let export = &mut self.match_export;
+        export.message_rx = Some(rx);
+        export.cancellation_token = Some(cancel_token.clone());
+        export.in_progress = true;
+        export.progress = Some(0.0);
+        export.status = "Exporting matches".to_string();
+
+        let options = export.options;
+        std::thread::spawn(move || {
+            let mut engine = SearchEngine::new();
+            engine.set_patterns(patterns, search_options);
+            engine.set_range(range);
+            export_matches(reader, &engine, options, &output_path, tx, cancel_token);
+        });
+    }
+
+    fn poll_match_export(&mut self) {
+        let export = &mut self.match_export;
+        let Some(ref rx) = export.message_rx else {
+            return;
+        };
+
+        let mut finished = false;
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                ExportMessage::Progress(done, total) => {
+                    export.progress = Some(done as f32 / total.max(1) as f32);
+                }
+                ExportMessage::Done(written) => {
+                    export.status = format!("Exported matches ({} bytes)", written);
+                    finished = true;
+                }
+                ExportMessage::Error(e) => {
+                    export.status = format!("Export failed: {}", e);
+                    finished = true;
+                }
+            }
+        }
+
+        if finished {
+            self.status_message = export.status.clone();
+            export.in_progress = false;
+            export.message_rx = None;
+            export.cancellation_token = None;
+            export.progress = None;
+        }
+    }
+
+    fn render_match_export(&mut self, ctx: &egui::Context) {
+        if !self.match_export.show {
+            return;
+        }
+
+        let mut open = true;
+        let mut start = false;
+        let utf8_file = self
+            .file_reader
+            .as_ref()
+            .is_some_and(|r| r.encoding() == encoding_rs::UTF_8);
+        let search_options = self.search_engine.options();
+        let regex_search = search_options.use_regex && search_options.max_edits.is_none();
+        let query = self.search_engine.patterns().join(", ");
+
+        egui::Window::new("Export Matches")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let export = &mut self.match_export;
+                ui.label(format!("Query: {}", query));
+                for (mode, label) in MATCH_EXPORT_MODES {
+                    let enabled = mode != MatchExportMode::CaptureGroups || regex_search;
+                    ui.add_enabled_ui(enabled, |ui| {
+                        ui.radio_value(&mut export.options.mode, mode, label)
+                            .on_disabled_hover_text("Needs a regex search");
+                    });
+                }
+                if export.options.mode == MatchExportMode::Lines {
+                    ui.horizontal(|ui| {
+                        ui.label("Context lines:");
+                        ui.add(egui::DragValue::new(&mut export.options.context).range(0..=100));
+                    });
+                }
+                ui.checkbox(&mut export.options.line_numbers, "Line numbers");
+                ui.add_enabled(
+                    !utf8_file && export.options.mode == MatchExportMode::Lines,
+                    egui::Checkbox::new(&mut export.options.to_utf8, "Convert to UTF-8"),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if export.in_progress {
+                        ui.spinner();
+                        if let Some(progress) = export.progress {
+                            ui.add(egui::ProgressBar::new(progress).desired_width(160.0));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            if let Some(token) = &export.cancellation_token {
+                                token.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    } else {
+                        start = ui.button("Export to File...").clicked();
+                    }
+                });
+                if !export.status.is_empty() {
+                    ui.label(&export.status);
+                }
+            });
+
+        if start {
+            self.start_match_export();
+        }
+        if !open {
+            self.match_export.show = false;
+        }
+    }
+
+    fn open_preferences(&mut self) {
+        self.preferences_draft = self.config.clone();
+        self.show_preferences = true;
+    }
+
+    /// Everything the command palette offers: all actions, then recent files,
+    /// encodings, bookmarks and saved searches.
+    fn palette_entries(&self) -> Vec<PaletteEntry> {
+        let mut entries: Vec<PaletteEntry> = Action::ALL
+            .iter()
+            // Saved searches are listed by name below
+            .filter(|&&action| {
+                action != Action::CommandPalette && action.saved_search_index().is_none()
+            })
+            .map(|&action| PaletteEntry {
+                label: action.label().to_string(),
+                detail: self.keymap.shortcut_text(action),
+                command: PaletteCommand::Action(action),
+            })
+            .collect();
+
+        for file in &self.session.recent_files {
+            let name = file
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| file.path.display().to_string());
+            entries.push(PaletteEntry {
+                label: format!("Open Recent: {}", name),
+                detail: file.path.display().to_string(),
+                command: PaletteCommand::OpenRecent(file.path.clone()),
+            });
+        }
+
+        for (name, encoding) in available_encodings() {
+            entries.push(PaletteEntry {
+                label: format!("Set Encoding: {}", name),
+                detail: String::new(),
+                command: PaletteCommand::SetEncoding(encoding),
+            });
+        }
+
+        for bookmark in self.bookmarks.as_slice() {
+            let line = self.line_indexer.find_line_at_offset(bookmark.offset);
+            entries.push(PaletteEntry {
+                label: format!("Go to Bookmark: Line {}", line + 1),
+                detail: bookmark.label.clone(),
+                command: PaletteCommand::JumpToBookmark(bookmark.offset),
+            });
+        }
+
+        for (i, saved) in self.search_history.saved.iter().enumerate() {
+            entries.push(PaletteEntry {
+                label: format!("Run Saved Search: {}", saved.name),
+                detail: self.saved_search_shortcut(i),
+                command: PaletteCommand::RunSavedSearch(i),
+            });
+        }
+
+        entries
+    }
+
+    fn run_palette_command(&mut self, ctx: &egui::Context, command: PaletteCommand) {
+        match command {
+            PaletteCommand::Action(action) => self.run_action(ctx, action),
+            PaletteCommand::OpenRecent(path) => self.open_recent_file(path),
+            PaletteCommand::SetEncoding(encoding) => self.set_encoding(encoding),
+            PaletteCommand::JumpToBookmark(offset) => self.jump_to_offset(offset),
+            PaletteCommand::RunSavedSearch(index) => self.run_saved_search(index),
+        }
+    }
+
+    fn render_command_palette(&mut self, ctx: &egui::Context) {
+        if !self.command_palette.open {
+            return;
+        }
+
+        // Navigation keys are taken before the text field sees them
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.command_palette.open = false;
+            return;
+        }
+
+        let entries = self.palette_entries();
+        let mut run = None;
+        let palette = &mut self.command_palette;
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut palette.query)
+                        .hint_text("Type a command")
+                        .desired_width(f32::INFINITY),
+                );
+                if palette.focus_input {
+                    response.request_focus();
+                    palette.focus_input = false;
+                }
+                if response.changed() {
+                    palette.selected = 0;
+                }
+
+                let filtered = palette.filter(&entries);
+                if down {
+                    palette.selected += 1;
+                }
+                if up {
+                    palette.selected = palette.selected.saturating_sub(1);
+                }
+                palette.selected = palette.selected.min(filtered.len().saturating_sub(1));
+                if enter {
+                    run = filtered.get(palette.selected).map(|e| e.command.clone());
+                }
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        if filtered.is_empty() {
+                            ui.label("No matching commands");
+                        }
+                        for (i, entry) in filtered.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let response =
+                                    ui.selectable_label(i == palette.selected, &entry.label);
+                                if i == palette.selected && (up || down) {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.clicked() {
+                                    run = Some(entry.command.clone());
+                                }
+                                ui.weak(&entry.detail);
+                            });
+                        }
+                    });
+            });
+
+        if let Some(command) = run {
+            self.command_palette.open = false;
+            self.run_palette_command(ctx, command);
+        }
+    }
+
+    fn render_preferences(&mut self, ctx: &egui::Context) {
+        if !self.show_preferences {
+            return;
+        }
+
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let draft = &mut self.preferences_draft;
+
+                ui.heading("Interface");
+                egui::Grid::new("preferences_ui")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Font size:");
+                        ui.add(egui::Slider::new(&mut draft.ui.font_size, 8.0..=32.0));
+                        ui.end_row();
+
+                        ui.label("Theme:");
+                        ui.checkbox(&mut draft.ui.dark_mode, "Dark mode");
+                        ui.end_row();
+
+                        ui.label("Text:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut draft.ui.wrap_mode, "Word wrap");
+                            ui.checkbox(&mut draft.ui.show_line_numbers, "Line numbers");
+                        });
+                        ui.end_row();
+
+                        ui.label("Hex view:");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut draft.ui.hex_bytes_per_row)
+                                    .range(1..=MAX_HEX_BYTES_PER_ROW)
+                                    .suffix(" bytes per row"),
+                            );
+                            ui.checkbox(&mut draft.ui.hex_decimal_offsets, "Decimal offsets");
+                        });
+                        ui.end_row();
+
+                        ui.label("Overscan lines:")
+                            .on_hover_text("Extra lines rendered beyond the visible area");
+                        ui.add(egui::DragValue::new(&mut draft.ui.overscan_lines).range(0..=100));
+                        ui.end_row();
+
+                        ui.label("Default encoding:");
+                        let selected = Encoding::for_label(draft.ui.default_encoding.as_bytes())
+                            .map(|e| e.name())
+                            .unwrap_or("Auto-detect");
+                        egui::ComboBox::from_id_salt("preferences_encoding")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut draft.ui.default_encoding,
+                                    "auto".to_string(),
+                                    "Auto-detect",
+                                );
+                                for (name, encoding) in available_encodings() {
+                                    ui.selectable_value(
+                                        &mut draft.ui.default_encoding,
+                                        encoding.name().to_string(),
+                                        name,
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.heading("Performance");
+                egui::Grid::new("preferences_performance")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Full line index up to (MB):").on_hover_text(
+                            "Larger files use a sparse index with estimated line numbers",
+                        );
+                        ui.add(
+                            mb_drag_value(&mut draft.performance.full_index_threshold_bytes)
+                                .range(0.0..=100_000.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Sparse index spacing (MB):");
+                        ui.add(
+                            mb_drag_value(&mut draft.performance.sparse_sample_size_bytes)
+                                .range(1.0..=1024.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Search threads:")
+                            .on_hover_text("0 uses every core");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.performance.search_threads)
+                                .range(0..=256),
+                        );
+                        ui.end_row();
+
+                        ui.label("Longest Replace All match (MB):")
+                            .on_hover_text("Longer matches stop Replace All with an error");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.performance.max_replace_match_mb)
+                                .range(1..=4096),
+                        );
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.heading("Memory");
+                egui::Grid::new("preferences_memory")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Search batch per thread (MB):");
+                        ui.add(
+                            mb_drag_value(&mut draft.performance.search_batch_size_bytes)
+                                .range(1.0..=1024.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Search results per page:");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.memory.search_page_size)
+                                .range(1..=1_000_000),
+                        );
+                        ui.end_row();
+
+                        ui.label("Search result buffer:").on_hover_text(
+                            "Results queued for the UI before search threads wait",
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut draft.memory.search_channel_capacity)
+                                .range(1..=1_000_000),
+                        );
+                        ui.end_row();
+
+                        ui.label("Find in Files matches per file:");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.memory.find_in_files_max_matches)
+                                .range(1..=1_000_000),
+                        );
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.heading("Editing");
+                ui.checkbox(
+                    &mut draft.editing.backup_on_save,
+                    "Keep a .bak copy when saving over a file",
+                );
+
+                ui.separator();
+                ui.heading("Keyboard");
+                ui.horizontal(|ui| {
+                    ui.label("Keymap:");
+                    egui::ComboBox::from_id_salt("preferences_keymap")
+                        .selected_text(draft.keymap.preset.label())
+                        .show_ui(ui, |ui| {
+                            for preset in KeymapPreset::ALL {
+                                ui.selectable_value(
+                                    &mut draft.keymap.preset,
+                                    preset,
+                                    preset.label(),
+                                );
+                            }
+                        });
+                });
+                let preset_keymap = Keymap::from_config(&KeymapConfig {
+                    preset: draft.keymap.preset,
+                    ..Default::default()
+                });
+                egui::CollapsingHeader::new("Key bindings")
+                    .id_salt("preferences_bindings")
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(
+                                "Leave blank for the keymap default. Examples: Ctrl+Shift+F, \
+                                 G G (sequence), F3, Ctrl+G (alternatives), none",
+                            )
+                            .weak(),
+                        );
+                        egui::ScrollArea::vertical()
+                            .max_height(240.0)
+                            .show(ui, |ui| {
+                                egui::Grid::new("preferences_bindings_grid")
+                                    .num_columns(2)
+                                    .show(ui, |ui| {
+                                        for action in Action::ALL {
+                                            ui.label(action.label());
+                                            let mut keys = draft
+                                                .keymap
+                                                .bindings
+                                                .get(action.id())
+                                                .cloned()
+                                                .unwrap_or_default();
+                                            let default_keys = preset_keymap.shortcut_text(*action);
+                                            let hint = if default_keys.is_empty() {
+                                                "unbound".to_string()
+                                            } else {
+                                                default_keys
+                                            };
+                                            let response = ui.add(
+                                                egui::TextEdit::singleline(&mut keys)
+                                                    .hint_text(hint)
+                                                    .desired_width(180.0),
+                                            );
+                                            if response.changed() {
+                                                if keys.trim().is_empty() {
+                                                    draft.keymap.bindings.remove(action.id());
+                                                } else {
+                                                    draft
+                                                        .keymap
+                                                        .bindings
+                                                        .insert(action.id().to_string(), keys);
+                                                }
+                                            }
+                                            ui.end_row();
+                                        }
+                                    });
+                            });
+                    });
+                let keymap_errors = draft.keymap.errors();
+                for e in &keymap_errors {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+
+                ui.separator();
+                ui.label(
+                    egui::RichText::new(
+                        "Index settings apply to the next opened file, search settings to the next search.",
+                    )
+                    .weak(),
+                );
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(keymap_errors.is_empty(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                    if ui.button("Reset to Defaults").clicked() {
+                        *draft = Config::default();
+                    }
+                });
+            });
+
+        if save {
+            self.config = std::mem::take(&mut self.preferences_draft).sanitized();
+            self.apply_config();
+            self.status_message = match self.config.save() {
+                Ok(()) => "Preferences saved".to_string(),
+                Err(e) => format!("Failed to save preferences: {}", e),
+            };
+        }
+        if save || cancel || !open {
+            self.show_preferences = false;
+        }
+    }
+
+    fn render_binary_prompt(&mut self, ctx: &egui::Context) {
+        if !self.show_binary_prompt {
+            return;
+        }
+        let Some(ref reader) = self.file_reader else {
+            return;
+        };
+        let name = reader
+            .path()
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut hex_view = false;
+        let mut close = false;
+        egui::Window::new("Binary File")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("{} looks like binary data.", name));
+                ui.label(
+                    "The hex view shows its bytes; the text view shows control characters \
+                     and invalid sequences as \\x escapes.",
+                );
+                ui.horizontal(|ui| {
+                    hex_view = ui.button("Open in Hex View").clicked();
+                    close = ui.button("Show as Text").clicked();
+                });
+            });
+        if hex_view {
+            self.set_hex_view(true);
+        }
+        if hex_view || close {
+            self.show_binary_prompt = false;
+        }
+    }
+
+    fn render_file_info(&mut self, ctx: &egui::Context) {
+        if self.show_file_info {
+            if let Some(ref reader) = self.file_reader {
+                egui::Window::new("File Information")
+                    .collapsible(false)
+                    .resizable(false)
                     .show(ctx, |ui| {
                         ui.label(format!("Path: {}", reader.path().display()));
                         ui.label(format!(
@@ -1398,6 +4797,12 @@ impl eframe::App for TextViewerApp {
             self.open_start_time = None;
         }
 
+        // Remember the open file's view state before the window closes
+        if ctx.input(|i| i.viewport().close_requested()) {
+            // The window is closing, so a failed save has nowhere left to be shown
+            let _ = self.remember_file_session();
+        }
+
         // Update window title
         let title = if self.unsaved_changes {
             "Large Text Viewer *"
@@ -1407,18 +4812,12 @@ impl eframe::App for TextViewerApp {
         ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.to_string()));
 
         // Handle keyboard shortcuts
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::S)) {
-            self.save_file();
-        }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::R)) {
-            self.show_search_bar = true;
-            self.show_replace = !self.show_replace;
+        for action in self.keymap.process(ctx) {
+            self.run_action(ctx, action);
         }
-        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::F)) {
-            self.show_search_bar = !self.show_search_bar;
-            if self.show_search_bar {
-                self.focus_search_input = true;
-            }
+        // Ctrl+C arrives as a copy event rather than a key press
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.events.contains(&egui::Event::Copy)) {
+            self.run_action(ctx, Action::Copy);
         }
 
         // Set theme
@@ -1436,16 +4835,187 @@ impl eframe::App for TextViewerApp {
 
         self.poll_search_results();
         self.poll_replace_results();
-
-        if self.search_in_progress || self.replace_in_progress {
+        self.poll_find_in_files();
+        self.poll_range_export();
+        self.poll_match_export();
+
+        if self.search_in_progress
+            || self.replace_in_progress
+            || self.find_in_files.in_progress
+            || self.range_export.in_progress
+            || self.match_export.in_progress
+        {
             ctx.request_repaint(); // Keep spinner animated during long searches
         }
 
         self.render_menu_bar(ctx);
         self.render_toolbar(ctx);
+        self.render_timeline(ctx);
         self.render_status_bar(ctx);
+        self.render_bookmarks(ctx);
+        self.render_pending_edits(ctx);
         self.render_text_area(ctx);
         self.render_encoding_selector(ctx);
         self.render_file_info(ctx);
+        self.render_binary_prompt(ctx);
+        self.render_saved_searches(ctx);
+        self.render_find_in_files(ctx);
+        self.render_range_export(ctx);
+        self.render_match_export(ctx);
+        self.render_preferences(ctx);
+        self.render_command_palette(ctx);
+    }
+}
+
+/// A line as drawn in the text area, kept for mapping pointer positions to offsets.
+struct RenderedRow {
+    rect: egui::Rect,
+    galley: Arc<egui::Galley>,
+    /// File offset of the line start
+    start: usize,
+    /// Offsets of the line's characters relative to `start`, plus its length
+    char_starts: Vec<usize>,
+}
+
+impl RenderedRow {
+    fn offset_at(&self, pos: egui::Pos2) -> usize {
+        let index = self
+            .galley
+            .cursor_from_pos(pos - self.rect.min)
+            .ccursor
+            .index;
+        self.start + self.char_starts[index.min(self.char_starts.len() - 1)]
+    }
+}
+
+/// Lays out a line with search matches `(start, end, is_selected, pattern)` and the
+/// selected ranges as background highlights, pending replacement text underlined in
+/// `MODIFIED_COLOR`, and escapes `(range, invalid)` in `ESCAPE_COLOR`, or on
+/// `INVALID_COLOR` for bytes that are not valid text; all ranges are byte ranges in
+/// `text`.
+#[allow(clippy::too_many_arguments)]
+fn line_layout_job(
+    text: &str,
+    matches: &[(usize, usize, bool, usize)],
+    modified: &[Range<usize>],
+    escapes: &[(Range<usize>, bool)],
+    selection: &[Range<usize>],
+    font_id: &egui::FontId,
+    text_color: egui::Color32,
+    selection_color: egui::Color32,
+) -> egui::text::LayoutJob {
+    let mut cuts = vec![0, text.len()];
+    for &(start, end, _, _) in matches {
+        cuts.push(start.min(text.len()));
+        cuts.push(end.min(text.len()));
+    }
+    let escape_ranges = escapes.iter().map(|(range, _)| range);
+    for range in modified.iter().chain(escape_ranges).chain(selection) {
+        cuts.push(range.start);
+        cuts.push(range.end);
+    }
+    cuts.retain(|&c| text.is_char_boundary(c));
+    cuts.sort_unstable();
+    cuts.dedup();
+
+    let format = |color, background| egui::TextFormat {
+        font_id: font_id.clone(),
+        color,
+        background,
+        ..Default::default()
+    };
+    let mut job = egui::text::LayoutJob::default();
+    if text.is_empty() {
+        job.append("", 0.0, format(text_color, egui::Color32::TRANSPARENT));
+    }
+    for piece in cuts.windows(2) {
+        let (a, b) = (piece[0], piece[1]);
+        let selected = selection.iter().any(|r| r.start <= a && b <= r.end);
+        let highlight = matches.iter().find(|m| m.0 <= a && b <= m.1);
+        let is_modified = modified.iter().any(|r| r.start <= a && b <= r.end);
+        let escape = escapes
+            .iter()
+            .find(|(r, _)| r.start <= a && b <= r.end)
+            .map(|&(_, invalid)| invalid);
+        let (color, background) = match highlight {
+            _ if selected => (text_color, selection_color),
+            // orange-ish for current match
+            Some(&(_, _, true, _)) => (egui::Color32::BLACK, egui::Color32::from_rgb(255, 200, 0)),
+            Some(&(_, _, false, pattern)) => (
+                egui::Color32::BLACK,
+                PATTERN_COLORS[pattern % PATTERN_COLORS.len()],
+            ),
+            None if escape == Some(true) => (egui::Color32::WHITE, INVALID_COLOR),
+            None if is_modified => (MODIFIED_COLOR, egui::Color32::TRANSPARENT),
+            None if escape == Some(false) => (ESCAPE_COLOR, egui::Color32::TRANSPARENT),
+            None => (text_color, egui::Color32::TRANSPARENT),
+        };
+        let mut text_format = format(color, background);
+        if is_modified {
+            text_format.underline = egui::Stroke::new(1.0, MODIFIED_COLOR);
+        }
+        job.append(&text[a..b], 0.0, text_format);
+    }
+    job
+}
+
+/// The loaded value, or the default with the error recorded in `errors`.
+fn loaded_or_default<T: Default>(result: anyhow::Result<T>, errors: &mut Vec<String>) -> T {
+    result.unwrap_or_else(|e| {
+        errors.push(e.to_string());
+        T::default()
+    })
+}
+
+/// A drag value showing the byte count `bytes` in MB.
+fn mb_drag_value(bytes: &mut usize) -> egui::DragValue<'_> {
+    egui::DragValue::from_get_set(move |value| {
+        if let Some(mb) = value {
+            *bytes = (mb * MB as f64).round() as usize;
+        }
+        *bytes as f64 / MB as f64
+    })
+    .speed(0.1)
+    .max_decimals(2)
+}
+
+fn format_bucket(bucket_secs: Option<i64>) -> String {
+    match bucket_secs {
+        None => "Auto".to_string(),
+        Some(secs) if secs % 86400 == 0 => format!("{}d", secs / 86400),
+        Some(secs) if secs % 3600 == 0 => format!("{}h", secs / 3600),
+        Some(secs) if secs % 60 == 0 => format!("{}m", secs / 60),
+        Some(secs) => format!("{}s", secs),
+    }
+}
+
+fn format_search_query(query: &SearchQuery) -> String {
+    let mut flags = Vec::new();
+    if query.case_sensitive {
+        flags.push("Aa");
+    }
+    if query.use_regex {
+        flags.push(".*");
+    }
+    if query.whole_word {
+        flags.push("W");
+    }
+    if query.hex_bytes {
+        flags.push("0x");
+    }
+    let fuzzy_flag = query.max_edits.map(|k| format!("~{}", k));
+    if let Some(ref flag) = fuzzy_flag {
+        flags.push(flag);
+    }
+    // Multi-pattern queries are shown on one line
+    let text = if query.multi_pattern {
+        query.query.lines().collect::<Vec<_>>().join(" | ")
+    } else {
+        query.query.clone()
+    };
+    if flags.is_empty() {
+        text
+    } else {
+        format!("{}  [{}]", text, flags.join(" "))
     }
 }
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::persist;
+
+const BOOKMARKS_FILE: &str = "bookmarks.toml";
+
+/// A marked line, keyed by the byte offset where the line starts so it stays put
+/// when the sparse line index only estimates line numbers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub offset: usize,
+    #[serde(default)]
+    pub label: String,
+}
+
+/// Bookmarks of one file, sorted by offset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks(Vec<Bookmark>);
+
+impl Bookmarks {
+    pub fn new(mut bookmarks: Vec<Bookmark>) -> Self {
+        bookmarks.sort_by_key(|b| b.offset);
+        bookmarks.dedup_by_key(|b| b.offset);
+        Self(bookmarks)
+    }
+
+    pub fn as_slice(&self) -> &[Bookmark] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Bookmark> {
+        self.0.get_mut(index)
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.0.len() {
+            self.0.remove(index);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Moves every bookmark to `map(offset)`, e.g. to follow the text after a save
+    /// that changed lengths. Bookmarks mapped onto the same offset merge.
+    pub fn map_offsets(&mut self, map: impl Fn(usize) -> usize) {
+        let bookmarks = std::mem::take(&mut self.0)
+            .into_iter()
+            .map(|b| Bookmark {
+                offset: map(b.offset),
+                ..b
+            })
+            .collect();
+        *self = Self::new(bookmarks);
+    }
+
+    /// The bookmark on the line spanning `line`, if any.
+    pub fn in_line(&self, line: Range<usize>) -> Option<&Bookmark> {
+        let i = self.0.partition_point(|b| b.offset < line.start);
+        self.0.get(i).filter(|b| b.offset < line.end)
+    }
+
+    /// Removes the bookmark on `line`, or adds one at its start. Returns true if added.
+    pub fn toggle_line(&mut self, line: Range<usize>) -> bool {
+        let i = self.0.partition_point(|b| b.offset < line.start);
+        if self.0.get(i).is_some_and(|b| b.offset < line.end) {
+            self.0.remove(i);
+            false
+        } else {
+            self.0.insert(
+                i,
+                Bookmark {
+                    offset: line.start,
+                    label: String::new(),
+                },
+            );
+            true
+        }
+    }
+
+    /// The first bookmark after `offset`, wrapping around to the first one.
+    pub fn next_after(&self, offset: usize) -> Option<&Bookmark> {
+        let i = self.0.partition_point(|b| b.offset <= offset);
+        self.0.get(i).or_else(|| self.0.first())
+    }
+
+    /// The last bookmark before `offset`, wrapping around to the last one.
+    pub fn previous_before(&self, offset: usize) -> Option<&Bookmark> {
+        let i = self.0.partition_point(|b| b.offset < offset);
+        i.checked_sub(1)
+            .and_then(|i| self.0.get(i))
+            .or_else(|| self.0.last())
+    }
+}
+
+/// Bookmarks of every file, keyed by path.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BookmarkStore {
+    #[serde(default)]
+    files: BTreeMap<String, Vec<Bookmark>>,
+}
+
+impl BookmarkStore {
+    pub fn load() -> anyhow::Result<Self> {
+        persist::load(BOOKMARKS_FILE)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save(BOOKMARKS_FILE, self)
+    }
+
+    pub fn get(&self, path: &Path) -> Bookmarks {
+        Bookmarks::new(self.files.get(&key(path)).cloned().unwrap_or_default())
+    }
+
+    pub fn set(&mut self, path: &Path, bookmarks: &Bookmarks) {
+        if bookmarks.is_empty() {
+            self.files.remove(&key(path));
+        } else {
+            self.files.insert(key(path), bookmarks.as_slice().to_vec());
+        }
+    }
+}
+
+fn key(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(bookmarks: &Bookmarks) -> Vec<usize> {
+        bookmarks.as_slice().iter().map(|b| b.offset).collect()
+    }
+
+    #[test]
+    fn test_toggle_line() {
+        let mut bookmarks = Bookmarks::default();
+        assert!(bookmarks.toggle_line(20..30));
+        assert!(bookmarks.toggle_line(0..10));
+        assert_eq!(offsets(&bookmarks), vec![0, 20]);
+        assert_eq!(bookmarks.in_line(20..30).map(|b| b.offset), Some(20));
+        assert!(bookmarks.in_line(10..20).is_none());
+
+        assert!(!bookmarks.toggle_line(20..30));
+        assert_eq!(offsets(&bookmarks), vec![0]);
+    }
+
+    #[test]
+    fn test_map_offsets() {
+        let mut bookmarks = Bookmarks::default();
+        for line in [0..10, 10..20, 20..30] {
+            bookmarks.toggle_line(line);
+        }
+        bookmarks.map_offsets(|offset| if offset >= 10 { offset + 5 } else { offset });
+        assert_eq!(offsets(&bookmarks), vec![0, 15, 25]);
+        bookmarks.map_offsets(|offset| offset.min(15));
+        assert_eq!(offsets(&bookmarks), vec![0, 15]);
+    }
+
+    #[test]
+    fn test_next_and_previous_wrap() {
+        let bookmarks = Bookmarks::new(
+            [30, 10, 20]
+                .into_iter()
+                .map(|offset| Bookmark {
+                    offset,
+                    label: String::new(),
+                })
+                .collect(),
+        );
+
+        assert_eq!(bookmarks.next_after(10).map(|b| b.offset), Some(20));
+        assert_eq!(bookmarks.next_after(30).map(|b| b.offset), Some(10));
+        assert_eq!(bookmarks.previous_before(20).map(|b| b.offset), Some(10));
+        assert_eq!(bookmarks.previous_before(10).map(|b| b.offset), Some(30));
+    }
+
+    #[test]
+    fn test_store_roundtrip_toml() {
+        let mut store = BookmarkStore::default();
+        let path = Path::new("/nonexistent/app.log");
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.toggle_line(42..50);
+        bookmarks.get_mut(0).unwrap().label = "first error".to_string();
+        store.set(path, &bookmarks);
+
+        let text = toml::to_string_pretty(&store).unwrap();
+        let parsed: BookmarkStore = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.get(path), bookmarks);
+
+        store.set(path, &Bookmarks::default());
+        assert!(store.files.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use encoding_rs::Encoding;
+use std::path::PathBuf;
+
+use crate::keymap::Action;
+
+/// Something the command palette can run.
+#[derive(Clone, Debug)]
+pub enum PaletteCommand {
+    Action(Action),
+    OpenRecent(PathBuf),
+    SetEncoding(&'static Encoding),
+    JumpToBookmark(usize),
+    RunSavedSearch(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct PaletteEntry {
+    pub label: String,
+    /// Shown dimmed on the right, e.g. the key binding or a file path.
+    pub detail: String,
+    pub command: PaletteCommand,
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    /// Index into the filtered entries.
+    pub selected: usize,
+    pub focus_input: bool,
+}
+
+impl CommandPalette {
+    pub fn show(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_input = true;
+    }
+
+    /// Entries matching the query, best match first; all entries when the query is empty.
+    pub fn filter<'a>(&self, entries: &'a [PaletteEntry]) -> Vec<&'a PaletteEntry> {
+        let mut scored: Vec<(i32, usize, &PaletteEntry)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| fuzzy_score(&self.query, &e.label).map(|s| (s, i, e)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored.into_iter().map(|(_, _, e)| e).collect()
+    }
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence match, or
+/// None if some query character is missing. Consecutive characters and matches at
+/// word starts score higher, so "tt" prefers "Toggle Tail" over "Toggle Timeline".
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut qi = 0;
+    let mut prev_match: Option<usize> = None;
+    let mut prev_char = ' ';
+    for (ci, c) in candidate.chars().enumerate() {
+        if qi < query.len() && c.to_lowercase().eq(std::iter::once(query[qi])) {
+            score += 1;
+            if prev_match == Some(ci.wrapping_sub(1)) {
+                score += 5;
+            }
+            if !prev_char.is_alphanumeric() {
+                score += 8;
+            }
+            prev_match = Some(ci);
+            qi += 1;
+        }
+        prev_char = c;
+    }
+
+    (qi == query.len()).then(|| score - candidate.chars().count() as i32 / 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_matches_subsequences() {
+        assert!(fuzzy_score("tgl", "Toggle Tail Mode").is_some());
+        assert!(fuzzy_score("xyz", "Toggle Tail Mode").is_none());
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn test_filter_prefers_word_starts() {
+        let entry = |label: &str| PaletteEntry {
+            label: label.to_string(),
+            detail: String::new(),
+            command: PaletteCommand::Action(Action::Find),
+        };
+        let entries = vec![
+            entry("Toggle Timeline"),
+            entry("Select Encoding"),
+            entry("Toggle Tail Mode"),
+        ];
+        let palette = CommandPalette {
+            query: "ttm".to_string(),
+            ..Default::default()
+        };
+        let labels: Vec<_> = palette
+            .filter(&entries)
+            .iter()
+            .map(|e| e.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["Toggle Tail Mode", "Toggle Timeline"]);
+    }
+}
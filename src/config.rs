@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use large_text_core::line_indexer::{DEFAULT_FULL_INDEX_THRESHOLD, DEFAULT_SPARSE_SAMPLE_SIZE};
+use large_text_core::replacer::StreamLimits;
+use large_text_core::search_engine::DEFAULT_BATCH_SIZE;
+
+use crate::keymap::KeymapConfig;
+use crate::persist;
+
+const CONFIG_FILE: &str = "config.toml";
+pub const MB: usize = 1024 * 1024;
+pub const MAX_HEX_BYTES_PER_ROW: usize = 64;
+
+/// User preferences, stored in `config.toml` in the config directory. Missing keys
+/// take their default values, so older or hand-written files keep working.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub ui: UiConfig,
+    pub performance: PerformanceConfig,
+    pub memory: MemoryConfig,
+    pub editing: EditingConfig,
+    pub keymap: KeymapConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    pub font_size: f32,
+    pub dark_mode: bool,
+    pub wrap_mode: bool,
+    pub show_line_numbers: bool,
+    /// Extra lines rendered beyond the visible area.
+    pub overscan_lines: usize,
+    /// Encoding label for newly opened files, or "auto" to detect it.
+    pub default_encoding: String,
+    /// Bytes shown on each row of the hex view.
+    pub hex_bytes_per_row: usize,
+    /// Show hex view offsets in decimal rather than hex.
+    pub hex_decimal_offsets: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    /// Files up to this many bytes are fully line-indexed; larger ones use a sparse index.
+    pub full_index_threshold_bytes: usize,
+    /// Spacing of sparse index checkpoints, in bytes.
+    pub sparse_sample_size_bytes: usize,
+    /// Bytes each search thread scans per batch.
+    pub search_batch_size_bytes: usize,
+    /// Search threads; 0 uses every core.
+    pub search_threads: usize,
+    /// Longest match Replace All holds in memory; longer matches fail the run.
+    pub max_replace_match_mb: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Search results held in memory at a time; Next/Previous load further pages.
+    pub search_page_size: usize,
+    /// Results buffered between search threads and the UI before threads wait.
+    pub search_channel_capacity: usize,
+    /// Matches listed per file by Find in Files.
+    pub find_in_files_max_matches: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditingConfig {
+    /// Keep the previous content as `<name>.bak` when saving over a file.
+    pub backup_on_save: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            dark_mode: true,
+            wrap_mode: false,
+            show_line_numbers: true,
+            overscan_lines: 2,
+            default_encoding: "auto".to_string(),
+            hex_bytes_per_row: 16,
+            hex_decimal_offsets: false,
+        }
+    }
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            full_index_threshold_bytes: DEFAULT_FULL_INDEX_THRESHOLD,
+            sparse_sample_size_bytes: DEFAULT_SPARSE_SAMPLE_SIZE,
+            search_batch_size_bytes: DEFAULT_BATCH_SIZE,
+            search_threads: 0,
+            max_replace_match_mb: StreamLimits::default().max_match_len / MB,
+        }
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            search_page_size: 1000,
+            search_channel_capacity: 10_000,
+            find_in_files_max_matches: 1000,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Self> {
+        Ok(persist::load::<Self>(CONFIG_FILE)?.sanitized())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save(CONFIG_FILE, self)
+    }
+
+    /// Clamps values into ranges the viewer can work with.
+    pub fn sanitized(mut self) -> Self {
+        self.ui.font_size = self.ui.font_size.clamp(8.0, 32.0);
+        self.ui.overscan_lines = self.ui.overscan_lines.min(100);
+        self.ui.hex_bytes_per_row = self.ui.hex_bytes_per_row.clamp(1, MAX_HEX_BYTES_PER_ROW);
+        self.performance.sparse_sample_size_bytes =
+            self.performance.sparse_sample_size_bytes.max(1);
+        self.performance.search_batch_size_bytes = self.performance.search_batch_size_bytes.max(1);
+        self.performance.max_replace_match_mb = self.performance.max_replace_match_mb.max(1);
+        self.memory.search_page_size = self.memory.search_page_size.max(1);
+        self.memory.search_channel_capacity = self.memory.search_channel_capacity.max(1);
+        self.memory.find_in_files_max_matches = self.memory.find_in_files_max_matches.max(1);
+        self
+    }
+
+    pub fn full_index_threshold(&self) -> usize {
+        self.performance.full_index_threshold_bytes
+    }
+
+    pub fn sparse_sample_size(&self) -> usize {
+        self.performance.sparse_sample_size_bytes
+    }
+
+    pub fn search_batch_size(&self) -> usize {
+        self.performance.search_batch_size_bytes
+    }
+
+    /// Replace All's stream limits, with the configured match cap.
+    pub fn replace_limits(&self) -> StreamLimits {
+        StreamLimits {
+            max_match_len: self.performance.max_replace_match_mb * MB,
+            ..Default::default()
+        }
+    }
+
+    pub fn search_threads(&self) -> Option<usize> {
+        (self.performance.search_threads > 0).then_some(self.performance.search_threads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_file_uses_defaults() {
+        let config: Config = toml::from_str("[ui]\nfont_size = 18.0\n").unwrap();
+        assert_eq!(config.ui.font_size, 18.0);
+        assert!(config.ui.dark_mode);
+        assert_eq!(config.performance, PerformanceConfig::default());
+        assert_eq!(config.memory.search_page_size, 1000);
+
+        // The defaults are exactly the core's own
+        assert_eq!(config.full_index_threshold(), DEFAULT_FULL_INDEX_THRESHOLD);
+        assert_eq!(config.sparse_sample_size(), DEFAULT_SPARSE_SAMPLE_SIZE);
+        assert_eq!(config.search_batch_size(), DEFAULT_BATCH_SIZE);
+        assert_eq!(config.replace_limits(), StreamLimits::default());
+    }
+
+    #[test]
+    fn test_sanitized_clamps() {
+        let mut config = Config::default();
+        config.ui.font_size = 100.0;
+        config.memory.search_page_size = 0;
+        config.ui.hex_bytes_per_row = 0;
+        let config = config.sanitized();
+        assert_eq!(config.ui.font_size, 32.0);
+        assert_eq!(config.memory.search_page_size, 1);
+        assert_eq!(config.ui.hex_bytes_per_row, 1);
+    }
+}
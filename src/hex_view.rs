@@ -0,0 +1,174 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+// Bytes between the extra gaps that group the hex column
+const GROUP_SIZE: usize = 8;
+// Narrowest offset column, so small files line up with large ones
+const MIN_OFFSET_DIGITS: usize = 8;
+
+/// Column layout of the hex view, where each row reads
+/// `offset  hh hh hh ...  ascii`, with an extra space after every eight bytes.
+/// Columns count monospace characters from the start of the row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HexLayout {
+    pub bytes_per_row: usize,
+    pub decimal_offsets: bool,
+    offset_digits: usize,
+}
+
+impl HexLayout {
+    /// A layout whose offset column fits every offset of a `file_len` byte file.
+    pub fn new(bytes_per_row: usize, decimal_offsets: bool, file_len: usize) -> Self {
+        let offset_digits = if decimal_offsets {
+            file_len.to_string().len()
+        } else {
+            format!("{:X}", file_len).len()
+        };
+        Self {
+            bytes_per_row: bytes_per_row.max(1),
+            decimal_offsets,
+            offset_digits: offset_digits.max(MIN_OFFSET_DIGITS),
+        }
+    }
+
+    /// Number of rows showing a `file_len` byte file.
+    pub fn rows(&self, file_len: usize) -> usize {
+        file_len.div_ceil(self.bytes_per_row)
+    }
+
+    /// The row holding the byte at `offset`.
+    pub fn row_of(&self, offset: usize) -> usize {
+        offset / self.bytes_per_row
+    }
+
+    /// File offset of the first byte of `row`.
+    pub fn row_start(&self, row: usize) -> usize {
+        row.saturating_mul(self.bytes_per_row)
+    }
+
+    /// Column of the first hex digit of byte `index` in a row; `bytes_per_row` gives
+    /// the column just past the hex bytes.
+    pub fn hex_column(&self, index: usize) -> usize {
+        self.offset_digits + 2 + index * 3 + index / GROUP_SIZE
+    }
+
+    /// Column of the character showing byte `index` in a row.
+    pub fn ascii_column(&self, index: usize) -> usize {
+        self.hex_column(self.bytes_per_row) + 1 + index
+    }
+
+    /// Text of the row starting at `offset` that shows `bytes`, padded so that a
+    /// short last row keeps its characters in the ASCII column.
+    pub fn row_text(&self, offset: usize, bytes: &[u8]) -> String {
+        let mut text = String::with_capacity(self.ascii_column(self.bytes_per_row));
+        if self.decimal_offsets {
+            let _ = write!(text, "{:0width$}  ", offset, width = self.offset_digits);
+        } else {
+            let _ = write!(text, "{:0width$X}  ", offset, width = self.offset_digits);
+        }
+        for index in 0..self.bytes_per_row {
+            match bytes.get(index) {
+                Some(b) => {
+                    let _ = write!(text, "{:02X} ", b);
+                }
+                None => text.push_str("   "),
+            }
+            if (index + 1) % GROUP_SIZE == 0 {
+                text.push(' ');
+            }
+        }
+        text.push(' ');
+        text.extend(bytes.iter().map(|&b| ascii_char(b)));
+        text
+    }
+
+    /// The position between bytes nearest to `column` (a fractional character
+    /// column), from 0 before the first byte of the row to `bytes_per_row` after the
+    /// last. Both the hex and the ASCII column map to byte positions.
+    pub fn position_at(&self, column: f32) -> usize {
+        let ascii_start = self.ascii_column(0) as f32;
+        let index = if column >= ascii_start - 0.5 {
+            (column - ascii_start + 0.5).max(0.0) as usize
+        } else {
+            // Past the middle of a byte's two digits puts the position after it
+            (0..self.bytes_per_row)
+                .take_while(|&i| column >= self.hex_column(i) as f32 + 1.0)
+                .count()
+        };
+        index.min(self.bytes_per_row)
+    }
+
+    /// Column ranges highlighting the bytes `range` (relative to the row start) in
+    /// the hex and the ASCII column.
+    pub fn highlight_columns(&self, range: Range<usize>) -> [Range<usize>; 2] {
+        let end = range.end.min(self.bytes_per_row);
+        let start = range.start.min(end);
+        if start == end {
+            return [0..0, 0..0];
+        }
+        [
+            self.hex_column(start)..self.hex_column(end - 1) + 2,
+            self.ascii_column(start)..self.ascii_column(end),
+        ]
+    }
+}
+
+/// The character the ASCII column shows for `byte`: printable ASCII as itself,
+/// everything else as a dot.
+pub fn ascii_char(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_text() {
+        let layout = HexLayout::new(16, false, 0x120);
+        let row = layout.row_text(0x110, b"Hi\x00\xff");
+        assert_eq!(
+            row,
+            format!("00000110  48 69 00 FF {}  Hi..", " ".repeat(12 * 3 + 1))
+        );
+        assert_eq!(row.find("Hi.."), Some(layout.ascii_column(0)));
+
+        // Offsets widen for large files, and can be decimal
+        let layout = HexLayout::new(4, true, 12_345_678_901);
+        assert_eq!(
+            layout.row_text(20, b"abcd"),
+            "00000000020  61 62 63 64  abcd"
+        );
+        assert_eq!(layout.rows(9), 3);
+        assert_eq!(layout.row_of(9), 2);
+        assert_eq!(layout.row_start(2), 8);
+    }
+
+    #[test]
+    fn test_position_and_highlight_columns() {
+        let layout = HexLayout::new(16, false, 100);
+        // "00000000  00 11 22 ..." puts byte 0's digits at columns 10 and 11
+        assert_eq!(layout.hex_column(0), 10);
+        assert_eq!(layout.position_at(0.0), 0);
+        assert_eq!(layout.position_at(10.5), 0);
+        assert_eq!(layout.position_at(11.2), 1);
+        // The gap after eight bytes
+        assert_eq!(layout.hex_column(8), 35);
+        assert_eq!(layout.position_at(34.5), 8);
+        assert_eq!(layout.position_at(59.0), 16);
+        // The ASCII column
+        assert_eq!(layout.ascii_column(0), 61);
+        assert_eq!(layout.position_at(61.2), 0);
+        assert_eq!(layout.position_at(63.6), 3);
+        assert_eq!(layout.position_at(200.0), 16);
+
+        assert_eq!(layout.highlight_columns(1..3), [13..18, 62..64]);
+        assert_eq!(layout.highlight_columns(7..9), [31..37, 68..70]);
+        assert_eq!(layout.highlight_columns(4..4), [0..0, 0..0]);
+        assert_eq!(layout.highlight_columns(15..40), [56..58, 76..77]);
+    }
+}
@@ -0,0 +1,628 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Seconds to wait for the next key of a multi-key sequence such as `g g`.
+const SEQUENCE_TIMEOUT: f64 = 1.0;
+
+macro_rules! actions {
+    ($($variant:ident => ($id:literal, $label:literal),)*) => {
+        /// Every command that can be bound to a key.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum Action {
+            $($variant,)*
+        }
+
+        impl Action {
+            pub const ALL: &'static [Action] = &[$(Action::$variant,)*];
+
+            /// Stable identifier used in the config file.
+            pub fn id(self) -> &'static str {
+                match self {
+                    $(Action::$variant => $id,)*
+                }
+            }
+
+            pub fn label(self) -> &'static str {
+                match self {
+                    $(Action::$variant => $label,)*
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    CommandPalette => ("command_palette", "Command Palette"),
+    OpenFile => ("open_file", "Open File"),
+    Save => ("save", "Save"),
+    Undo => ("undo", "Undo Replacement"),
+    Redo => ("redo", "Redo Replacement"),
+    TogglePendingEditsPanel => ("toggle_pending_edits_panel", "Toggle Pending Replacements Panel"),
+    ClearRecentFiles => ("clear_recent_files", "Clear Recent Files"),
+    ShowFileInfo => ("show_file_info", "File Info"),
+    Preferences => ("preferences", "Preferences"),
+    Exit => ("exit", "Exit"),
+    Find => ("find", "Find"),
+    Replace => ("replace", "Replace"),
+    FocusSearch => ("focus_search", "Focus Search Box"),
+    FindNext => ("find_next", "Next Match"),
+    FindPrevious => ("find_previous", "Previous Match"),
+    FindInFiles => ("find_in_files", "Find in Files"),
+    ExportMatches => ("export_matches", "Export Matches"),
+    ManageSavedSearches => ("manage_saved_searches", "Manage Saved Searches"),
+    RunSavedSearch1 => ("run_saved_search_1", "Run Saved Search 1"),
+    RunSavedSearch2 => ("run_saved_search_2", "Run Saved Search 2"),
+    RunSavedSearch3 => ("run_saved_search_3", "Run Saved Search 3"),
+    RunSavedSearch4 => ("run_saved_search_4", "Run Saved Search 4"),
+    RunSavedSearch5 => ("run_saved_search_5", "Run Saved Search 5"),
+    RunSavedSearch6 => ("run_saved_search_6", "Run Saved Search 6"),
+    RunSavedSearch7 => ("run_saved_search_7", "Run Saved Search 7"),
+    RunSavedSearch8 => ("run_saved_search_8", "Run Saved Search 8"),
+    RunSavedSearch9 => ("run_saved_search_9", "Run Saved Search 9"),
+    GoToLine => ("go_to_line", "Go to Line"),
+    ToggleWrap => ("toggle_wrap", "Toggle Word Wrap"),
+    ToggleLineNumbers => ("toggle_line_numbers", "Toggle Line Numbers"),
+    ToggleHexView => ("toggle_hex_view", "Toggle Hex View"),
+    ToggleDarkMode => ("toggle_dark_mode", "Toggle Dark Mode"),
+    ToggleTimeline => ("toggle_timeline", "Toggle Match Timeline"),
+    SelectEncoding => ("select_encoding", "Select Encoding"),
+    ToggleTail => ("toggle_tail", "Toggle Tail Mode"),
+    ToggleBookmark => ("toggle_bookmark", "Toggle Bookmark"),
+    NextBookmark => ("next_bookmark", "Next Bookmark"),
+    PreviousBookmark => ("previous_bookmark", "Previous Bookmark"),
+    ToggleBookmarksPanel => ("toggle_bookmarks_panel", "Toggle Bookmarks Panel"),
+    ClearBookmarks => ("clear_bookmarks", "Clear Bookmarks"),
+    MoveLeft => ("move_left", "Move Caret Left"),
+    MoveRight => ("move_right", "Move Caret Right"),
+    MoveUp => ("move_up", "Move Caret Up"),
+    MoveDown => ("move_down", "Move Caret Down"),
+    MoveWordLeft => ("move_word_left", "Move Caret to Previous Word"),
+    MoveWordRight => ("move_word_right", "Move Caret to Next Word"),
+    MoveLineStart => ("move_line_start", "Move Caret to Line Start"),
+    MoveLineEnd => ("move_line_end", "Move Caret to Line End"),
+    SelectLeft => ("select_left", "Extend Selection Left"),
+    SelectRight => ("select_right", "Extend Selection Right"),
+    SelectUp => ("select_up", "Extend Selection Up"),
+    SelectDown => ("select_down", "Extend Selection Down"),
+    SelectWordLeft => ("select_word_left", "Extend Selection to Previous Word"),
+    SelectWordRight => ("select_word_right", "Extend Selection to Next Word"),
+    SelectLineStart => ("select_line_start", "Extend Selection to Line Start"),
+    SelectLineEnd => ("select_line_end", "Extend Selection to Line End"),
+    SelectAll => ("select_all", "Select All"),
+    Copy => ("copy", "Copy Selection"),
+    ExportRange => ("export_range", "Copy or Export Range"),
+    ScrollDown => ("scroll_down", "Scroll Down One Line"),
+    ScrollUp => ("scroll_up", "Scroll Up One Line"),
+    PageDown => ("page_down", "Page Down"),
+    PageUp => ("page_up", "Page Up"),
+    GoToTop => ("go_to_top", "Go to Top"),
+    GoToBottom => ("go_to_bottom", "Go to Bottom"),
+}
+
+impl Action {
+    /// The actions running the first nine saved searches, in order.
+    pub const RUN_SAVED_SEARCH: [Action; 9] = [
+        Action::RunSavedSearch1,
+        Action::RunSavedSearch2,
+        Action::RunSavedSearch3,
+        Action::RunSavedSearch4,
+        Action::RunSavedSearch5,
+        Action::RunSavedSearch6,
+        Action::RunSavedSearch7,
+        Action::RunSavedSearch8,
+        Action::RunSavedSearch9,
+    ];
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| a.id() == id)
+    }
+
+    /// Index of the saved search this action runs.
+    pub fn saved_search_index(self) -> Option<usize> {
+        Self::RUN_SAVED_SEARCH.iter().position(|&a| a == self)
+    }
+
+    /// Caret, selection, clipboard and undo actions, which a focused text field handles itself.
+    fn defers_to_text_fields(self) -> bool {
+        matches!(
+            self,
+            Action::MoveLeft
+                | Action::MoveRight
+                | Action::MoveUp
+                | Action::MoveDown
+                | Action::MoveWordLeft
+                | Action::MoveWordRight
+                | Action::MoveLineStart
+                | Action::MoveLineEnd
+                | Action::SelectLeft
+                | Action::SelectRight
+                | Action::SelectUp
+                | Action::SelectDown
+                | Action::SelectWordLeft
+                | Action::SelectWordRight
+                | Action::SelectLineStart
+                | Action::SelectLineEnd
+                | Action::SelectAll
+                | Action::Copy
+                | Action::Undo
+                | Action::Redo
+        )
+    }
+}
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("Ctrl+Shift+P", Action::CommandPalette),
+    ("Ctrl+O", Action::OpenFile),
+    ("Ctrl+S", Action::Save),
+    ("Ctrl+Z", Action::Undo),
+    ("Ctrl+Y", Action::Redo),
+    ("Ctrl+Shift+Z", Action::Redo),
+    ("Ctrl+Comma", Action::Preferences),
+    ("Ctrl+F", Action::Find),
+    ("Ctrl+R", Action::Replace),
+    ("F3", Action::FindNext),
+    ("Shift+F3", Action::FindPrevious),
+    ("Alt+1", Action::RunSavedSearch1),
+    ("Alt+2", Action::RunSavedSearch2),
+    ("Alt+3", Action::RunSavedSearch3),
+    ("Alt+4", Action::RunSavedSearch4),
+    ("Alt+5", Action::RunSavedSearch5),
+    ("Alt+6", Action::RunSavedSearch6),
+    ("Alt+7", Action::RunSavedSearch7),
+    ("Alt+8", Action::RunSavedSearch8),
+    ("Alt+9", Action::RunSavedSearch9),
+    ("Ctrl+Shift+F", Action::FindInFiles),
+    ("Ctrl+G", Action::GoToLine),
+    ("Alt+Z", Action::ToggleWrap),
+    ("Ctrl+Shift+H", Action::ToggleHexView),
+    ("Ctrl+T", Action::ToggleTail),
+    ("Ctrl+F2", Action::ToggleBookmark),
+    ("F2", Action::NextBookmark),
+    ("Shift+F2", Action::PreviousBookmark),
+    ("ArrowLeft", Action::MoveLeft),
+    ("ArrowRight", Action::MoveRight),
+    ("ArrowUp", Action::MoveUp),
+    ("ArrowDown", Action::MoveDown),
+    ("Ctrl+ArrowLeft", Action::MoveWordLeft),
+    ("Ctrl+ArrowRight", Action::MoveWordRight),
+    ("Home", Action::MoveLineStart),
+    ("End", Action::MoveLineEnd),
+    ("Shift+ArrowLeft", Action::SelectLeft),
+    ("Shift+ArrowRight", Action::SelectRight),
+    ("Shift+ArrowUp", Action::SelectUp),
+    ("Shift+ArrowDown", Action::SelectDown),
+    ("Ctrl+Shift+ArrowLeft", Action::SelectWordLeft),
+    ("Ctrl+Shift+ArrowRight", Action::SelectWordRight),
+    ("Shift+Home", Action::SelectLineStart),
+    ("Shift+End", Action::SelectLineEnd),
+    ("Ctrl+A", Action::SelectAll),
+    ("Ctrl+Home", Action::GoToTop),
+    ("Ctrl+End", Action::GoToBottom),
+];
+
+const VIM_BINDINGS: &[(&str, Action)] = &[
+    ("H", Action::MoveLeft),
+    ("L", Action::MoveRight),
+    ("W", Action::MoveWordRight),
+    ("B", Action::MoveWordLeft),
+    ("0", Action::MoveLineStart),
+    ("J", Action::ScrollDown),
+    ("K", Action::ScrollUp),
+    ("Ctrl+D", Action::PageDown),
+    ("Ctrl+U", Action::PageUp),
+    ("G G", Action::GoToTop),
+    ("Shift+G", Action::GoToBottom),
+    ("N", Action::FindNext),
+    ("Shift+N", Action::FindPrevious),
+    ("Slash", Action::FocusSearch),
+];
+
+const LESS_BINDINGS: &[(&str, Action)] = &[
+    ("J", Action::ScrollDown),
+    ("K", Action::ScrollUp),
+    ("Space", Action::PageDown),
+    ("F", Action::PageDown),
+    ("B", Action::PageUp),
+    ("G", Action::GoToTop),
+    ("Shift+G", Action::GoToBottom),
+    ("N", Action::FindNext),
+    ("Shift+N", Action::FindPrevious),
+    ("Slash", Action::FocusSearch),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    /// Adds `h`/`l`, `w`/`b`, `0`, `j`/`k`, `gg`/`G`, `n`/`N` and `/` on top of the default bindings.
+    Vim,
+    /// Adds `j`/`k`, Space/`f`/`b`, `g`/`G`, `n`/`N` and `/` on top of the default bindings.
+    Less,
+}
+
+impl KeymapPreset {
+    pub const ALL: [KeymapPreset; 3] = [Self::Default, Self::Vim, Self::Less];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Vim => "Vim",
+            Self::Less => "less",
+        }
+    }
+
+    fn bindings(self) -> impl Iterator<Item = &'static (&'static str, Action)> {
+        let extra = match self {
+            Self::Default => &[][..],
+            Self::Vim => VIM_BINDINGS,
+            Self::Less => LESS_BINDINGS,
+        };
+        DEFAULT_BINDINGS.iter().chain(extra)
+    }
+}
+
+/// The `[keymap]` section of the config file.
+///
+/// `bindings` maps an action ID to the keys that replace the preset's bindings for it:
+/// chords such as `Ctrl+Shift+F`, sequences separated by spaces (`G G`), alternatives
+/// separated by commas, or `none` to unbind the action.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeymapConfig {
+    pub preset: KeymapPreset,
+    pub bindings: BTreeMap<String, String>,
+}
+
+impl KeymapConfig {
+    /// Checks every override, returning one message per invalid binding.
+    pub fn errors(&self) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter_map(|(id, keys)| {
+                if Action::from_id(id).is_none() {
+                    return Some(format!("Unknown action: {}", id));
+                }
+                parse_bindings(keys).err().map(|e| format!("{}: {}", id, e))
+            })
+            .collect()
+    }
+}
+
+/// A key together with the modifiers that must be held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub key: egui::Key,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyChord {
+    fn from_event(key: egui::Key, modifiers: egui::Modifiers) -> Self {
+        Self {
+            key,
+            // `command` is Ctrl on Windows/Linux and Cmd on macOS
+            ctrl: modifiers.command,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+        }
+    }
+
+    fn matches(&self, pressed: &KeyChord) -> bool {
+        // Punctuation often needs Shift on non-US layouts, so Shift is not compared for it
+        let shift_matches = self.shift == pressed.shift || is_punctuation(self.key);
+        self.key == pressed.key
+            && self.ctrl == pressed.ctrl
+            && self.alt == pressed.alt
+            && shift_matches
+    }
+
+    /// Chords that still fire while a text field has keyboard focus.
+    fn works_while_typing(&self) -> bool {
+        let name = self.key.name();
+        self.ctrl || self.alt || (name.starts_with('F') && name.len() > 1)
+    }
+}
+
+fn is_punctuation(key: egui::Key) -> bool {
+    let symbol = key.symbol_or_name();
+    symbol.len() == 1 && !symbol.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chord = KeyChord {
+            key: egui::Key::Escape,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "cmd" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                _ if key.is_some() => return Err(format!("More than one key in '{}'", s)),
+                _ => {
+                    let upper = part.to_ascii_uppercase();
+                    let name = if part.len() == 1 {
+                        upper.as_str()
+                    } else {
+                        part
+                    };
+                    key = Some(
+                        egui::Key::from_name(name)
+                            .ok_or_else(|| format!("Unknown key '{}'", part))?,
+                    );
+                }
+            }
+        }
+        chord.key = key.ok_or_else(|| format!("No key in '{}'", s))?;
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// Parses comma-separated alternatives, each a space-separated chord sequence.
+fn parse_bindings(keys: &str) -> Result<Vec<Vec<KeyChord>>, String> {
+    if keys.trim().is_empty() || keys.trim().eq_ignore_ascii_case("none") {
+        return Ok(Vec::new());
+    }
+    keys.split(',')
+        .map(str::trim)
+        .filter(|alt| !alt.is_empty())
+        .map(|alt| alt.split_whitespace().map(KeyChord::from_str).collect())
+        .collect()
+}
+
+fn format_sequence(sequence: &[KeyChord]) -> String {
+    sequence
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+enum Feed {
+    Fired(Action),
+    Pending,
+    NoMatch,
+}
+
+/// Resolves key presses into actions.
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyChord>, Action)>,
+    pending: Vec<KeyChord>,
+    pending_since: f64,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&KeymapConfig::default())
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap from a preset plus overrides; invalid overrides, as listed by
+    /// `KeymapConfig::errors`, are skipped.
+    pub fn from_config(config: &KeymapConfig) -> Self {
+        let mut bindings: Vec<(Vec<KeyChord>, Action)> = config
+            .preset
+            .bindings()
+            .filter_map(|(keys, action)| Some((parse_bindings(keys).ok()?.pop()?, *action)))
+            .collect();
+
+        for (id, keys) in &config.bindings {
+            let (Some(action), Ok(sequences)) = (Action::from_id(id), parse_bindings(keys)) else {
+                continue;
+            };
+            bindings.retain(|(_, a)| *a != action);
+            bindings.extend(sequences.into_iter().map(|seq| (seq, action)));
+        }
+
+        Self {
+            bindings,
+            pending: Vec::new(),
+            pending_since: 0.0,
+        }
+    }
+
+    /// Keys bound to `action`, for display next to menu items.
+    pub fn shortcut_text(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(seq, _)| format_sequence(seq))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn feed(&mut self, chord: KeyChord, now: f64) -> Feed {
+        if now - self.pending_since > SEQUENCE_TIMEOUT {
+            self.pending.clear();
+        }
+        let retry = !self.pending.is_empty();
+        self.pending.push(chord);
+        self.pending_since = now;
+
+        let pending = &self.pending;
+        let is_prefix = |seq: &[KeyChord]| {
+            seq.len() >= pending.len() && seq.iter().zip(pending).all(|(b, p)| b.matches(p))
+        };
+        if let Some((_, action)) = self
+            .bindings
+            .iter()
+            .find(|(seq, _)| seq.len() == pending.len() && is_prefix(seq))
+        {
+            let action = *action;
+            self.pending.clear();
+            return Feed::Fired(action);
+        }
+        if self.bindings.iter().any(|(seq, _)| is_prefix(seq)) {
+            return Feed::Pending;
+        }
+
+        self.pending.clear();
+        if retry {
+            // The sequence broke off; the key may still start or be a binding by itself
+            self.feed(chord, now)
+        } else {
+            Feed::NoMatch
+        }
+    }
+
+    /// Consumes bound key presses from this frame's input and returns their actions.
+    /// Plain keys are left alone while a text field has focus.
+    pub fn process(&mut self, ctx: &egui::Context) -> Vec<Action> {
+        let typing = ctx.wants_keyboard_input();
+        ctx.input_mut(|input| {
+            let now = input.time;
+            let mut fired = Vec::new();
+            input.events.retain(|event| {
+                let egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } = event
+                else {
+                    return true;
+                };
+                let chord = KeyChord::from_event(*key, *modifiers);
+                if typing && !chord.works_while_typing() {
+                    return true;
+                }
+                match self.feed(chord, now) {
+                    Feed::Fired(action) if typing && action.defers_to_text_fields() => true,
+                    Feed::Fired(action) => {
+                        fired.push(action);
+                        false
+                    }
+                    Feed::Pending => false,
+                    Feed::NoMatch => true,
+                }
+            });
+            fired
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    fn fire(keymap: &mut Keymap, keys: &[&str]) -> Vec<Action> {
+        keys.iter()
+            .filter_map(|k| match keymap.feed(chord(k), 0.0) {
+                Feed::Fired(action) => Some(action),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_format_chords() {
+        let c = chord("ctrl+shift+f");
+        assert!(c.ctrl && c.shift && !c.alt);
+        assert_eq!(c.key, egui::Key::F);
+        assert_eq!(c.to_string(), "Ctrl+Shift+F");
+        assert_eq!(chord("/").key, egui::Key::Slash);
+        assert!("Ctrl+Nope".parse::<KeyChord>().is_err());
+        assert!("Ctrl+A+B".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_preset_bindings_parse() {
+        for preset in KeymapPreset::ALL {
+            for (keys, action) in preset.bindings() {
+                assert!(parse_bindings(keys).is_ok(), "{:?}: {}", action, keys);
+            }
+        }
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            fire(
+                &mut keymap,
+                &["Shift+ArrowLeft", "ArrowLeft", "Ctrl+Shift+End"]
+            ),
+            vec![Action::SelectLeft, Action::MoveLeft]
+        );
+        assert_eq!(
+            fire(&mut keymap, &["Alt+3", "3"]),
+            vec![Action::RunSavedSearch3]
+        );
+        assert_eq!(Action::RunSavedSearch3.saved_search_index(), Some(2));
+        assert_eq!(Action::Find.saved_search_index(), None);
+    }
+
+    #[test]
+    fn test_vim_sequences() {
+        let mut keymap = Keymap::from_config(&KeymapConfig {
+            preset: KeymapPreset::Vim,
+            ..Default::default()
+        });
+        assert_eq!(
+            fire(&mut keymap, &["G", "G", "Shift+G", "N", "Ctrl+F"]),
+            vec![
+                Action::GoToTop,
+                Action::GoToBottom,
+                Action::FindNext,
+                Action::Find
+            ]
+        );
+        // A broken-off sequence still lets the second key fire on its own
+        assert_eq!(fire(&mut keymap, &["G", "J"]), vec![Action::ScrollDown]);
+    }
+
+    #[test]
+    fn test_overrides_replace_preset_bindings() {
+        let mut config = KeymapConfig::default();
+        config
+            .bindings
+            .insert("find".to_string(), "Ctrl+K, F4".to_string());
+        config
+            .bindings
+            .insert("save".to_string(), "none".to_string());
+        assert!(config.errors().is_empty());
+
+        let mut keymap = Keymap::from_config(&config);
+        assert_eq!(keymap.shortcut_text(Action::Find), "Ctrl+K, F4");
+        assert_eq!(keymap.shortcut_text(Action::Save), "");
+        assert_eq!(fire(&mut keymap, &["Ctrl+F", "F4"]), vec![Action::Find]);
+
+        config
+            .bindings
+            .insert("bogus".to_string(), "F5".to_string());
+        config
+            .bindings
+            .insert("open_file".to_string(), "Ctrl+Nope".to_string());
+        assert_eq!(config.errors().len(), 2);
+        // Valid overrides still apply alongside invalid ones
+        let keymap = Keymap::from_config(&config);
+        assert_eq!(keymap.shortcut_text(Action::Find), "Ctrl+K, F4");
+    }
+}
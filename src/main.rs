@@ -1,4 +1,13 @@
 mod app;
+mod bookmarks;
+mod command_palette;
+mod config;
+mod hex_view;
+mod keymap;
+mod pending_edits;
+mod persist;
+mod search_history;
+mod session;
 
 use app::TextViewerApp;
 use eframe::egui;
@@ -14,6 +23,6 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Large Text Viewer",
         options,
-        Box::new(|_cc| Ok(Box::new(TextViewerApp::default()))),
+        Box::new(|_cc| Ok(Box::new(TextViewerApp::new()))),
     )
 }
@@ -0,0 +1,290 @@
+use std::ops::Range;
+
+/// A replacement queued by Replace, applied to the file on save.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingReplacement {
+    pub offset: usize,
+    pub old_len: usize,
+    pub new_text: String,
+}
+
+impl PendingReplacement {
+    fn old_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.old_len
+    }
+}
+
+/// One span of a line as it will read after saving: a view over the piece table of
+/// the file plus the pending replacements.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece<'a> {
+    /// Unchanged bytes of the file.
+    File(Range<usize>),
+    /// `text` standing in for the file bytes `old`. An edit spanning several lines
+    /// shows its text on the first and hides the rest of its bytes on the others.
+    Edit { old: Range<usize>, text: &'a str },
+}
+
+/// A change to the queue, kept so it can be undone and redone.
+#[derive(Clone, Debug)]
+enum EditOp {
+    Add(PendingReplacement),
+    Remove(usize, PendingReplacement),
+}
+
+/// The queued replacements in the order they were made, with undo and redo.
+#[derive(Default)]
+pub struct PendingEdits {
+    edits: Vec<PendingReplacement>,
+    undo_stack: Vec<EditOp>,
+    redo_stack: Vec<EditOp>,
+}
+
+impl PendingEdits {
+    pub fn as_slice(&self) -> &[PendingReplacement] {
+        &self.edits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn push(&mut self, replacement: PendingReplacement) {
+        self.edits.push(replacement.clone());
+        self.record(EditOp::Add(replacement));
+    }
+
+    /// Removes the replacement at `index`, returning it.
+    pub fn remove(&mut self, index: usize) -> Option<PendingReplacement> {
+        if index >= self.edits.len() {
+            return None;
+        }
+        let replacement = self.edits.remove(index);
+        self.record(EditOp::Remove(index, replacement.clone()));
+        Some(replacement)
+    }
+
+    /// Reverts the last add or remove, returning the replacement it affected.
+    pub fn undo(&mut self) -> Option<PendingReplacement> {
+        let op = self.undo_stack.pop()?;
+        let replacement = match &op {
+            EditOp::Add(replacement) => {
+                // Undo runs newest first, so the added entry is still the last one
+                self.edits.pop();
+                replacement.clone()
+            }
+            EditOp::Remove(index, replacement) => {
+                self.edits.insert(*index, replacement.clone());
+                replacement.clone()
+            }
+        };
+        self.redo_stack.push(op);
+        Some(replacement)
+    }
+
+    /// Reapplies the last undone add or remove, returning the replacement it affected.
+    pub fn redo(&mut self) -> Option<PendingReplacement> {
+        let op = self.redo_stack.pop()?;
+        let replacement = match &op {
+            EditOp::Add(replacement) => {
+                self.edits.push(replacement.clone());
+                replacement.clone()
+            }
+            EditOp::Remove(index, replacement) => {
+                self.edits.remove(*index);
+                replacement.clone()
+            }
+        };
+        self.undo_stack.push(op);
+        Some(replacement)
+    }
+
+    /// Whether a queued replacement already covers part of `range`.
+    pub fn overlaps(&self, range: Range<usize>) -> bool {
+        self.edits.iter().any(|r| {
+            let old = r.old_range();
+            old.start < range.end.max(range.start + 1) && range.start < old.end.max(old.start + 1)
+        })
+    }
+
+    /// The replacements in file order, skipping any that overlap an earlier one.
+    pub fn applied(&self) -> Vec<&PendingReplacement> {
+        let mut sorted: Vec<&PendingReplacement> = self.edits.iter().collect();
+        sorted.sort_by_key(|r| r.offset);
+        let mut end = 0;
+        sorted.retain(|r| {
+            let keep = r.offset >= end;
+            if keep {
+                end = r.offset + r.old_len;
+            }
+            keep
+        });
+        sorted
+    }
+
+    /// Drops every replacement along with the undo history, e.g. once they are saved.
+    pub fn clear(&mut self) {
+        self.edits.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    fn record(&mut self, op: EditOp) {
+        self.undo_stack.push(op);
+        self.redo_stack.clear();
+    }
+}
+
+/// Splits the file bytes `range` into pieces, with `applied` (as returned by
+/// `PendingEdits::applied`) laid over them.
+pub fn pieces<'a>(applied: &[&'a PendingReplacement], range: Range<usize>) -> Vec<Piece<'a>> {
+    let mut pieces = Vec::new();
+    let mut pos = range.start;
+    let first = applied.partition_point(|r| r.offset + r.old_len.max(1) <= range.start);
+    for edit in &applied[first..] {
+        let old = edit.old_range();
+        let inside = if old.is_empty() {
+            old.start >= range.start && old.start < range.end
+        } else {
+            old.start < range.end
+        };
+        if !inside {
+            break;
+        }
+        let old = old.start.max(range.start)..old.end.min(range.end);
+        if pos < old.start {
+            pieces.push(Piece::File(pos..old.start));
+        }
+        let text = if edit.offset >= range.start {
+            edit.new_text.as_str()
+        } else {
+            ""
+        };
+        pos = old.end;
+        pieces.push(Piece::Edit { old, text });
+    }
+    if pos < range.end {
+        pieces.push(Piece::File(pos..range.end));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacement(offset: usize) -> PendingReplacement {
+        PendingReplacement {
+            offset,
+            old_len: 3,
+            new_text: "new".to_string(),
+        }
+    }
+
+    fn offsets(edits: &PendingEdits) -> Vec<usize> {
+        edits.as_slice().iter().map(|r| r.offset).collect()
+    }
+
+    #[test]
+    fn test_undo_redo_adds_and_removes() {
+        let mut edits = PendingEdits::default();
+        edits.push(replacement(10));
+        edits.push(replacement(20));
+        edits.push(replacement(30));
+        assert_eq!(edits.remove(0).map(|r| r.offset), Some(10));
+        assert_eq!(offsets(&edits), vec![20, 30]);
+
+        // Undoing the removal puts the entry back where it was
+        assert_eq!(edits.undo().map(|r| r.offset), Some(10));
+        assert_eq!(offsets(&edits), vec![10, 20, 30]);
+        assert_eq!(edits.undo().map(|r| r.offset), Some(30));
+        assert_eq!(offsets(&edits), vec![10, 20]);
+
+        assert_eq!(edits.redo().map(|r| r.offset), Some(30));
+        assert_eq!(edits.redo().map(|r| r.offset), Some(10));
+        assert_eq!(offsets(&edits), vec![20, 30]);
+        assert!(!edits.can_redo());
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let mut edits = PendingEdits::default();
+        edits.push(replacement(10));
+        edits.undo();
+        assert!(edits.is_empty());
+        assert!(edits.can_redo());
+
+        edits.push(replacement(40));
+        assert!(!edits.can_redo());
+        assert!(edits.redo().is_none());
+        assert_eq!(offsets(&edits), vec![40]);
+
+        edits.clear();
+        assert!(!edits.can_undo());
+    }
+
+    #[test]
+    fn test_pieces_overlay_edits() {
+        let mut edits = PendingEdits::default();
+        edits.push(replacement(14));
+        edits.push(replacement(2));
+        // Overlaps the edit at 2, so it is left out of the view
+        edits.push(replacement(4));
+        edits.push(PendingReplacement {
+            offset: 18,
+            old_len: 6,
+            new_text: "wrap".to_string(),
+        });
+        assert!(edits.overlaps(3..4));
+        assert!(!edits.overlaps(7..14));
+        let applied = edits.applied();
+        assert_eq!(
+            applied.iter().map(|r| r.offset).collect::<Vec<_>>(),
+            vec![2, 14, 18]
+        );
+
+        assert_eq!(
+            pieces(&applied, 0..20),
+            vec![
+                Piece::File(0..2),
+                Piece::Edit {
+                    old: 2..5,
+                    text: "new"
+                },
+                Piece::File(5..14),
+                Piece::Edit {
+                    old: 14..17,
+                    text: "new"
+                },
+                Piece::File(17..18),
+                Piece::Edit {
+                    old: 18..20,
+                    text: "wrap"
+                },
+            ]
+        );
+        // The next line only hides the rest of the edit that started above it
+        assert_eq!(
+            pieces(&applied, 20..30),
+            vec![
+                Piece::Edit {
+                    old: 20..24,
+                    text: ""
+                },
+                Piece::File(24..30),
+            ]
+        );
+    }
+}
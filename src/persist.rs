@@ -0,0 +1,52 @@
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Directory holding all persisted viewer state, e.g. `~/.config/large-text-viewer` on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("large-text-viewer"))
+}
+
+/// Loads a TOML file from the config directory. A missing file gives the default
+/// value; a file that cannot be read or parsed is an error.
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> Result<T> {
+    let Some(path) = config_dir().map(|dir| dir.join(file_name)) else {
+        return Ok(T::default());
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&path, &text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(anyhow::anyhow!("Cannot read {}: {}", path.display(), e)),
+    }
+}
+
+/// Parses the TOML `text` of the file at `path`, with a one-line error naming the file.
+fn parse<T: DeserializeOwned>(path: &Path, text: &str) -> Result<T> {
+    toml::from_str(text).map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e.message()))
+}
+
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> Result<()> {
+    let dir = config_dir().ok_or_else(|| anyhow::anyhow!("No config directory available"))?;
+    std::fs::create_dir_all(&dir)?;
+    let text = toml::to_string_pretty(value)?;
+    std::fs::write(dir.join(file_name), text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_history::SearchHistory;
+
+    #[test]
+    fn test_parse_reports_invalid_files() {
+        let path = Path::new("search_history.toml");
+        assert!(parse::<SearchHistory>(path, "").is_ok());
+
+        let err = parse::<SearchHistory>(path, "recent = 3").err().unwrap();
+        let message = err.to_string();
+        assert!(message.starts_with("Invalid search_history.toml: "));
+        assert!(!message.contains('\n'));
+    }
+}
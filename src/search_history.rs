@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::persist;
+
+const HISTORY_FILE: &str = "search_history.toml";
+const MAX_RECENT: usize = 50;
+
+/// A search query together with the flags it was run with.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub query: String,
+    pub use_regex: bool,
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Edit distance for approximate matching, if enabled.
+    #[serde(default)]
+    pub max_edits: Option<usize>,
+    /// `query` holds one pattern per line.
+    #[serde(default)]
+    pub multi_pattern: bool,
+    /// `query` is a hex byte pattern.
+    #[serde(default)]
+    pub hex_bytes: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: SearchQuery,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SearchHistory {
+    /// Most recent first.
+    #[serde(default)]
+    pub recent: Vec<SearchQuery>,
+    #[serde(default)]
+    pub saved: Vec<SavedSearch>,
+}
+
+impl SearchHistory {
+    pub fn load() -> anyhow::Result<Self> {
+        persist::load(HISTORY_FILE)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save(HISTORY_FILE, self)
+    }
+
+    /// Moves `query` to the front of the recent list, dropping any older duplicate.
+    pub fn record(&mut self, query: SearchQuery) {
+        if query.query.is_empty() {
+            return;
+        }
+        self.recent.retain(|q| *q != query);
+        self.recent.insert(0, query);
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Saves `query` under `name`, replacing an existing saved search with the same name.
+    pub fn save_search(&mut self, name: String, query: SearchQuery) {
+        if let Some(existing) = self.saved.iter_mut().find(|s| s.name == name) {
+            existing.query = query;
+        } else {
+            self.saved.push(SavedSearch { name, query });
+        }
+    }
+
+    pub fn remove_saved(&mut self, index: usize) {
+        if index < self.saved.len() {
+            self.saved.remove(index);
+        }
+    }
+
+    pub fn clear_recent(&mut self) {
+        self.recent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str) -> SearchQuery {
+        SearchQuery {
+            query: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_dedupes_and_orders() {
+        let mut history = SearchHistory::default();
+        history.record(query("a"));
+        history.record(query("b"));
+        history.record(query("a"));
+        history.record(query(""));
+
+        assert_eq!(history.recent, vec![query("a"), query("b")]);
+    }
+
+    #[test]
+    fn test_save_search_replaces_by_name() {
+        let mut history = SearchHistory::default();
+        history.save_search("errors".to_string(), query("ERROR"));
+        history.save_search("errors".to_string(), query("ERROR|FATAL"));
+
+        assert_eq!(history.saved.len(), 1);
+        assert_eq!(history.saved[0].query, query("ERROR|FATAL"));
+    }
+
+    #[test]
+    fn test_roundtrip_toml() {
+        let mut history = SearchHistory::default();
+        history.record(query("timeout"));
+        history.save_search("t".to_string(), query("timeout"));
+
+        let text = toml::to_string_pretty(&history).unwrap();
+        let parsed: SearchHistory = toml::from_str(&text).unwrap();
+        assert_eq!(parsed.recent, history.recent);
+        assert_eq!(parsed.saved, history.saved);
+    }
+}
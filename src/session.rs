@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::persist;
+use crate::search_history::SearchQuery;
+
+const SESSION_FILE: &str = "session.toml";
+const MAX_RECENT_FILES: usize = 20;
+
+/// View state of a file, restored when it is reopened. Bookmarks are kept separately
+/// in the bookmark store so they outlive the recent-files list.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileSession {
+    pub path: PathBuf,
+    /// Byte offset of the top visible line.
+    #[serde(default)]
+    pub scroll_offset: usize,
+    /// Encoding label, e.g. "UTF-8".
+    pub encoding: String,
+    pub wrap_mode: bool,
+    pub font_size: f32,
+    #[serde(default)]
+    pub search: Option<SearchQuery>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Reopen the most recent file on startup.
+    #[serde(default)]
+    pub reopen_last_session: bool,
+    /// Most recent first.
+    #[serde(default)]
+    pub recent_files: Vec<FileSession>,
+}
+
+impl Session {
+    pub fn load() -> anyhow::Result<Self> {
+        persist::load(SESSION_FILE)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save(SESSION_FILE, self)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&FileSession> {
+        self.recent_files.iter().find(|f| same_file(&f.path, path))
+    }
+
+    /// Moves `file` to the front of the recent files, replacing its previous state.
+    pub fn record(&mut self, file: FileSession) {
+        self.recent_files
+            .retain(|f| !same_file(&f.path, &file.path));
+        self.recent_files.insert(0, file);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn last_file(&self) -> Option<&FileSession> {
+        self.recent_files.first()
+    }
+
+    pub fn clear_recent(&mut self) {
+        self.recent_files.clear();
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (std::fs::canonicalize(a), std::fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, scroll_offset: usize) -> FileSession {
+        FileSession {
+            path: PathBuf::from(path),
+            scroll_offset,
+            encoding: "UTF-8".to_string(),
+            wrap_mode: false,
+            font_size: 14.0,
+            search: None,
+        }
+    }
+
+    #[test]
+    fn test_record_replaces_and_orders() {
+        let mut session = Session::default();
+        session.record(file("/logs/a.log", 0));
+        session.record(file("/logs/b.log", 0));
+        session.record(file("/logs/a.log", 4096));
+
+        let paths: Vec<_> = session.recent_files.iter().map(|f| &f.path).collect();
+        assert_eq!(
+            paths,
+            vec![Path::new("/logs/a.log"), Path::new("/logs/b.log")]
+        );
+        assert_eq!(
+            session
+                .get(Path::new("/logs/a.log"))
+                .map(|f| f.scroll_offset),
+            Some(4096)
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_toml() {
+        let mut session = Session {
+            reopen_last_session: true,
+            ..Default::default()
+        };
+        let mut state = file("/logs/a.log", 123);
+        state.search = Some(SearchQuery {
+            query: "timeout".to_string(),
+            ..Default::default()
+        });
+        session.record(state);
+
+        let text = toml::to_string_pretty(&session).unwrap();
+        let parsed: Session = toml::from_str(&text).unwrap();
+        assert!(parsed.reopen_last_session);
+        assert_eq!(parsed.recent_files, session.recent_files);
+    }
+}